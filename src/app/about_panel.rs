@@ -0,0 +1,103 @@
+use crate::data::provenance::Provenance;
+use crate::utils::time::now_seconds;
+use dioxus::prelude::*;
+
+/// A timestamp from the API, e.g. `2025-04-04T10:58:23.565156Z`, to the minute
+fn format_fetched_at(fetched_at: &str) -> String {
+    let minutes = fetched_at.get(..16).unwrap_or(fetched_at);
+    format!("{} UTC", minutes.replace('T', " "))
+}
+
+/// Where the loaded datasets came from, and whether they are out of date
+#[component]
+pub fn AboutPanel(
+    visible: bool,
+    provenance: Vec<Provenance>,
+    max_age_days: Signal<u32>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let now = now_seconds();
+    let max_age = *max_age_days.read();
+    let oldest_stale = provenance
+        .iter()
+        .filter(|p| p.is_stale(now, max_age))
+        .filter_map(|p| p.age_days(now))
+        .max_by(f64::total_cmp);
+
+    rsx! {
+        div {
+            class: if visible { "oim-about-panel visible" } else { "oim-about-panel" },
+
+            div {
+                class: "oim-about-header",
+                h2 { "About the data" }
+                button {
+                    class: "oim-about-close",
+                    onclick: move |_| on_close.call(()),
+                    "×"
+                }
+            }
+
+            div {
+                class: "oim-about-body",
+
+                if let Some(age) = oldest_stale {
+                    p {
+                        class: "about-stale-warning",
+                        "Some of the data was fetched {age:.0} days ago, more than {max_age} days. "
+                        "It may no longer match the network."
+                    }
+                }
+
+                if provenance.is_empty() {
+                    p { "The data hasn't loaded yet." }
+                } else {
+                    table {
+                        tr {
+                            th { "Dataset" }
+                            th { "Records" }
+                            th { "Fetched" }
+                            th { "Query" }
+                        }
+                        for dataset in provenance.iter() {
+                            tr {
+                                key: "{dataset.file.file_name()}",
+                                class: if dataset.is_stale(now, max_age) { "stale" } else { "" },
+                                td { "{dataset.file.file_name()}" }
+                                td { "{dataset.record_count}" }
+                                td {
+                                    match (&dataset.fetched_at, dataset.age_days(now)) {
+                                        (Some(fetched_at), Some(age)) => rsx! {
+                                            "{format_fetched_at(fetched_at)} ({age:.0} days ago)"
+                                        },
+                                        (Some(fetched_at), None) => rsx! { "{fetched_at}" },
+                                        (None, _) => rsx! { "Unknown" },
+                                    }
+                                }
+                                td {
+                                    code { {dataset.query.clone().unwrap_or_default()} }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                label {
+                    class: "about-max-age",
+                    "Warn when older than "
+                    input {
+                        r#type: "number",
+                        min: "1",
+                        value: "{max_age}",
+                        oninput: move |event| {
+                            if let Ok(days) = event.value().parse::<u32>() {
+                                max_age_days.set(days.max(1));
+                            }
+                        }
+                    }
+                    " days"
+                }
+            }
+        }
+    }
+}
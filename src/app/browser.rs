@@ -0,0 +1,736 @@
+//! Browser-only wiring for the app shell.
+//!
+//! Everything here talks to JavaScript (MapLibre, the map controls and the
+//! simulation controller script), so this module is only built for `wasm32`.
+
+use super::TflLayers;
+use super::simulation;
+use crate::data::TflDataRepository;
+use crate::data::bus::{BUS_ROUTE_MIN_ZOOM, BUS_STOP_MIN_ZOOM};
+use crate::maplibre::bindings::Popup;
+use crate::maplibre::helpers;
+use crate::maplibre::helpers::{
+    create_bus_stop_layer, create_bus_stop_letter_layer, create_circle_layer, create_label_layer,
+    create_line_layer,
+};
+use crate::utils::log::{self, LogCategory, with_context};
+use dioxus::prelude::*;
+use std::collections::HashSet;
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use web_sys::window;
+
+/// The MapLibre map, once it has been created
+fn map_instance() -> Option<crate::maplibre::bindings::Map> {
+    window()
+        .and_then(|w| js_sys::Reflect::get(&w, &JsValue::from_str("mapInstance")).ok())
+        .filter(|map| !map.is_undefined() && !map.is_null())
+        .map(Into::into)
+}
+
+/// The map extent, if zoomed in far enough to show bus routes
+fn bus_viewport(map: &crate::maplibre::bindings::Map) -> Option<[f64; 4]> {
+    (map.get_zoom() >= BUS_ROUTE_MIN_ZOOM).then(|| map.bbox())
+}
+
+/// Escape text for putting in a popup's HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A string property of a clicked feature, or empty if it has none
+fn feature_property(feature: &JsValue, name: &str) -> String {
+    js_sys::Reflect::get(feature, &JsValue::from_str("properties"))
+        .and_then(|properties| js_sys::Reflect::get(&properties, &JsValue::from_str(name)))
+        .ok()
+        .and_then(|value| value.as_string())
+        .unwrap_or_default()
+}
+
+/// Show a popup listing the routes serving the bus stop clicked on
+fn show_bus_stop_popup(event: JsValue) {
+    let Some(map) = map_instance() else {
+        return;
+    };
+    let feature = js_sys::Reflect::get(&event, &JsValue::from_str("features"))
+        .map(|features| js_sys::Array::from(&features).get(0))
+        .unwrap_or(JsValue::UNDEFINED);
+    if feature.is_undefined() {
+        return;
+    }
+
+    let letter = feature_property(&feature, "letter");
+    let towards = feature_property(&feature, "towards");
+    let mut html = format!(
+        "<strong>{}</strong>",
+        escape_html(&feature_property(&feature, "name"))
+    );
+    if !letter.is_empty() {
+        html.push_str(&format!(" (Stop {})", escape_html(&letter)));
+    }
+    if !towards.is_empty() {
+        html.push_str(&format!("<br>Towards {}", escape_html(&towards)));
+    }
+    html.push_str(&format!(
+        "<br>Routes serving this stop: {}",
+        escape_html(&feature_property(&feature, "routes"))
+    ));
+
+    let lng_lat =
+        js_sys::Reflect::get(&event, &JsValue::from_str("lngLat")).unwrap_or(JsValue::UNDEFINED);
+    Popup::new(&js_sys::Object::new())
+        .set_lng_lat(&lng_lat)
+        .set_html(&html)
+        .add_to(&map);
+}
+
+/// Pan and zoom the map to a `[west, south, east, north]` extent
+pub fn fit_map_to_bbox(bbox: [f64; 4]) {
+    if let Some(map) = map_instance() {
+        map.fit_bbox(bbox);
+    }
+}
+
+/// Remove the TfL routes and stations from the map, so they're added afresh
+/// from the next data, e.g. after switching to another dated snapshot
+pub fn clear_tfl_layers() {
+    let Some(map) = map_instance() else {
+        return;
+    };
+
+    for layer_id in map.layer_ids() {
+        if let Some(line_id) = layer_id.strip_suffix(ROUTE_LAYER_SUFFIX) {
+            map.remove_layer(&layer_id);
+            let source_id = format!("{}-route", line_id);
+            if map.get_source(&source_id).is_some() {
+                map.remove_source(&source_id);
+            }
+        }
+    }
+    remove_bus_stop_layers(&map);
+    for layer_id in ["tfl-station-labels", "tfl-stations-layer"] {
+        if map.get_layer(layer_id).is_some() {
+            map.remove_layer(layer_id);
+        }
+    }
+    if map.get_source("tfl-stations").is_some() {
+        map.remove_source("tfl-stations");
+    }
+}
+
+/// The app's signals the map and the JavaScript controls read and update
+#[derive(Clone, Copy)]
+pub struct BridgedSignals {
+    pub tfl_data: Signal<TflDataRepository>,
+    pub viewport: Signal<Option<[f64; 4]>>,
+    pub bus_stops_in_view: Signal<bool>,
+    pub layers: Signal<TflLayers>,
+    pub show_layers_panel: Signal<bool>,
+    pub show_key_panel: Signal<bool>,
+    pub show_simulation_panel: Signal<bool>,
+    pub vehicle_count: Signal<Option<usize>>,
+    pub held_count: Signal<Option<usize>>,
+    pub simulation_clock: Signal<Option<String>>,
+}
+
+/// Hook up the map and the JavaScript controls to the app's signals.
+///
+/// Adds the TfL data to the map once loaded, keeps `viewport` and
+/// `bus_stops_in_view` up to date as the map moves, installs the simulation
+/// controller, and exposes the functions the map controls call to open the
+/// key, layer and simulation panels.
+pub fn use_browser_bridges(signals: BridgedSignals) {
+    let BridgedSignals {
+        tfl_data,
+        mut viewport,
+        mut bus_stops_in_view,
+        layers,
+        show_layers_panel,
+        show_key_panel,
+        show_simulation_panel,
+        mut vehicle_count,
+        mut held_count,
+        mut simulation_clock,
+    } = signals;
+
+    // Add an effect to update the map when TFL data is loaded
+    use_effect(move || {
+        // Only react if the data is loaded
+        if tfl_data.read().is_loaded {
+            log::info_with_category(LogCategory::App, "TFL data loaded, updating map layers");
+
+            // Update the map with the TFL data
+            if let Some(manager) = window()
+                .and_then(|w| js_sys::Reflect::get(&w, &JsValue::from_str("mapInstance")).ok())
+            {
+                let map: crate::maplibre::bindings::Map = manager.clone().into();
+
+                // We need to check if the map style is loaded
+                if map.is_style_loaded() {
+                    log::info_with_category(
+                        LogCategory::App,
+                        "Map style loaded, adding TFL data layers",
+                    );
+
+                    // Call a helper function to add the TFL data to the map
+                    add_tfl_data_to_map(&map, tfl_data.read().clone());
+                } else {
+                    log::info_with_category(
+                        LogCategory::App,
+                        "Map style not loaded yet, waiting for 'load' event",
+                    );
+
+                    // Create a callback for the 'load' event
+                    let tfl_data_clone = tfl_data;
+                    let load_callback = Closure::wrap(Box::new(move || {
+                        log::info_with_category(
+                            LogCategory::App,
+                            "Map 'load' event fired, adding TFL data layers",
+                        );
+
+                        // If we get here via a callback, we need to get the map again
+                        if let Some(window) = window()
+                            && let Ok(map_instance) =
+                                js_sys::Reflect::get(&window, &JsValue::from_str("mapInstance"))
+                        {
+                            let map: crate::maplibre::bindings::Map = map_instance.into();
+                            add_tfl_data_to_map(&map, tfl_data_clone.read().clone());
+                        }
+                    }) as Box<dyn FnMut()>);
+
+                    // Register the callback
+                    map.on("load", &load_callback);
+
+                    // Leak the callback to keep it alive
+                    load_callback.forget();
+                }
+            }
+
+            // Make the TfL data available to JavaScript for the simulation
+            let js_code = r#"
+                // Create a placeholder for TfL data
+                window.__tflData = true; // Simple flag to indicate data is available
+            "#;
+            let _ = js_sys::eval(js_code);
+        }
+    });
+
+    // Track the map extent once the map exists, for loading bus routes and
+    // stops in view
+    let mut tracking_viewport = use_signal(|| false);
+    use_effect(move || {
+        if !tfl_data.read().is_loaded || *tracking_viewport.peek() {
+            return;
+        }
+        let Some(map) = map_instance() else {
+            return;
+        };
+
+        let on_move = Closure::wrap(Box::new(move || {
+            if let Some(map) = map_instance() {
+                let extent = bus_viewport(&map);
+                if *viewport.peek() != extent {
+                    viewport.set(extent);
+                }
+                let stops_in_view = map.get_zoom() >= BUS_STOP_MIN_ZOOM;
+                if *bus_stops_in_view.peek() != stops_in_view {
+                    bus_stops_in_view.set(stops_in_view);
+                }
+            }
+        }) as Box<dyn FnMut()>);
+        map.on("moveend", &on_move);
+        on_move.forget();
+
+        let on_stop_click = Closure::wrap(Box::new(show_bus_stop_popup) as Box<dyn FnMut(JsValue)>);
+        map.on_layer("click", BUS_STOPS_LAYER, &on_stop_click);
+        on_stop_click.forget();
+
+        viewport.set(bus_viewport(&map));
+        bus_stops_in_view.set(map.get_zoom() >= BUS_STOP_MIN_ZOOM);
+        tracking_viewport.set(true);
+    });
+
+    // Initialize simulation JS when app loads
+    use_effect(move || {
+        with_context("app::simulation_init", LogCategory::App, |logger| {
+            // Only create the SimulationController if it doesn't already exist
+            if let Some(window) = window()
+                && let Ok(simulation_controller) =
+                    js_sys::Reflect::get(&window, &JsValue::from_str("SimulationController"))
+                && !simulation_controller.is_undefined()
+            {
+                // SimulationController already exists, no need to add the script
+                // logger.debug("SimulationController already exists, skipping initialization");
+                return;
+            }
+            logger.info("Initializing simulation controller script");
+
+            let controller_script = format!(
+                r#"
+                // Global simulation controller
+                const SimulationController = {{
+                  initialized: false,
+                  running: false,
+
+                  initialize: function() {{
+                    console.log("SimulationController.initialize() called");
+                    if (this.initialized) {{
+                      console.log("Simulation already initialized, skipping");
+                      return;
+                    }}
+
+                    // Call the Rust initialization function
+                    if (typeof window.rust_initialize_simulation === 'function') {{
+                      console.log("Calling rust_initialize_simulation()");
+                      window.rust_initialize_simulation();
+                      this.initialized = true;
+                      this.running = true;
+                    }} else {{
+                      console.error("rust_initialize_simulation function not found");
+                    }}
+                  }},
+
+                  toggle: function() {{
+                    console.log("SimulationController.toggle() called");
+                    if (!this.initialized) {{
+                      this.initialize();
+                      return;
+                    }}
+
+                    if (typeof window.rust_toggle_simulation === 'function') {{
+                      window.rust_toggle_simulation();
+                      this.running = !this.running;
+                      console.log("Simulation running:", this.running);
+                    }}
+                  }},
+
+                  reset: function() {{
+                    console.log("SimulationController.reset() called");
+                    if (typeof window.rust_reset_simulation === 'function') {{
+                      window.rust_reset_simulation();
+                      this.running = true;
+                      console.log("Simulation reset and running");
+                    }}
+                  }}
+                }};
+
+                // Make it globally available
+                window.SimulationController = SimulationController;
+
+                // Only initialize automatically if simulation is enabled
+                const simulationEnabled = {0};
+
+                if (simulationEnabled) {{
+                  // Initialize when map is ready
+                  if (window.mapInstance && window.mapInstance.isStyleLoaded()) {{
+                    setTimeout(function() {{
+                      SimulationController.initialize();
+                    }}, 1000);
+                  }} else {{
+                    const initInterval = setInterval(function() {{
+                      if (window.mapInstance && window.mapInstance.isStyleLoaded()) {{
+                        clearInterval(initInterval);
+                        setTimeout(function() {{
+                          SimulationController.initialize();
+                        }}, 1000);
+                      }}
+                    }}, 1000);
+                  }}
+                }} else {{
+                  console.log("Automatic simulation initialization disabled");
+                }}
+                "#,
+                layers.read().simulation
+            );
+
+            if let Err(e) = helpers::add_inline_script(&controller_script) {
+                logger.error(&format!("Failed to add simulation script: {:?}", e));
+            } else {
+                logger.info("Simulation controller script added successfully");
+            }
+        });
+    });
+
+    use_effect(move || {
+        with_context("app::simulation_functions", LogCategory::App, |logger| {
+            logger.info("Exposing simulation functions");
+
+            // Try to expose simulation functions if available
+            match simulation::expose_simulation_functions(Some(tfl_data.read().clone())) {
+                Ok(_) => {
+                    logger.info("Simulation functions exposed successfully");
+                }
+                Err(err) => {
+                    logger.error(&format!("Failed to expose simulation functions: {:?}", err));
+                }
+            }
+
+            // Add the controller script
+            let controller_script = r#"
+    // SimulationController code here...
+    "#;
+
+            if let Err(e) = helpers::add_inline_script(controller_script) {
+                logger.error(&format!("Failed to add simulation script: {:?}", e));
+            } else {
+                logger.debug("Additional controller script added");
+            }
+        })
+    });
+
+    // Add an effect to set up the simulation panel connection
+    use_effect(move || {
+        with_context(
+            "app::simulation_panel_connection",
+            LogCategory::App,
+            |logger| {
+                logger.info("Setting up simulation panel connection to JavaScript");
+
+                // Create a copy of the signal for the closure
+                let mut show_sim = show_simulation_panel;
+
+                // Create a closure that will open the simulation panel when called from JavaScript
+                let open_sim_callback = Closure::wrap(Box::new(move || {
+                    log::info_with_category(
+                        LogCategory::App,
+                        "openTflSimulationPanel called from JavaScript",
+                    );
+                    show_sim.set(true);
+                }) as Box<dyn FnMut()>);
+
+                // Expose the closure to JavaScript
+                if let Some(window) = window() {
+                    if let Err(e) = js_sys::Reflect::set(
+                        &window,
+                        &JsValue::from_str("openTflSimulationPanel"),
+                        open_sim_callback.as_ref(),
+                    ) {
+                        logger.error(&format!("Failed to set openTflSimulationPanel: {:?}", e));
+                    } else {
+                        logger.info("Successfully exposed openTflSimulationPanel to JavaScript");
+                    }
+                }
+
+                // Forget the closure to prevent memory leaks
+                open_sim_callback.forget();
+            },
+        );
+    });
+
+    // Add an effect to update the simulation vehicle count and clock, keeping
+    // the interval it sets up until the effect runs again
+    let mut update_interval = use_signal(|| Option::<i32>::None);
+    use_effect(move || {
+        let mut update_vehicle_count = move || {
+            if *show_simulation_panel.read() {
+                // Get the vehicle count and time from the simulation state
+                let count = simulation::get_vehicle_count();
+                vehicle_count.set(Some(count));
+                held_count.set(Some(simulation::get_held_count()));
+                simulation_clock.set(Some(simulation::simulation_clock()));
+            }
+        };
+
+        // Set up an interval to update the vehicle count, in place of the last
+        if let Some(window) = window() {
+            if let Some(handle) = *update_interval.peek() {
+                window.clear_interval_with_handle(handle);
+            }
+            let callback = Closure::wrap(Box::new(update_vehicle_count) as Box<dyn FnMut()>);
+            let handle = window.set_interval_with_callback_and_timeout_and_arguments(
+                callback.as_ref().unchecked_ref(),
+                1000,
+                &js_sys::Array::new(),
+            );
+            update_interval.set(handle.ok());
+            callback.forget();
+        }
+
+        // Run the function once immediately
+        update_vehicle_count();
+    });
+
+    // Add an effect to connect the key panel (glorified onclick event handler)
+    use_effect(move || {
+        with_context("app::key_panel_connection", LogCategory::App, |logger| {
+            logger.info("Setting up key panel connection to JavaScript");
+
+            // Create a clone of the signal for the closure
+            let mut show_key = show_key_panel;
+
+            // Create a closure that will open the key panel when called from JavaScript
+            // Don't capture logger in this closure!
+            let open_key_callback = Closure::wrap(Box::new(move || {
+                // Use direct log calls instead of the captured logger
+                log::debug_with_category(
+                    LogCategory::App,
+                    "openTflKeyPanel called from JavaScript",
+                );
+                show_key.set(true);
+            }) as Box<dyn FnMut()>);
+
+            // Expose the closure to JavaScript
+            if let Some(window) = window() {
+                if let Err(e) = js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("openTflKeyPanel"),
+                    open_key_callback.as_ref(),
+                ) {
+                    logger.error(&format!("Failed to set openTflKeyPanel: {:?}", e));
+                } else {
+                    logger.info("Successfully exposed openTflKeyPanel to JavaScript");
+                }
+            }
+
+            // Forget the closure to prevent memory leaks
+            open_key_callback.forget();
+        });
+    });
+
+    // Add an effect to set up the layer panel connection
+    use_effect(move || {
+        with_context("app::layer_panel_connection", LogCategory::App, |logger| {
+            logger.info("Setting up layer panel connection to JavaScript");
+
+            // Create a copy of the signal for the closure
+            let mut show_layers = show_layers_panel;
+
+            // Create a closure that will open the layer panel when called from JavaScript
+            let open_layer_panel_callback = Closure::wrap(Box::new(move || {
+                log::info_with_category(
+                    LogCategory::App,
+                    "openTflLayerPanel called from JavaScript",
+                );
+                show_layers.set(true);
+            }) as Box<dyn FnMut()>);
+
+            // Expose the closure to JavaScript
+            if let Some(window) = window() {
+                if let Err(e) = js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("openTflLayerPanel"),
+                    open_layer_panel_callback.as_ref(),
+                ) {
+                    logger.error(&format!("Failed to set openTflLayerPanel: {:?}", e));
+                } else {
+                    logger.info("Successfully exposed openTflLayerPanel to JavaScript");
+                }
+            }
+
+            // Forget the closure to prevent memory leaks
+            open_layer_panel_callback.forget();
+        });
+    });
+}
+
+/// Suffix of the layer ID for each line's route geometry
+const ROUTE_LAYER_SUFFIX: &str = "-route-layer";
+
+/// Source of the bus stops, and the prefix of their layers' IDs
+const BUS_STOPS_SOURCE: &str = "bus-stops";
+const BUS_STOPS_LAYER: &str = "bus-stops-layer";
+const BUS_STOP_LETTERS_LAYER: &str = "bus-stops-letters";
+
+/// Remove the bus stops from the map, e.g. when buses are switched off
+fn remove_bus_stop_layers(map: &crate::maplibre::bindings::Map) {
+    for layer_id in [BUS_STOP_LETTERS_LAYER, BUS_STOPS_LAYER] {
+        if map.get_layer(layer_id).is_some() {
+            map.remove_layer(layer_id);
+        }
+    }
+    if map.get_source(BUS_STOPS_SOURCE).is_some() {
+        map.remove_source(BUS_STOPS_SOURCE);
+    }
+}
+
+/// Helper function to add TFL data layers to an already initialized map
+///
+/// Brings the map's TfL layers in line with the data. Route layers are
+/// reconciled by diff: lines no longer in the data are removed and new ones
+/// added, leaving the rest of the map untouched.
+fn add_tfl_data_to_map(map: &crate::maplibre::bindings::Map, tfl_data: TflDataRepository) {
+    with_context("add_tfl_data_to_map", LogCategory::Map, |logger| {
+        logger.info("Updating TFL data layers on map");
+
+        // Reconcile the route layers on the map with the lines in the data
+        let on_map: HashSet<String> = map
+            .layer_ids()
+            .iter()
+            .filter_map(|id| id.strip_suffix(ROUTE_LAYER_SUFFIX))
+            .map(str::to_string)
+            .collect();
+        let wanted: HashSet<String> = tfl_data.route_geometries.keys().cloned().collect();
+
+        // Remove lines that are no longer in the data, e.g. buses switched off
+        for line_id in on_map.difference(&wanted) {
+            logger.debug(&format!("Removing {} route geometry", line_id));
+            map.remove_layer(&format!("{}{}", line_id, ROUTE_LAYER_SUFFIX));
+            let source_id = format!("{}-route", line_id);
+            if map.get_source(&source_id).is_some() {
+                map.remove_source(&source_id);
+            }
+        }
+
+        // Add route geometries for lines that aren't on the map yet
+        if let Ok(route_data) =
+            crate::data::generate_route_geometries(&tfl_data, wanted.difference(&on_map))
+        {
+            logger.info(&format!(
+                "Adding {} TFL route geometries to map",
+                route_data.len()
+            ));
+
+            for (line_id, route_geojson) in route_data {
+                logger.debug(&format!("Adding {} route geometry", line_id));
+                let source_id = format!("{}-route", line_id);
+                let layer_id = format!("{}{}", line_id, ROUTE_LAYER_SUFFIX);
+
+                // A stale source may be left over if its layer was removed
+                if map.get_source(&source_id).is_some() {
+                    map.remove_source(&source_id);
+                }
+                map.add_source(&source_id, &route_geojson);
+
+                // Get the appropriate color for this line
+                let color = tfl_data.line_color(&line_id);
+
+                let route_mode = tfl_data
+                    .routes
+                    .get(&line_id)
+                    .and_then(|directions| directions.values().next())
+                    .and_then(|response| response.first())
+                    .map(|route_sequence| route_sequence.mode.to_lowercase())
+                    .unwrap_or_else(|| "train".to_string());
+                let width = if route_mode == "bus" { 0.0 } else { 3.0 };
+
+                // Add the layer with a dashed style to distinguish from simplified line data
+                if let Ok(route_layer) = create_line_layer(&layer_id, &source_id, &color, width) {
+                    // Keep the bus stops on top
+                    if map.get_layer(BUS_STOPS_LAYER).is_some() {
+                        map.add_layer_before(&route_layer, BUS_STOPS_LAYER);
+                    } else {
+                        map.add_layer(&route_layer);
+                    }
+                    logger.debug(&format!("Added {} route geometry", line_id));
+                }
+            }
+        } else {
+            logger.error("Failed to generate route geometries");
+        }
+
+        // Add all stations as a GeoJSON source
+        if let Ok(stations_geojson) = crate::data::stations_to_geojson(&tfl_data.stations) {
+            // Make sure the source doesn't already exist
+            if map.get_layer("tfl-stations-layer").is_none() {
+                logger.info(&format!(
+                    "Adding {} stations to map",
+                    tfl_data.stations.len()
+                ));
+
+                // Add the source
+                web_sys::console::log_1(&stations_geojson);
+                map.add_source("tfl-stations", &stations_geojson);
+
+                // Add a circle layer for the stations
+                if let Ok(stations_layer) =
+                    create_circle_layer("tfl-stations-layer", "tfl-stations")
+                {
+                    map.add_layer(&stations_layer);
+                    logger.debug("Added stations layer");
+                }
+
+                // Add a label layer for the stations
+                if let Ok(labels_layer) = create_label_layer("tfl-station-labels", "tfl-stations") {
+                    map.add_layer(&labels_layer);
+                    logger.debug("Added station labels layer");
+                }
+            } else {
+                logger.debug("Stations layer already exists, skipping");
+            }
+        } else {
+            logger.error("Failed to convert stations to GeoJSON");
+        }
+
+        // Bus stops are only drawn once zoomed in
+        let bus_stops = tfl_data.bus_stops();
+        let on_map = map.get_layer(BUS_STOPS_LAYER).is_some();
+        if bus_stops.is_empty() && on_map {
+            logger.debug("Removing bus stops");
+            remove_bus_stop_layers(map);
+        } else if !bus_stops.is_empty() && !on_map {
+            match crate::data::bus_stops_to_geojson(bus_stops) {
+                Ok(stops_geojson) => {
+                    logger.info(&format!("Adding {} bus stops to map", bus_stops.len()));
+                    if map.get_source(BUS_STOPS_SOURCE).is_some() {
+                        map.remove_source(BUS_STOPS_SOURCE);
+                    }
+                    map.add_source(BUS_STOPS_SOURCE, &stops_geojson);
+                    if let Ok(layer) =
+                        create_bus_stop_layer(BUS_STOPS_LAYER, BUS_STOPS_SOURCE, BUS_STOP_MIN_ZOOM)
+                    {
+                        map.add_layer(&layer);
+                    }
+                    if let Ok(layer) = create_bus_stop_letter_layer(
+                        BUS_STOP_LETTERS_LAYER,
+                        BUS_STOPS_SOURCE,
+                        BUS_STOP_MIN_ZOOM,
+                    ) {
+                        map.add_layer(&layer);
+                    }
+                }
+                Err(e) => logger.error(&format!("Failed to convert bus stops to GeoJSON: {:?}", e)),
+            }
+        }
+
+        // Commented out as this is deprecated: uncomment to see new lines before adding their routes
+        // // Add all tube lines (NB this is being incrementally deprecated)
+        // if let Ok(line_data) = crate::data::generate_all_line_data(&tfl_data) {
+        //     logger.info(&format!("Adding {} TFL lines to map", line_data.len()));
+
+        //     for (line_name, line_geojson, color) in line_data {
+        //         // Skip lines that have proper route data
+        //         match line_name.as_str() {
+        //             "bakerloo" | "central" | "circle" | "district" | "hammersmith-city"
+        //             | "jubilee" | "metropolitan" | "northern" | "piccadilly" | "victoria"
+        //             | "waterloo-city" | "elizabeth" | "thameslink" | "tram" | "dlr" | "london-cable-car" => {
+        //                 logger.debug(&format!(
+        //                     "Skipping {} line - using route data instead",
+        //                     line_name
+        //                 ));
+        //                 continue; // Skip this iteration
+        //             }
+        //             _ => {} // Process other lines normally
+        //         }
+        //         web_sys::console::log_1(&line_geojson);
+        //         let source_id = format!("{}-line", line_name);
+        //         let layer_id = format!("{}-line-layer", line_name);
+
+        //         // Make sure the layer doesn't already exist
+        //         if map.get_layer(&layer_id).is_none() {
+        //             // Add the source
+        //             map.add_source(&source_id, &line_geojson);
+
+        //             // Add the layer
+        //             if let Ok(line_layer) = create_line_layer(&layer_id, &source_id, &color, 4.0) {
+        //                 map.add_layer(&line_layer);
+        //                 // Anything set this way is invisible (so as to deprecate as we migratet to routes)
+        //                 map.set_layout_property(
+        //                     &layer_id,
+        //                     "visibility",
+        //                     &JsValue::from_str("none"),
+        //                 );
+        //                 logger.debug(&format!("Added {} line", line_name));
+        //             }
+        //         } else {
+        //             logger.debug(&format!("{} line already exists, skipping", line_name));
+        //         }
+        //     }
+        // } else {
+        //     logger.error("Failed to generate line data");
+        // }
+
+        logger.info("TFL data layers added to map");
+    });
+}
@@ -26,7 +26,7 @@ pub fn Canvas(layers: Signal<TflLayers>, tfl_data: Signal<TflDataRepository>) ->
         // Check if we've already initialized - avoid double initialization
         if *already_initialized.write() {
             log::info_with_category(LogCategory::Map, "Map already initialized, skipping");
-            return {};
+            return;
         }
 
         // Mark as initialized immediately to prevent potential recursion
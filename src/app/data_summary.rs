@@ -0,0 +1,72 @@
+//! Stand-in for the map on native builds.
+//!
+//! MapLibre only runs in the browser, so the desktop app shows what was
+//! loaded from disk and offers the buttons the map controls would otherwise
+//! provide.
+
+use crate::data::TflDataRepository;
+use crate::data::line_definitions::{LINE_INFOS, line_info_as_of};
+use dioxus::prelude::*;
+
+#[component]
+pub fn DataSummary(
+    tfl_data: Signal<TflDataRepository>,
+    on_show_key: EventHandler<()>,
+    on_show_layers: EventHandler<()>,
+    on_show_simulation: EventHandler<()>,
+) -> Element {
+    let data = tfl_data.read();
+
+    let platform_count: usize = data.platforms_by_station.values().map(Vec::len).sum();
+
+    // Lines in registry order first, then anything else (e.g. buses) by id
+    let mut line_ids: Vec<&String> = data.routes.keys().collect();
+    line_ids.sort_by_key(|id| {
+        let position = LINE_INFOS.iter().position(|info| info.id == id.as_str());
+        (position.unwrap_or(usize::MAX), id.to_string())
+    });
+    let lines: Vec<(String, String)> = line_ids
+        .into_iter()
+        .map(|id| {
+            let name = line_info_as_of(id, data.as_of.as_deref())
+                .map(|info| info.name.to_string())
+                .unwrap_or_else(|| id.clone());
+            (id.clone(), name)
+        })
+        .collect();
+
+    rsx! {
+        div {
+            class: "data-summary",
+
+            div {
+                class: "data-summary-controls",
+                button { onclick: move |_| on_show_key.call(()), "Key" }
+                button { onclick: move |_| on_show_layers.call(()), "Layers" }
+                button { onclick: move |_| on_show_simulation.call(()), "Simulation" }
+            }
+
+            if !data.is_loaded {
+                p { "Loading TfL data..." }
+            } else {
+                p {
+                    "{data.stations.len()} stations, {platform_count} platforms, {lines.len()} lines"
+                }
+
+                table {
+                    for (id, name) in lines {
+                        tr {
+                            key: "{id}",
+                            td { "{name}" }
+                            td {
+                                div {
+                                    class: format_args!("color-line {}", id)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
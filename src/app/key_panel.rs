@@ -1,13 +1,49 @@
-use crate::data::line_definitions::{
-    get_other_rail_lines, get_overground_lines, get_underground_lines,
-};
+use crate::data::LineDetails;
+use crate::data::line_definitions::{LINE_INFOS, LineType, line_info_as_of, lines_as_of};
 use dioxus::prelude::*;
+use std::collections::HashMap;
 
+/// Style for a line's colour swatch, if the data gives the line its own colour
+fn swatch_style(line_details: &HashMap<String, LineDetails>, line_id: &str) -> String {
+    line_details
+        .get(line_id)
+        .and_then(|details| details.color.as_ref())
+        .map(|color| format!("background-color: {}", color))
+        .unwrap_or_default()
+}
+
+/// Key to the lines in `line_ids` as they were on `as_of`, or to every line
+/// until the data has loaded
+///
+/// Lines the registry doesn't know are listed with the other lines if
+/// `line_details` has them, e.g. from a GTFS feed, apart from buses.
 #[component]
-pub fn KeyPanel(visible: bool, on_close: EventHandler<()>) -> Element {
-    let underground_lines = get_underground_lines();
-    let overground_lines = get_overground_lines();
-    let other_rail_lines = get_other_rail_lines();
+pub fn KeyPanel(
+    visible: bool,
+    line_ids: Vec<String>,
+    as_of: Option<String>,
+    line_details: HashMap<String, LineDetails>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let lines = if line_ids.is_empty() {
+        lines_as_of(LINE_INFOS.iter().map(|info| info.id), as_of.as_deref())
+    } else {
+        lines_as_of(line_ids.iter().map(String::as_str), as_of.as_deref())
+    };
+    let (underground_lines, rest): (Vec<_>, Vec<_>) = lines
+        .into_iter()
+        .partition(|line| matches!(line.line_type, LineType::Underground));
+    // "Other rail" means neither Underground nor Overground
+    let (overground_lines, other_rail_lines): (Vec<_>, Vec<_>) = rest
+        .into_iter()
+        .partition(|line| matches!(line.line_type, LineType::Overground));
+    let mut feed_lines: Vec<(&String, &LineDetails)> = line_ids
+        .iter()
+        .filter(|id| line_info_as_of(id, as_of.as_deref()).is_none())
+        .filter_map(|id| Some((id, line_details.get(id)?)))
+        .filter(|(_, details)| details.mode != "bus")
+        .collect();
+    feed_lines.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
 
     rsx! {
         div {
@@ -34,7 +70,8 @@ pub fn KeyPanel(visible: bool, on_close: EventHandler<()>) -> Element {
                             td { "{line.name}" }
                             td {
                                 div {
-                                    class: format_args!("color-line {}", line.id)
+                                    class: format_args!("color-line {}", line.id),
+                                    style: swatch_style(&line_details, line.id),
                                 }
                             }
                         }
@@ -49,7 +86,8 @@ pub fn KeyPanel(visible: bool, on_close: EventHandler<()>) -> Element {
                             td { "{line.name}" }
                             td {
                                 div {
-                                    class: format_args!("color-line {}", line.id)
+                                    class: format_args!("color-line {}", line.id),
+                                    style: swatch_style(&line_details, line.id),
                                 }
                             }
                         }
@@ -64,7 +102,23 @@ pub fn KeyPanel(visible: bool, on_close: EventHandler<()>) -> Element {
                             td { "{line.name}" }
                             td {
                                 div {
-                                    class: format_args!("color-line {}", line.id)
+                                    class: format_args!("color-line {}", line.id),
+                                    style: swatch_style(&line_details, line.id),
+                                }
+                            }
+                        }
+                    }
+                    for (id, details) in &feed_lines {
+                        tr {
+                            td { "{details.name}" }
+                            td {
+                                div {
+                                    class: "color-line",
+                                    style: format_args!(
+                                        "background-color: {}",
+                                        details.color.as_deref().unwrap_or("#777777")
+                                    ),
+                                    title: "{id}",
                                 }
                             }
                         }
@@ -1,10 +1,10 @@
 use super::TflLayers;
 use dioxus::prelude::*;
-use web_sys::window;
 
 // Add this helper function in src/app/layer_panel.rs or at the module level in src/app/mod.rs
+#[cfg(target_arch = "wasm32")]
 fn update_js_layer_visibility(layer_id: &str, visible: bool) {
-    if let Some(window) = window() {
+    if let Some(_window) = web_sys::window() {
         let js_code = format!(
             r#"
             if (window.LayerSwitcher && window.LayerSwitcher.getInstance()) {{
@@ -21,13 +21,41 @@ fn update_js_layer_visibility(layer_id: &str, visible: bool) {
     }
 }
 
+// The layer switcher only exists on the map, so there is nothing to update natively
+#[cfg(not(target_arch = "wasm32"))]
+fn update_js_layer_visibility(_layer_id: &str, _visible: bool) {}
+
+/// Show or hide the simulated vehicles on the map
+#[cfg(target_arch = "wasm32")]
+fn update_js_simulation_visibility(visible: bool) {
+    let js_code = format!(
+        r#"
+        if (window.mapInstance) {{
+            const visibility = {} ? 'visible' : 'none';
+            if (window.mapInstance.getLayer('buses-layer')) {{
+                window.mapInstance.setLayoutProperty('buses-layer', 'visibility', visibility);
+            }}
+            if (window.mapInstance.getLayer('trains-layer')) {{
+                window.mapInstance.setLayoutProperty('trains-layer', 'visibility', visibility);
+            }}
+        }}
+        "#,
+        visible
+    );
+    let _ = js_sys::eval(&js_code);
+}
+
 #[component]
 pub fn LayerPanel(
     visible: bool,
     layers: Signal<TflLayers>,
     load_bus_routes: Signal<bool>,
+    bus_search_error: Option<String>,
+    on_search_bus_route: EventHandler<String>,
     on_close: EventHandler<()>,
 ) -> Element {
+    let mut bus_query = use_signal(String::new);
+
     rsx! {
         div {
             class: if visible { "layer-switcher-list active" } else { "layer-switcher-list" },
@@ -235,7 +263,32 @@ pub fn LayerPanel(
                 }
                 label {
                     r#for: "load_bus_routes",
-                    "Load Bus Routes (requires reload)"
+                    "Load Bus Routes"
+                }
+            }
+
+            // Bus routes load as they come into view, or can be looked up directly
+            if *load_bus_routes.read() {
+                form {
+                    class: "layer-item bus-route-search",
+                    onsubmit: move |evt| {
+                        evt.prevent_default();
+                        on_search_bus_route.call(bus_query.read().clone());
+                    },
+                    input {
+                        r#type: "search",
+                        placeholder: "Bus route, e.g. 24",
+                        value: "{bus_query}",
+                        oninput: move |evt| bus_query.set(evt.value())
+                    }
+                    button { r#type: "submit", "Find" }
+                }
+
+                if let Some(error) = bus_search_error {
+                    div {
+                        class: "bus-route-search-error",
+                        "{error}"
+                    }
                 }
             }
 
@@ -263,6 +316,27 @@ pub fn LayerPanel(
                 }
             }
 
+            div {
+                class: "layer-item",
+                input {
+                    r#type: "checkbox",
+                    id: "bus_stops",
+                    name: "bus_stops",
+                    checked: layers.read().bus_stops,
+                    onchange: move |_| {
+                        let mut updated = *layers.read();
+                        updated.bus_stops = !updated.bus_stops;
+                        layers.set(updated);
+
+                        update_js_layer_visibility("bus-stops", updated.bus_stops);
+                    }
+                }
+                label {
+                    r#for: "bus_stops",
+                    "Bus Stops"
+                }
+            }
+
             h4 { "Background" }
             div {
                 class: "layer-item",
@@ -325,21 +399,8 @@ pub fn LayerPanel(
                         layers.set(updated);
 
                         // Update visibility of simulation layers via JS
-                        let js_code = format!(
-                            r#"
-                            if (window.mapInstance) {{
-                                const visibility = {} ? 'visible' : 'none';
-                                if (window.mapInstance.getLayer('buses-layer')) {{
-                                    window.mapInstance.setLayoutProperty('buses-layer', 'visibility', visibility);
-                                }}
-                                if (window.mapInstance.getLayer('trains-layer')) {{
-                                    window.mapInstance.setLayoutProperty('trains-layer', 'visibility', visibility);
-                                }}
-                            }}
-                            "#,
-                            updated.simulation
-                        );
-                        let _ = js_sys::eval(&js_code);
+                        #[cfg(target_arch = "wasm32")]
+                        update_js_simulation_visibility(updated.simulation);
                     }
                 }
                 label {
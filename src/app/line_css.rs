@@ -3,10 +3,12 @@ use crate::data::line_definitions;
 use dioxus::prelude::*;
 
 /// Component that renders the dynamic CSS for line colors
+///
+/// Lines are coloured as they were on `as_of`, or as they are now.
 #[component]
-pub fn LineCss() -> Element {
+pub fn LineCss(as_of: Option<String>) -> Element {
     // Generate CSS content from line definitions
-    let css_content = line_definitions::generate_line_css();
+    let css_content = line_definitions::generate_line_css(as_of.as_deref());
 
     rsx! {
         // Use Dioxus's document::Style component to inject CSS
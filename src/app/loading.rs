@@ -0,0 +1,100 @@
+use crate::data::DataError;
+use crate::data::source::DataFile;
+use crate::data::stages::{LoadProgress, StageStatus};
+use dioxus::prelude::*;
+
+/// Human-readable name of a dataset
+fn dataset_name(file: DataFile) -> &'static str {
+    match file {
+        DataFile::Stations => "Stations",
+        DataFile::Platforms => "Platforms",
+        DataFile::RailRoutes => "Rail routes",
+        DataFile::BusManifest => "Bus routes",
+    }
+}
+
+/// Format a byte count as e.g. "1.2 MB"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Text describing where a stage has got to
+fn status_text(status: &StageStatus) -> String {
+    match status {
+        StageStatus::Pending => "Waiting".to_string(),
+        StageStatus::Loading { bytes, total } => match total {
+            // The total is only a hint (it may be the compressed size)
+            Some(total) if total >= bytes => {
+                format!("{} of {}", format_bytes(*bytes), format_bytes(*total))
+            }
+            _ => format_bytes(*bytes),
+        },
+        StageStatus::Done { bytes } => format!("Done ({})", format_bytes(*bytes)),
+        StageStatus::Failed(_) => "Failed".to_string(),
+        StageStatus::Skipped => "Skipped".to_string(),
+    }
+}
+
+/// Overlay shown over the map while the datasets load
+#[component]
+pub fn LoadingOverlay(progress: LoadProgress) -> Element {
+    if !progress.is_loading() {
+        return rsx! {};
+    }
+
+    rsx! {
+        div {
+            class: "loading-overlay",
+
+            h3 { "Loading TfL data" }
+
+            table {
+                for file in DataFile::ALL {
+                    if *progress.status(file) != StageStatus::Skipped {
+                        tr {
+                            key: "{file.file_name()}",
+                            class: if matches!(progress.status(file), StageStatus::Loading { .. }) { "loading-active" },
+                            td { "{dataset_name(file)}" }
+                            td { "{status_text(progress.status(file))}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Banner explaining why a dataset failed to load, with a button to retry it
+#[component]
+pub fn ErrorBanner(file: DataFile, error: DataError, on_retry: EventHandler<()>) -> Element {
+    rsx! {
+        div {
+            class: "error-banner",
+
+            div {
+                class: "error-banner-message",
+                strong { "{dataset_name(file)} failed to load. " }
+                "{error}"
+            }
+
+            button {
+                class: "error-banner-retry",
+                onclick: move |_| on_retry.call(()),
+                "Retry"
+            }
+        }
+    }
+}
@@ -4,25 +4,41 @@
 
 use dioxus::prelude::*;
 
+mod about_panel;
+#[cfg(target_arch = "wasm32")]
+mod browser;
+#[cfg(target_arch = "wasm32")]
 mod canvas;
+#[cfg(not(target_arch = "wasm32"))]
+mod data_summary;
 mod key_panel;
 mod layer_panel;
 mod line_css;
+mod loading;
+mod network_date;
+mod scenario_editor;
 mod simulation;
 mod simulation_panel; // New module for vehicle simulation
 
 use crate::app::line_css::LineCss;
-use crate::data::TflDataRepository;
-use crate::data::line_definitions::get_line_color;
-use crate::maplibre::helpers;
-use crate::maplibre::helpers::{create_circle_layer, create_label_layer, create_line_layer};
+use crate::data::gtfs::{self, GtfsFeed};
+use crate::data::provenance::{DEFAULT_MAX_AGE_DAYS, fetched_on};
+use crate::data::snapshots::{SnapshotIndex, SnapshotSource, load_snapshot_index};
+use crate::data::stages::{LoadProgress, RepositoryLoader, StageEvent};
+use crate::data::validate::{DEFAULT_MAX_STATION_DISTANCE, Severity};
+use crate::data::{AnySource, DataSource, TflDataRepository, loader};
 use crate::utils::log::{self, LogCategory, with_context};
+use about_panel::AboutPanel;
+#[cfg(target_arch = "wasm32")]
 use canvas::Canvas;
+#[cfg(not(target_arch = "wasm32"))]
+use data_summary::DataSummary;
 use key_panel::KeyPanel;
 use layer_panel::LayerPanel;
+use loading::{ErrorBanner, LoadingOverlay};
+use network_date::NetworkDatePicker;
 use simulation_panel::SimulationPanel;
-use wasm_bindgen::{JsCast, JsValue, closure::Closure};
-use web_sys::window;
+use std::collections::HashSet;
 
 // If you have images or CSS as assets, define them with Dioxus' asset! macro
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -32,6 +48,8 @@ const TFL_CSS: Asset = asset!("/assets/tfl.css");
 const KEY_CSS: Asset = asset!("/assets/key.css");
 const SIM_CSS: Asset = asset!("/assets/simulation.css");
 const LAYER_CSS: Asset = asset!("/assets/layerswitcher.css");
+const LOADING_CSS: Asset = asset!("/assets/loading.css");
+const ABOUT_CSS: Asset = asset!("/assets/about.css");
 
 /// Model to track layer visibility.
 ///
@@ -58,6 +76,8 @@ pub struct TflLayers {
     pub labels: bool,
     /// Station markers and labels
     pub stations: bool,
+    /// Bus stops, shown once zoomed in while bus routes are loaded
+    pub bus_stops: bool,
     /// Depot locations
     pub depots: bool,
     /// Vehicle simulation
@@ -77,456 +97,615 @@ impl Default for TflLayers {
             cable_car: true,
             labels: false,
             stations: true,
+            bus_stops: true,
             depots: false,
             simulation: false, // Simulation disabled by default
         }
     }
 }
 
-/// Main application component.
+/// Choose where to load the TfL data from.
 ///
-/// This is the root component of the TfL Simulation application.
-#[component]
-pub fn app() -> Element {
-    let mut show_layers_panel = use_signal(|| false);
-    let mut show_key_panel = use_signal(|| false);
-    let mut show_simulation_panel = use_signal(|| false);
-    let mut simulation_initialized = use_signal(|| false);
-    let mut simulation_is_paused = use_signal(|| true);
-    let mut vehicle_count = use_signal(|| Option::<usize>::None);
-    let mut load_bus_routes = use_signal(|| false);
-    let layers = use_signal(TflLayers::default);
-    let mut tfl_data = use_signal(TflDataRepository::default);
+/// Defaults to the bundled assets; a `?data=<base url>` query parameter points
+/// the app at an alternate dataset or a local stand-in for the API instead.
+#[cfg(target_arch = "wasm32")]
+fn data_source() -> AnySource {
+    use crate::data::UrlSource;
+
+    let base_url = web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .and_then(|params| params.get("data"))
+        .filter(|url| !url.is_empty());
+
+    match base_url {
+        Some(url) => {
+            log::info_with_category(LogCategory::App, &format!("Loading TfL data from {}", url));
+            AnySource::Url(UrlSource::new(url))
+        }
+        None => AnySource::default(),
+    }
+}
 
-    use_future(move || async move {
-        with_context("app::load_tfl_data", LogCategory::App, |logger| {
-            logger.info("Loading TfL station and platform data");
-
-            // Only load if not already loaded
-            if !tfl_data.read().is_loaded {
-                logger.info("Initializing TfL data repository");
-
-                // Use spawn_local for the async operation, but don't use logger inside
-                wasm_bindgen_futures::spawn_local(async move {
-                    let should_load_buses = *load_bus_routes.read();
-
-                    match TflDataRepository::initialize(should_load_buses).await {
-                        Ok(repository) => {
-                            log::info_with_category(
-                                LogCategory::App,
-                                &format!(
-                                    "TfL data loaded successfully with {} stations",
-                                    repository.stations.len()
-                                ),
-                            );
-                            tfl_data.set(repository);
-                        }
-                        Err(e) => {
-                            log::error_with_category(
-                                LogCategory::App,
-                                &format!("Failed to load TfL data: {}", e),
-                            );
-                        }
-                    }
-                });
-            } else {
-                logger.info("TfL data already loaded, skipping");
-            }
-        });
-    });
+/// A GTFS feed to show in place of the TfL data, if one is given.
+///
+/// A `?gtfs=<base url>` query parameter points at the feed's unzipped files.
+#[cfg(target_arch = "wasm32")]
+fn gtfs_source() -> Option<AnySource> {
+    use crate::data::UrlSource;
+
+    web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .and_then(|params| params.get("gtfs"))
+        .filter(|url| !url.is_empty())
+        .map(|url| AnySource::Url(UrlSource::new(url)))
+}
 
-    // Add an effect to reload data when bus routes toggle changes
-    use_effect(move || {
-        // Skip the initial render
-        static mut FIRST_RUN: bool = true;
-        let is_first_run = unsafe {
-            if FIRST_RUN {
-                FIRST_RUN = false;
-                true
-            } else {
-                false
-            }
-        };
+/// A GTFS feed to show in place of the TfL data, if one is given.
+///
+/// The `GTFS_FEED` environment variable names the feed's zip or directory.
+#[cfg(not(target_arch = "wasm32"))]
+fn gtfs_source() -> Option<AnySource> {
+    use crate::data::source::{FileSystemSource, ZipSource};
+
+    let path = std::path::PathBuf::from(std::env::var_os("GTFS_FEED")?);
+    if path.is_dir() {
+        Some(AnySource::FileSystem(FileSystemSource::new(path)))
+    } else {
+        Some(AnySource::Zip(ZipSource::new(path)))
+    }
+}
 
-        if !is_first_run {
-            with_context("app::reload_tfl_data", LogCategory::App, |logger| {
-                let should_load_buses = *load_bus_routes.read();
-                logger.info(&format!(
-                    "Bus routes toggle changed to {}",
-                    should_load_buses
-                ));
-
-                // Reload data
-                wasm_bindgen_futures::spawn_local(async move {
-                    match TflDataRepository::initialize(should_load_buses).await {
-                        Ok(repository) => {
-                            log::info_with_category(
-                                LogCategory::App,
-                                &format!(
-                                    "TfL data reloaded with bus routes: {}",
-                                    should_load_buses
-                                ),
-                            );
-                            tfl_data.set(repository);
-                        }
-                        Err(e) => {
-                            log::error_with_category(
-                                LogCategory::App,
-                                &format!("Failed to reload TfL data: {}", e),
-                            );
-                        }
-                    }
-                });
-            });
-        }
-    });
+/// How many days old the data can be before the About panel warns about it.
+///
+/// Defaults to [`DEFAULT_MAX_AGE_DAYS`]; a `?max_age=<days>` query parameter
+/// overrides it.
+#[cfg(target_arch = "wasm32")]
+fn max_data_age_days() -> u32 {
+    web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .and_then(|params| params.get("max_age"))
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_MAX_AGE_DAYS)
+}
 
-    // Add an effect to update the map when TFL data is loaded
-    use_effect(move || {
-        // Only react if the data is loaded
-        if tfl_data.read().is_loaded {
-            log::info_with_category(LogCategory::App, "TFL data loaded, updating map layers");
-
-            // Update the map with the TFL data
-            if let Some(manager) = window()
-                .and_then(|w| js_sys::Reflect::get(&w, &JsValue::from_str("mapInstance")).ok())
-            {
-                let map: crate::maplibre::bindings::Map = manager.clone().into();
-
-                // We need to check if the map style is loaded
-                if map.is_style_loaded() {
-                    log::info_with_category(
-                        LogCategory::App,
-                        "Map style loaded, adding TFL data layers",
-                    );
+/// How many days old the data can be before the About panel warns about it.
+///
+/// Defaults to [`DEFAULT_MAX_AGE_DAYS`]; the `TFL_DATA_MAX_AGE_DAYS`
+/// environment variable overrides it.
+#[cfg(not(target_arch = "wasm32"))]
+fn max_data_age_days() -> u32 {
+    std::env::var("TFL_DATA_MAX_AGE_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_MAX_AGE_DAYS)
+}
 
-                    // Call a helper function to add the TFL data to the map
-                    add_tfl_data_to_map(&map, tfl_data.read().clone());
-                } else {
-                    log::info_with_category(
-                        LogCategory::App,
-                        "Map style not loaded yet, waiting for 'load' event",
-                    );
+/// The seed to run the vehicle simulation from, if one is given; otherwise
+/// each run takes a fresh one.
+///
+/// A `?seed=<number>` query parameter replays a run from its seed.
+#[cfg(target_arch = "wasm32")]
+fn requested_simulation_seed() -> Option<u64> {
+    web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .and_then(|params| params.get("seed"))
+        .and_then(|seed| seed.parse().ok())
+}
 
-                    // Create a callback for the 'load' event
-                    let tfl_data_clone = tfl_data;
-                    let load_callback = Closure::wrap(Box::new(move || {
-                        log::info_with_category(
-                            LogCategory::App,
-                            "Map 'load' event fired, adding TFL data layers",
-                        );
-
-                        // If we get here via a callback, we need to get the map again
-                        if let Some(window) = window() {
-                            if let Ok(map_instance) =
-                                js_sys::Reflect::get(&window, &JsValue::from_str("mapInstance"))
-                            {
-                                let map: crate::maplibre::bindings::Map = map_instance.into();
-                                add_tfl_data_to_map(&map, tfl_data_clone.read().clone());
-                            }
-                        }
-                    }) as Box<dyn FnMut()>);
+/// The seed to run the vehicle simulation from, if one is given; otherwise
+/// each run takes a fresh one.
+///
+/// The `SIMULATION_SEED` environment variable replays a run from its seed.
+#[cfg(not(target_arch = "wasm32"))]
+fn requested_simulation_seed() -> Option<u64> {
+    std::env::var("SIMULATION_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+}
 
-                    // Register the callback
-                    map.on("load", &load_callback);
+/// Choose where to load the TfL data from.
+///
+/// Native builds read the files from disk, see [`crate::data::source::FileSystemSource`].
+#[cfg(not(target_arch = "wasm32"))]
+fn data_source() -> AnySource {
+    AnySource::default()
+}
 
-                    // Leak the callback to keep it alive
-                    load_callback.forget();
-                }
-            }
+/// The MapLibre map, drawn into a canvas in the page.
+///
+/// The panels are opened from the map's own controls, via [`browser`].
+#[cfg(target_arch = "wasm32")]
+fn map_view(
+    layers: Signal<TflLayers>,
+    tfl_data: Signal<TflDataRepository>,
+    _show_key_panel: Signal<bool>,
+    _show_layers_panel: Signal<bool>,
+    _show_simulation_panel: Signal<bool>,
+) -> Element {
+    rsx! { Canvas { layers: layers, tfl_data: tfl_data } }
+}
 
-            // Make the TfL data available to JavaScript for the simulation
-            let js_code = r#"
-                // Create a placeholder for TfL data
-                window.__tflData = true; // Simple flag to indicate data is available
-            "#;
-            let _ = js_sys::eval(js_code);
+/// Native builds have no MapLibre, so show what was loaded instead
+#[cfg(not(target_arch = "wasm32"))]
+fn map_view(
+    _layers: Signal<TflLayers>,
+    tfl_data: Signal<TflDataRepository>,
+    mut show_key_panel: Signal<bool>,
+    mut show_layers_panel: Signal<bool>,
+    mut show_simulation_panel: Signal<bool>,
+) -> Element {
+    rsx! {
+        DataSummary {
+            tfl_data: tfl_data,
+            on_show_key: move |_| show_key_panel.set(true),
+            on_show_layers: move |_| show_layers_panel.set(true),
+            on_show_simulation: move |_| show_simulation_panel.set(true),
         }
-    });
+    }
+}
 
-    // Initialize simulation JS when app loads
-    use_effect(move || {
-        with_context("app::simulation_init", LogCategory::App, |logger| {
-            // Only create the SimulationController if it doesn't already exist
-            if let Some(window) = window() {
-                if let Ok(simulation_controller) =
-                    js_sys::Reflect::get(&window, &JsValue::from_str("SimulationController"))
-                {
-                    if !simulation_controller.is_undefined() {
-                        // SimulationController already exists, no need to add the script
-                        // logger.debug("SimulationController already exists, skipping initialization");
-                        return;
-                    }
+/// Load whichever dataset stages haven't loaded yet, then update the
+/// repository.
+///
+/// The stages are read from the loader's dated snapshot, if it has one.
+/// Progress is reported to `progress` as each file streams in. If a stage
+/// fails, the stages that did load are kept, so calling this again retries
+/// just the failed one. Only one run loads at a time: buses switched on or
+/// off meanwhile are loaded or dropped by another run once it finishes.
+fn load_remaining_stages(
+    mut loader: Signal<RepositoryLoader>,
+    progress: Signal<LoadProgress>,
+    mut tfl_data: Signal<TflDataRepository>,
+) {
+    // Take the loader out of the signal, as it's needed across awaits
+    let Some(mut stages) = loader.write().start_run() else {
+        return;
+    };
+
+    // Spawn the async operation, but don't use logger inside
+    spawn(async move {
+        let on_event = move |event: StageEvent| {
+            let mut progress = progress;
+            progress.write().apply(event)
+        };
+
+        let source = SnapshotSource::new(data_source(), stages.snapshot().map(str::to_string));
+        let result = stages.run(&source, &on_event).await;
+        match &result {
+            Ok(()) => {
+                let first_load = !tfl_data.peek().is_loaded;
+                // Apply just what changed, e.g. adding the bus routes
+                stages.sync(&mut tfl_data.write());
+                if tfl_data.peek().services.is_none() {
+                    fetch_service_patterns(tfl_data);
+                }
+                if first_load {
+                    log_validation(&stages);
                 }
+                log::info_with_category(
+                    LogCategory::App,
+                    &format!(
+                        "TfL data loaded successfully with {} stations",
+                        tfl_data.peek().stations.len()
+                    ),
+                );
             }
-            logger.info("Initializing simulation controller script");
-
-            let controller_script = format!(
-                r#"
-                // Global simulation controller
-                const SimulationController = {{
-                  initialized: false,
-                  running: false,
-
-                  initialize: function() {{
-                    console.log("SimulationController.initialize() called");
-                    if (this.initialized) {{
-                      console.log("Simulation already initialized, skipping");
-                      return;
-                    }}
-
-                    // Call the Rust initialization function
-                    if (typeof window.rust_initialize_simulation === 'function') {{
-                      console.log("Calling rust_initialize_simulation()");
-                      window.rust_initialize_simulation();
-                      this.initialized = true;
-                      this.running = true;
-                    }} else {{
-                      console.error("rust_initialize_simulation function not found");
-                    }}
-                  }},
-
-                  toggle: function() {{
-                    console.log("SimulationController.toggle() called");
-                    if (!this.initialized) {{
-                      this.initialize();
-                      return;
-                    }}
-
-                    if (typeof window.rust_toggle_simulation === 'function') {{
-                      window.rust_toggle_simulation();
-                      this.running = !this.running;
-                      console.log("Simulation running:", this.running);
-                    }}
-                  }},
-
-                  reset: function() {{
-                    console.log("SimulationController.reset() called");
-                    if (typeof window.rust_reset_simulation === 'function') {{
-                      window.rust_reset_simulation();
-                      this.running = true;
-                      console.log("Simulation reset and running");
-                    }}
-                  }}
-                }};
-
-                // Make it globally available
-                window.SimulationController = SimulationController;
-
-                // Only initialize automatically if simulation is enabled
-                const simulationEnabled = {0};
-
-                if (simulationEnabled) {{
-                  // Initialize when map is ready
-                  if (window.mapInstance && window.mapInstance.isStyleLoaded()) {{
-                    setTimeout(function() {{
-                      SimulationController.initialize();
-                    }}, 1000);
-                  }} else {{
-                    const initInterval = setInterval(function() {{
-                      if (window.mapInstance && window.mapInstance.isStyleLoaded()) {{
-                        clearInterval(initInterval);
-                        setTimeout(function() {{
-                          SimulationController.initialize();
-                        }}, 1000);
-                      }}
-                    }}, 1000);
-                  }}
-                }} else {{
-                  console.log("Automatic simulation initialization disabled");
-                }}
-                "#,
-                layers.read().simulation
-            );
+            Err(e) => {
+                log::error_with_category(
+                    LogCategory::App,
+                    &format!("Failed to load TfL data: {}", e),
+                );
+            }
+        }
+
+        if loader.write().finish_run(stages) && result.is_ok() {
+            load_remaining_stages(loader, progress, tfl_data);
+        }
+    });
+}
 
-            if let Err(e) = helpers::add_inline_script(&controller_script) {
-                logger.error(&format!("Failed to add simulation script: {:?}", e));
-            } else {
-                logger.info("Simulation controller script added successfully");
+/// Load a GTFS feed in place of the TfL data, see [`crate::data::gtfs`]
+fn load_gtfs_feed(source: AnySource, mut tfl_data: Signal<TflDataRepository>) {
+    spawn(async move {
+        log::info_with_category(
+            LogCategory::App,
+            &format!("Loading GTFS feed from {}", source.describe()),
+        );
+        match GtfsFeed::load(&source).await {
+            Ok(feed) => {
+                let repository = gtfs::to_repository(&feed);
+                log::info_with_category(
+                    LogCategory::App,
+                    &format!(
+                        "GTFS feed loaded with {} stations and {} routes",
+                        repository.stations.len(),
+                        repository.routes.len()
+                    ),
+                );
+                tfl_data.set(repository);
             }
-        });
+            Err(e) => log::error_with_category(
+                LogCategory::App,
+                &format!("Failed to load GTFS feed: {}", e),
+            ),
+        }
     });
+}
 
-    use_effect(move || {
-        with_context("app::simulation_functions", LogCategory::App, |logger| {
-            logger.info("Exposing simulation functions");
+/// Log any integrity issues in the datasets just loaded, so that a bad data
+/// refresh shows up in the console and not only on the map
+fn log_validation(stages: &RepositoryLoader) {
+    let Some(report) = stages.validate(DEFAULT_MAX_STATION_DISTANCE) else {
+        return;
+    };
+
+    for issue in &report.issues {
+        let message = format!("Data issue: {}", issue);
+        match issue.severity() {
+            Severity::Error => log::warn_with_category(LogCategory::App, &message),
+            Severity::Warning => log::debug_with_category(LogCategory::App, &message),
+        }
+    }
+    if !report.is_empty() {
+        log::info_with_category(
+            LogCategory::App,
+            &format!("Dataset validation found {}", report.summary()),
+        );
+    }
+}
 
-            // Try to expose simulation functions if available
-            match simulation::expose_simulation_functions(Some(tfl_data.read().clone())) {
-                Ok(_) => {
-                    logger.info("Simulation functions exposed successfully");
-                }
-                Err(err) => {
-                    logger.error(&format!("Failed to expose simulation functions: {:?}", err));
-                }
+/// Load bus routes by ID, adding them to the repository together.
+///
+/// IDs are kept in `pending` while they load, so a route isn't fetched twice.
+/// If `keep` is given, the routes already loaded within it are marked as
+/// recently used first, so the new ones don't push them out of the cache.
+fn fetch_bus_routes(
+    ids: Vec<String>,
+    keep: Option<[f64; 4]>,
+    mut tfl_data: Signal<TflDataRepository>,
+    mut pending: Signal<HashSet<String>>,
+) {
+    pending.write().extend(ids.iter().cloned());
+
+    spawn(async move {
+        let source = data_source();
+        let mut routes = Vec::new();
+        for id in &ids {
+            match loader::load_bus_route(&source, id).await {
+                Ok(routes_file) => routes.push((id.clone(), routes_file)),
+                Err(e) => log::warn_with_category(
+                    LogCategory::App,
+                    &format!("Failed to load bus route {}: {}", id, e),
+                ),
             }
+        }
 
-            // Add the controller script
-            let controller_script = r#"
-    // SimulationController code here...
-    "#;
-
-            if let Err(e) = helpers::add_inline_script(controller_script) {
-                logger.error(&format!("Failed to add simulation script: {:?}", e));
-            } else {
-                logger.debug("Additional controller script added");
+        // One write for the batch, so the map is only updated once
+        {
+            let mut data = tfl_data.write();
+            if let Some(bounds) = keep {
+                data.keep_bus_routes_in(bounds);
             }
-        })
+            for (id, routes_file) in &routes {
+                data.add_bus_route(id, routes_file);
+            }
+        }
+        log::debug_with_category(
+            LogCategory::App,
+            &format!("Loaded {} of {} bus routes", routes.len(), ids.len()),
+        );
+
+        let mut pending = pending.write();
+        for id in &ids {
+            pending.remove(id);
+        }
     });
+}
 
-    // Add an effect to set up the simulation panel connection
-    use_effect(move || {
-        with_context(
-            "app::simulation_panel_connection",
-            LogCategory::App,
-            |logger| {
-                logger.info("Setting up simulation panel connection to JavaScript");
+/// Load the bus stops, adding them to the repository if buses are still on
+fn fetch_bus_stops(mut tfl_data: Signal<TflDataRepository>) {
+    spawn(async move {
+        match loader::load_bus_stops(&data_source()).await {
+            Ok(stops_file) => tfl_data.write().set_bus_stops(stops_file),
+            Err(e) => log::warn_with_category(
+                LogCategory::App,
+                &format!(
+                    "Failed to load bus stops, showing routes without them: {}",
+                    e
+                ),
+            ),
+        }
+    });
+}
 
-                // Create a clone of the signal for the closure
-                let mut show_sim = show_simulation_panel.clone();
+/// Read how often each line runs, for the simulation, see
+/// [`crate::data::services`]
+fn fetch_service_patterns(mut tfl_data: Signal<TflDataRepository>) {
+    spawn(async move {
+        match loader::load_service_patterns(&data_source()).await {
+            Ok(patterns) => tfl_data.write().services = Some(patterns),
+            Err(e) => log::warn_with_category(
+                LogCategory::App,
+                &format!(
+                    "Failed to load service patterns, simulating every line at the same headway: {}",
+                    e
+                ),
+            ),
+        }
+    });
+}
+
+/// Main application component.
+///
+/// This is the root component of the TfL Simulation application.
+#[component]
+pub fn app() -> Element {
+    let mut show_layers_panel = use_signal(|| false);
+    let mut show_key_panel = use_signal(|| false);
+    let mut show_simulation_panel = use_signal(|| false);
+    let mut show_about_panel = use_signal(|| false);
+    let max_age_days = use_signal(max_data_age_days);
+    let mut simulation_initialized = use_signal(|| false);
+    let mut simulation_is_paused = use_signal(|| true);
+    let vehicle_count = use_signal(|| Option::<usize>::None);
+    let held_count = use_signal(|| Option::<usize>::None);
+    let mut scenario = use_signal(simulation::scenario::Scenario::default);
+    let mut scenario_lines = use_signal(Vec::<simulation::scenario::LineStations>::new);
+    let simulation_clock = use_signal(|| Option::<String>::None);
+    let mut simulation_speed = use_signal(|| simulation::clock::DEFAULT_SPEED);
+    let mut simulation_start_time =
+        use_signal(|| simulation::clock::format_clock(simulation::clock::DEFAULT_START_TIME));
+    // The seed the current run started from, and the one to start the next from
+    let mut simulation_seed = use_signal(|| Option::<u64>::None);
+    let mut requested_seed = use_signal(|| {
+        let seed = requested_simulation_seed();
+        simulation::set_simulation_seed(seed);
+        seed
+    });
+    let load_bus_routes = use_signal(|| false);
+    let layers = use_signal(TflLayers::default);
+    let mut tfl_data = use_signal(TflDataRepository::default);
+    let mut repository_loader = use_signal(|| RepositoryLoader::new(false));
+    let mut load_progress = use_signal(LoadProgress::default);
+    // The map extent, while zoomed in far enough to show bus routes
+    let viewport = use_signal(|| Option::<[f64; 4]>::None);
+    let pending_bus_routes = use_signal(HashSet::<String>::new);
+    // Whether zoomed in far enough to show bus stops
+    let bus_stops_in_view = use_signal(|| false);
+    let mut bus_search_error = use_signal(|| Option::<String>::None);
+    let mut snapshot_index = use_signal(SnapshotIndex::default);
+    // The date the network is shown as of, or `None` for now
+    let mut network_date = use_signal(|| Option::<String>::None);
+    // When the current (not snapshot) data was fetched, to compare snapshots with
+    let mut current_data_date = use_signal(|| Option::<String>::None);
+    // A GTFS feed replaces the TfL data, bus routes and snapshots included
+    let gtfs = use_hook(gtfs_source);
+
+    let has_gtfs = gtfs.is_some();
+
+    use_future(move || {
+        let gtfs = gtfs.clone();
+        async move {
+            with_context("app::load_tfl_data", LogCategory::App, |logger| {
+                logger.info("Loading TfL station and platform data");
+
+                // Only load if not already loaded
+                if tfl_data.read().is_loaded {
+                    logger.info("TfL data already loaded, skipping");
+                } else if let Some(source) = gtfs {
+                    load_gtfs_feed(source, tfl_data);
+                } else {
+                    logger.info("Initializing TfL data repository");
+                    load_remaining_stages(repository_loader, load_progress, tfl_data);
+                }
+            });
+        }
+    });
 
-                // Create a closure that will open the simulation panel when called from JavaScript
-                let open_sim_callback = Closure::wrap(Box::new(move || {
+    // Find out which dated snapshots there are to pick from
+    use_future(move || async move {
+        if has_gtfs {
+            return;
+        }
+        match load_snapshot_index(&data_source()).await {
+            Ok(index) => {
+                if !index.snapshots.is_empty() {
                     log::info_with_category(
                         LogCategory::App,
-                        "openTflSimulationPanel called from JavaScript",
+                        &format!("{} dated snapshots available", index.snapshots.len()),
                     );
-                    show_sim.set(true);
-                }) as Box<dyn FnMut()>);
-
-                // Expose the closure to JavaScript
-                if let Some(window) = window() {
-                    if let Err(e) = js_sys::Reflect::set(
-                        &window,
-                        &JsValue::from_str("openTflSimulationPanel"),
-                        open_sim_callback.as_ref(),
-                    ) {
-                        logger.error(&format!("Failed to set openTflSimulationPanel: {:?}", e));
-                    } else {
-                        logger.info("Successfully exposed openTflSimulationPanel to JavaScript");
-                    }
                 }
-
-                // Forget the closure to prevent memory leaks
-                open_sim_callback.forget();
-            },
-        );
+                snapshot_index.set(index);
+            }
+            Err(e) => log::warn_with_category(
+                LogCategory::App,
+                &format!("Failed to load the snapshot index: {}", e),
+            ),
+        }
     });
 
-    // Add an effect to update the simulation vehicle count
+    // Remember when the current data was fetched, once it has loaded
     use_effect(move || {
-        let timer = std::time::Duration::from_secs(1);
+        let data = tfl_data.read();
+        if !data.is_loaded || data.snapshot.is_some() || current_data_date.peek().is_some() {
+            return;
+        }
+        if let Some(date) = fetched_on(&data.provenance) {
+            current_data_date.set(Some(date.to_string()));
+        }
+    });
 
-        let mut update_vehicle_count = move || {
-            if *show_simulation_panel.read() {
-                // Get the vehicle count from the simulation state
-                let count = simulation::with_simulation_state_ref(|state| state.vehicles.len());
-                vehicle_count.set(Some(count));
-            }
-        };
+    // Show the network as of a date, switching to the closest snapshot
+    let show_network_as_of = move |date: Option<String>| {
+        let snapshot = date.as_deref().and_then(|date| {
+            snapshot_index
+                .peek()
+                .choose(date, current_data_date.peek().as_deref())
+                .map(|entry| entry.date.clone())
+        });
+        network_date.set(date.clone());
 
-        // Set up an interval to update the vehicle count
-        let mut interval_handle = None;
-        if let Some(window) = window() {
-            let callback =
-                Closure::wrap(Box::new(update_vehicle_count.clone()) as Box<dyn FnMut()>);
-            if let Ok(handle) = window.set_interval_with_callback_and_timeout_and_arguments(
-                callback.as_ref().unchecked_ref(),
-                1000,
-                &js_sys::Array::new(),
-            ) {
-                interval_handle = Some(handle);
-            }
-            callback.forget();
+        // Routes are coloured when they're added, so add them all again
+        #[cfg(target_arch = "wasm32")]
+        browser::clear_tfl_layers();
+
+        if snapshot == tfl_data.peek().snapshot {
+            // Same data, only the line names and colours change
+            repository_loader.write().set_as_of(date.clone());
+            tfl_data.write().as_of = date;
+            return;
+        }
+
+        log::info_with_category(
+            LogCategory::App,
+            &format!(
+                "Switching to {} data",
+                snapshot.as_deref().unwrap_or("current")
+            ),
+        );
+        let load_buses = *load_bus_routes.peek();
+        repository_loader.set(RepositoryLoader::new(load_buses).with_snapshot(snapshot, date));
+        tfl_data.set(TflDataRepository::default());
+        load_progress.set(LoadProgress::default());
+        load_remaining_stages(repository_loader, load_progress, tfl_data);
+    };
+
+    // Add or remove the bus routes when the toggle changes. This also runs on
+    // the first render, when the toggle already matches the loader.
+    use_effect(move || {
+        let should_load_buses = *load_bus_routes.read();
+        if has_gtfs || repository_loader.peek().load_buses() == should_load_buses {
+            return;
         }
 
-        // Run the function once immediately
-        update_vehicle_count();
+        with_context("app::toggle_bus_routes", LogCategory::App, |logger| {
+            logger.info(&format!(
+                "Bus routes toggle changed to {}",
+                should_load_buses
+            ));
 
-        // Return cleanup function
-        (move || {
-            if let Some(handle) = interval_handle {
-                if let Some(window) = window() {
-                    window.clear_interval_with_handle(handle);
-                }
-            }
-        })()
+            // Only the bus routes stage runs, and only the first time they're wanted
+            repository_loader.write().set_load_buses(should_load_buses);
+            load_remaining_stages(repository_loader, load_progress, tfl_data);
+        });
     });
 
-    // Add an effect to connect the key panel (glorified onclick event handler)
+    // Load the bus routes that come into view
     use_effect(move || {
-        with_context("app::key_panel_connection", LogCategory::App, |logger| {
-            logger.info("Setting up key panel connection to JavaScript");
-
-            // Create a clone of the signal for the closure
-            let mut show_key = show_key_panel;
-
-            // Create a closure that will open the key panel when called from JavaScript
-            // Don't capture logger in this closure!
-            let open_key_callback = Closure::wrap(Box::new(move || {
-                // Use direct log calls instead of the captured logger
-                log::debug_with_category(
-                    LogCategory::App,
-                    "openTflKeyPanel called from JavaScript",
-                );
-                show_key.set(true);
-            }) as Box<dyn FnMut()>);
-
-            // Expose the closure to JavaScript
-            if let Some(window) = window() {
-                if let Err(e) = js_sys::Reflect::set(
-                    &window,
-                    &JsValue::from_str("openTflKeyPanel"),
-                    open_key_callback.as_ref(),
-                ) {
-                    logger.error(&format!("Failed to set openTflKeyPanel: {:?}", e));
-                } else {
-                    logger.info("Successfully exposed openTflKeyPanel to JavaScript");
-                }
-            }
+        let Some(bounds) = *viewport.read() else {
+            return;
+        };
 
-            // Forget the closure to prevent memory leaks
-            open_key_callback.forget();
-        });
+        let pending = pending_bus_routes.peek();
+        let to_load: Vec<String> = tfl_data
+            .peek()
+            .bus_routes_to_load(bounds)
+            .into_iter()
+            .filter(|id| !pending.contains(id))
+            .collect();
+        drop(pending);
+
+        if !to_load.is_empty() {
+            log::debug_with_category(
+                LogCategory::App,
+                &format!("Loading {} bus routes in view", to_load.len()),
+            );
+            fetch_bus_routes(to_load, Some(bounds), tfl_data, pending_bus_routes);
+        }
     });
 
-    // Add an effect to set up the layer panel connection
+    // Load the bus stops the first time they'd be shown, once per switching
+    // buses on
     use_effect(move || {
-        with_context("app::layer_panel_connection", LogCategory::App, |logger| {
-            logger.info("Setting up layer panel connection to JavaScript");
+        if !*bus_stops_in_view.read() || !tfl_data.read().wants_bus_stops() {
+            return;
+        }
+        log::debug_with_category(LogCategory::App, "Loading bus stops");
+        tfl_data.write().request_bus_stops();
+        fetch_bus_stops(tfl_data);
+    });
 
-            // Create a clone of the signal for the closure
-            let mut show_layers = show_layers_panel.clone();
+    // Look up a bus route by ID, loading it and showing it on the map
+    let search_bus_route = move |query: String| {
+        let data = tfl_data.peek();
+        let Some(route) = data.buses.as_ref().and_then(|buses| buses.find(&query)) else {
+            bus_search_error.set(Some(format!("No bus route \"{}\"", query.trim())));
+            return;
+        };
+        bus_search_error.set(None);
+
+        let id = route.id.clone();
+        #[cfg(target_arch = "wasm32")]
+        browser::fit_map_to_bbox(route.bbox);
+
+        let loaded = data
+            .buses
+            .as_ref()
+            .is_some_and(|buses| buses.is_loaded(&id));
+        drop(data);
+        if !loaded && !pending_bus_routes.peek().contains(&id) {
+            fetch_bus_routes(vec![id], None, tfl_data, pending_bus_routes);
+        }
+    };
+
+    // The map, simulation and panel openers all live in JS on the web
+    #[cfg(target_arch = "wasm32")]
+    browser::use_browser_bridges(browser::BridgedSignals {
+        tfl_data,
+        viewport,
+        bus_stops_in_view,
+        layers,
+        show_layers_panel,
+        show_key_panel,
+        show_simulation_panel,
+        vehicle_count,
+        held_count,
+        simulation_clock,
+    });
 
-            // Create a closure that will open the layer panel when called from JavaScript
-            let open_layer_panel_callback = Closure::wrap(Box::new(move || {
-                log::info_with_category(
-                    LogCategory::App,
-                    "openTflLayerPanel called from JavaScript",
-                );
-                show_layers.set(true);
-            }) as Box<dyn FnMut()>);
-
-            // Expose the closure to JavaScript
-            if let Some(window) = window() {
-                if let Err(e) = js_sys::Reflect::set(
-                    &window,
-                    &JsValue::from_str("openTflLayerPanel"),
-                    open_layer_panel_callback.as_ref(),
-                ) {
-                    logger.error(&format!("Failed to set openTflLayerPanel: {:?}", e));
-                } else {
-                    logger.info("Successfully exposed openTflLayerPanel to JavaScript");
+    // Without JS timers, drive the simulation from an async task instead
+    #[cfg(not(target_arch = "wasm32"))]
+    use_future(move || async move {
+        let mut vehicle_count = vehicle_count;
+        let mut held_count = held_count;
+        let mut simulation_clock = simulation_clock;
+        let tick = std::time::Duration::from_millis(simulation::TICK_INTERVAL_MS.into());
+        loop {
+            tokio::time::sleep(tick).await;
+            if *simulation_initialized.peek() {
+                simulation::step_simulation();
+                let count = Some(simulation::get_vehicle_count());
+                if *vehicle_count.peek() != count {
+                    vehicle_count.set(count);
+                }
+                let held = Some(simulation::get_held_count());
+                if *held_count.peek() != held {
+                    held_count.set(held);
                 }
+                simulation_clock.set(Some(simulation::simulation_clock()));
             }
-
-            // Forget the closure to prevent memory leaks
-            open_layer_panel_callback.forget();
-        });
+        }
     });
 
+    let data_is_stale = {
+        let now = crate::utils::time::now_seconds();
+        let max_age = *max_age_days.read();
+        tfl_data
+            .read()
+            .provenance
+            .iter()
+            .any(|dataset| dataset.is_stale(now, max_age))
+    };
+
+    let line_ids: Vec<String> = tfl_data.read().routes.keys().cloned().collect();
+    let as_of = tfl_data.read().as_of.clone();
+
     rsx! {
-        LineCss {}
+        LineCss { as_of: as_of.clone() }
 
         document::Link { rel: "icon", href: FAVICON }
         document::Link { rel: "stylesheet", href: MAIN_CSS }
@@ -534,6 +713,8 @@ pub fn app() -> Element {
         document::Link { rel: "stylesheet", href: KEY_CSS }
         document::Link { rel: "stylesheet", href: SIM_CSS }
         document::Link { rel: "stylesheet", href: LAYER_CSS }
+        document::Link { rel: "stylesheet", href: LOADING_CSS }
+        document::Link { rel: "stylesheet", href: ABOUT_CSS }
 
         header {
             img { src: LOGO_SVG }
@@ -541,43 +722,97 @@ pub fn app() -> Element {
 
             nav {
                 ul {
-                    li { a { href: "#", "About" } }
+                    li {
+                        a {
+                            href: "#",
+                            class: if data_is_stale { "stale" } else { "" },
+                            title: if data_is_stale { "The data is out of date" } else { "About the data" },
+                            onclick: move |event| {
+                                event.prevent_default();
+                                show_about_panel.set(true);
+                            },
+                            "About"
+                        }
+                    }
                     li { a { href: "#", "Stats" } }
                     li { a { href: "#", "Exports" } }
                 }
             }
+
+            NetworkDatePicker {
+                index: snapshot_index.read().clone(),
+                date: network_date.read().clone(),
+                snapshot: tfl_data.read().snapshot.clone(),
+                disabled: load_progress.read().is_loading(),
+                on_change: show_network_as_of,
+            }
         }
 
         main {
             class: "app-content",
 
-            // Main map container
-            Canvas { layers: layers, tfl_data: tfl_data }
+            LoadingOverlay { progress: load_progress.read().clone() }
+
+            if let Some((file, error)) = load_progress.read().failure() {
+                ErrorBanner {
+                    file: file,
+                    error: error.clone(),
+                    on_retry: move |_| {
+                        repository_loader.write().retry_skipped();
+                        load_remaining_stages(repository_loader, load_progress, tfl_data);
+                    }
+                }
+            }
+
+            // Main map container, or a summary of the loaded data where there is no map
+            {map_view(layers, tfl_data, show_key_panel, show_layers_panel, show_simulation_panel)}
 
             // Layer panel component - conditionally shown
             LayerPanel {
                 visible: *show_layers_panel.read(),
                 layers: layers,
                 load_bus_routes: load_bus_routes,
+                bus_search_error: bus_search_error.read().clone(),
+                on_search_bus_route: search_bus_route,
                 on_close: move |_| show_layers_panel.set(false)
             }
 
             // Key panel component - conditionally shown
             KeyPanel {
                 visible: *show_key_panel.read(),
+                line_ids: line_ids,
+                as_of: as_of,
+                line_details: tfl_data.read().line_details.clone(),
                 on_close: move |_| show_key_panel.set(false)
             }
 
+            AboutPanel {
+                visible: *show_about_panel.read(),
+                provenance: tfl_data.read().provenance.clone(),
+                max_age_days: max_age_days,
+                on_close: move |_| show_about_panel.set(false)
+            }
+
             SimulationPanel {
                 visible: *show_simulation_panel.read(),
                 is_paused: *simulation_is_paused.read(),
                 vehicle_count: *vehicle_count.read(),
+                held_count: *held_count.read(),
+                scenario: scenario.read().clone(),
+                scenario_lines: scenario_lines.read().clone(),
+                clock: simulation_clock.read().clone(),
+                speed: *simulation_speed.read(),
+                start_time: simulation_start_time.read().clone(),
+                seed: *simulation_seed.read(),
+                requested_seed: *requested_seed.read(),
                 on_close: move |_| show_simulation_panel.set(false),
                 on_toggle: move |_| {
                     // Check if simulation has been initialized
                     if !*simulation_initialized.read() {
                         // If not initialized, initialize it
                         simulation::initialize_simulation(Some(tfl_data.read().clone()));
+                        simulation_seed.set(Some(simulation::simulation_seed()));
+                        scenario_lines.set(simulation::simulation_network());
                         simulation_initialized.set(true);
                         simulation_is_paused.set(false); // Start running
                     } else {
@@ -590,143 +825,37 @@ pub fn app() -> Element {
                 on_reset: move |_| {
                     // Reset always initializes
                     simulation::reset_simulation(Some(tfl_data.read().clone()));
+                    simulation_seed.set(Some(simulation::simulation_seed()));
+                    scenario_lines.set(simulation::simulation_network());
                     simulation_initialized.set(true);
                     simulation_is_paused.set(false);
-                }
-            }
-        }
-    }
-}
-
-/// Helper function to add TFL data layers to an already initialized map
-fn add_tfl_data_to_map(map: &crate::maplibre::bindings::Map, tfl_data: TflDataRepository) {
-    with_context("add_tfl_data_to_map", LogCategory::Map, |logger| {
-        logger.info("Adding TFL data layers to map");
-
-        // Add route geometries from our new data
-        if let Ok(route_data) = crate::data::generate_all_route_geometries(&tfl_data) {
-            logger.info(&format!(
-                "Adding {} TFL route geometries to map",
-                route_data.len()
-            ));
-
-            for (line_id, route_geojson) in route_data {
-                logger.debug(&format!("Adding {} route geometry", line_id));
-                web_sys::console::log_1(&route_geojson);
-                let source_id = format!("{}-route", line_id);
-                let layer_id = format!("{}-route-layer", line_id);
-
-                // Make sure the layer doesn't already exist
-                if map.get_layer(&layer_id).is_none() {
-                    // Add the source
-                    map.add_source(&source_id, &route_geojson);
-
-                    // Get the appropriate color for this line
-                    let color = get_line_color(&line_id);
-
-                    let route_mode = tfl_data
-                        .routes
-                        .get(&line_id)
-                        .and_then(|directions| directions.values().next())
-                        .and_then(|response| response.first())
-                        .map(|route_sequence| route_sequence.mode.to_lowercase())
-                        .unwrap_or_else(|| "train".to_string());
-                    let width = if route_mode == "bus" { 0.0 } else { 3.0 };
-
-                    // Add the layer with a dashed style to distinguish from simplified line data
-                    if let Ok(route_layer) = create_line_layer(&layer_id, &source_id, &color, width)
-                    {
-                        map.add_layer(&route_layer);
-                        logger.debug(&format!("Added {} route geometry", line_id));
+                },
+                on_step: move |_| {
+                    let mut simulation_clock = simulation_clock;
+                    if *simulation_initialized.read() {
+                        simulation::single_step_simulation();
+                        simulation_clock.set(Some(simulation::simulation_clock()));
                     }
-                } else {
-                    logger.debug(&format!("{} route layer already exists, skipping", line_id));
-                }
-            }
-        } else {
-            logger.error("Failed to generate route geometries");
-        }
-
-        // Add all stations as a GeoJSON source
-        if let Ok(stations_geojson) = crate::data::stations_to_geojson(&tfl_data.stations) {
-            // Make sure the source doesn't already exist
-            if map.get_layer("tfl-stations-layer").is_none() {
-                logger.info(&format!(
-                    "Adding {} stations to map",
-                    tfl_data.stations.len()
-                ));
-
-                // Add the source
-                web_sys::console::log_1(&stations_geojson);
-                map.add_source("tfl-stations", &stations_geojson);
-
-                // Add a circle layer for the stations
-                if let Ok(stations_layer) =
-                    create_circle_layer("tfl-stations-layer", "tfl-stations")
-                {
-                    map.add_layer(&stations_layer);
-                    logger.debug("Added stations layer");
-                }
-
-                // Add a label layer for the stations
-                if let Ok(labels_layer) = create_label_layer("tfl-station-labels", "tfl-stations") {
-                    map.add_layer(&labels_layer);
-                    logger.debug("Added station labels layer");
+                },
+                on_speed: move |speed| {
+                    simulation::set_simulation_speed(speed);
+                    simulation_speed.set(speed);
+                },
+                on_start_time: move |time: String| {
+                    if let Some(seconds) = simulation::clock::parse_clock(&time) {
+                        simulation::set_simulation_start_time(seconds);
+                        simulation_start_time.set(time);
+                    }
+                },
+                on_seed: move |seed: Option<u64>| {
+                    simulation::set_simulation_seed(seed);
+                    requested_seed.set(seed);
+                },
+                on_scenario: move |next: simulation::scenario::Scenario| {
+                    simulation::set_simulation_scenario(next.clone());
+                    scenario.set(next);
                 }
-            } else {
-                logger.debug("Stations layer already exists, skipping");
             }
-        } else {
-            logger.error("Failed to convert stations to GeoJSON");
         }
-
-        // Commented out as this is deprecated: uncomment to see new lines before adding their routes
-        // // Add all tube lines (NB this is being incrementally deprecated)
-        // if let Ok(line_data) = crate::data::generate_all_line_data(&tfl_data) {
-        //     logger.info(&format!("Adding {} TFL lines to map", line_data.len()));
-
-        //     for (line_name, line_geojson, color) in line_data {
-        //         // Skip lines that have proper route data
-        //         match line_name.as_str() {
-        //             "bakerloo" | "central" | "circle" | "district" | "hammersmith-city"
-        //             | "jubilee" | "metropolitan" | "northern" | "piccadilly" | "victoria"
-        //             | "waterloo-city" | "elizabeth" | "thameslink" | "tram" | "dlr" | "london-cable-car" => {
-        //                 logger.debug(&format!(
-        //                     "Skipping {} line - using route data instead",
-        //                     line_name
-        //                 ));
-        //                 continue; // Skip this iteration
-        //             }
-        //             _ => {} // Process other lines normally
-        //         }
-        //         web_sys::console::log_1(&line_geojson);
-        //         let source_id = format!("{}-line", line_name);
-        //         let layer_id = format!("{}-line-layer", line_name);
-
-        //         // Make sure the layer doesn't already exist
-        //         if map.get_layer(&layer_id).is_none() {
-        //             // Add the source
-        //             map.add_source(&source_id, &line_geojson);
-
-        //             // Add the layer
-        //             if let Ok(line_layer) = create_line_layer(&layer_id, &source_id, &color, 4.0) {
-        //                 map.add_layer(&line_layer);
-        //                 // Anything set this way is invisible (so as to deprecate as we migratet to routes)
-        //                 map.set_layout_property(
-        //                     &layer_id,
-        //                     "visibility",
-        //                     &JsValue::from_str("none"),
-        //                 );
-        //                 logger.debug(&format!("Added {} line", line_name));
-        //             }
-        //         } else {
-        //             logger.debug(&format!("{} line already exists, skipping", line_name));
-        //         }
-        //     }
-        // } else {
-        //     logger.error("Failed to generate line data");
-        // }
-
-        logger.info("TFL data layers added to map");
-    });
+    }
 }
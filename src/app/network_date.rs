@@ -0,0 +1,73 @@
+use crate::data::line_definitions::alias_dates;
+use crate::data::snapshots::SnapshotIndex;
+use dioxus::prelude::*;
+
+/// Pick the date to show the network as of
+///
+/// `date` is the date picked, or `None` for the current network, and
+/// `snapshot` the dated snapshot shown for it. Without a snapshot, the current
+/// data is shown with its lines named as they were on the date, so the dates
+/// lines were renamed on are suggested. Picking a date is disabled while data
+/// is loading.
+#[component]
+pub fn NetworkDatePicker(
+    index: SnapshotIndex,
+    date: Option<String>,
+    snapshot: Option<String>,
+    disabled: bool,
+    on_change: EventHandler<Option<String>>,
+) -> Element {
+    let earliest = index
+        .snapshots
+        .first()
+        .map(|entry| entry.date.clone())
+        .unwrap_or_default();
+    let shown = snapshot.as_ref().map(|snapshot| {
+        let label = index
+            .snapshots
+            .iter()
+            .find(|entry| &entry.date == snapshot)
+            .and_then(|entry| entry.label.clone());
+        match label {
+            Some(label) => format!("Data from {}: {}", snapshot, label),
+            None => format!("Data from {}", snapshot),
+        }
+    });
+
+    rsx! {
+        div {
+            class: "network-date",
+
+            label {
+                "Network as of "
+                input {
+                    r#type: "date",
+                    min: "{earliest}",
+                    list: "network-dates",
+                    value: date.clone().unwrap_or_default(),
+                    disabled: disabled,
+                    onchange: move |event| {
+                        let value = event.value();
+                        on_change.call((!value.is_empty()).then_some(value));
+                    }
+                }
+            }
+            datalist {
+                id: "network-dates",
+                for suggested in index.snapshots.iter().map(|entry| entry.date.as_str()).chain(alias_dates()) {
+                    option { key: "{suggested}", value: "{suggested}" }
+                }
+            }
+            if date.is_some() {
+                button {
+                    disabled: disabled,
+                    onclick: move |_| on_change.call(None),
+                    "Today"
+                }
+            }
+            if let Some(shown) = shown {
+                p { class: "network-date-snapshot", "{shown}" }
+            }
+        }
+    }
+}
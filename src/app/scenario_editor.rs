@@ -0,0 +1,349 @@
+use crate::app::simulation::scenario::{LineStations, ReducedService, Scenario, Suspension};
+use crate::data::line_definitions::line_info_as_of;
+use dioxus::prelude::*;
+use std::collections::HashMap;
+
+/// Edit the disruptions the simulation runs with from the next reset
+///
+/// `lines` are the stations to choose from, undisrupted; they're empty until
+/// the simulation has first started. Scenarios are saved and loaded as JSON.
+#[component]
+pub fn ScenarioEditor(
+    scenario: Scenario,
+    lines: Vec<LineStations>,
+    on_change: EventHandler<Scenario>,
+) -> Element {
+    let mut line_id = use_signal(String::new);
+    let mut station = use_signal(String::new);
+    let mut section_to = use_signal(String::new);
+    let mut service_percent = use_signal(|| 50.0);
+    let mut message = use_signal(|| Option::<String>::None);
+
+    let names: HashMap<&str, &str> = lines
+        .iter()
+        .flat_map(|line| &line.stations)
+        .map(|(id, name)| (id.as_str(), name.as_str()))
+        .collect();
+    let station_name = |id: &str| names.get(id).copied().unwrap_or(id).to_string();
+    let line = lines
+        .iter()
+        .find(|line| line.line_id == *line_id.read())
+        .cloned();
+
+    // What the scenario disrupts, each with the scenario left without it
+    let mut items: Vec<(String, Scenario)> = Vec::new();
+    for (index, id) in scenario.closed_stations.iter().enumerate() {
+        let mut without = scenario.clone();
+        without.closed_stations.remove(index);
+        items.push((format!("{} closed", station_name(id)), without));
+    }
+    for (index, suspension) in scenario.suspended_sections.iter().enumerate() {
+        let mut without = scenario.clone();
+        without.suspended_sections.remove(index);
+        let text = format!(
+            "{} suspended between {} and {}",
+            line_name(&suspension.line_id),
+            station_name(&suspension.from),
+            station_name(&suspension.to)
+        );
+        items.push((text, without));
+    }
+    for (index, reduced) in scenario.reduced_services.iter().enumerate() {
+        let mut without = scenario.clone();
+        without.reduced_services.remove(index);
+        let text = format!(
+            "{} running {:.0}% of its trains",
+            line_name(&reduced.line_id),
+            reduced.service_level * 100.0
+        );
+        items.push((text, without));
+    }
+    for (index, id) in scenario.crossovers.iter().enumerate() {
+        let mut without = scenario.clone();
+        without.crossovers.remove(index);
+        items.push((
+            format!("Trains can turn back at {}", station_name(id)),
+            without,
+        ));
+    }
+
+    // The changes the chosen line and station make
+    let chosen = (*station.read()).clone();
+    let close_station = (!chosen.is_empty()).then(|| {
+        let mut scenario = scenario.clone();
+        scenario.closed_stations.push(chosen.clone());
+        scenario
+    });
+    let add_crossover = (!chosen.is_empty()).then(|| {
+        let mut scenario = scenario.clone();
+        scenario.crossovers.push(chosen.clone());
+        scenario
+    });
+    let to = (*section_to.read()).clone();
+    let suspend_section = (!chosen.is_empty() && !to.is_empty() && chosen != to).then(|| {
+        let mut scenario = scenario.clone();
+        scenario.suspended_sections.push(Suspension {
+            line_id: line_id.read().clone(),
+            from: chosen.clone(),
+            to: to.clone(),
+        });
+        scenario
+    });
+    let reduce_service = line.as_ref().map(|line| {
+        let mut scenario = scenario.clone();
+        scenario.reduced_services.push(ReducedService {
+            line_id: line.line_id.clone(),
+            service_level: *service_percent.read() / 100.0,
+        });
+        scenario
+    });
+
+    let file_name = format!("{}.json", file_stem(&scenario.name));
+    let json = scenario.to_json();
+    let renamed = scenario.clone();
+    let cleared = Scenario::default();
+
+    rsx! {
+        div {
+            class: "simulation-scenario",
+            h3 { "Disruption scenario" }
+
+            label {
+                "Name "
+                input {
+                    r#type: "text",
+                    placeholder: "Untitled",
+                    value: "{scenario.name}",
+                    onchange: move |event| {
+                        on_change.call(Scenario { name: event.value(), ..renamed.clone() })
+                    },
+                }
+            }
+
+            if lines.is_empty() {
+                p { class: "scenario-note", "Start the simulation to choose stations and lines." }
+            } else {
+                label {
+                    "Line "
+                    select {
+                        onchange: move |event| {
+                            line_id.set(event.value());
+                            station.set(String::new());
+                            section_to.set(String::new());
+                        },
+                        option { value: "", "Choose a line" }
+                        for line in lines.iter() {
+                            option {
+                                key: "{line.line_id}",
+                                value: "{line.line_id}",
+                                selected: line.line_id == *line_id.read(),
+                                {line_name(&line.line_id)}
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(line) = line {
+                div {
+                    class: "scenario-add",
+                    select {
+                        onchange: move |event| station.set(event.value()),
+                        option { value: "", "Station" }
+                        for (id, name) in line.stations.iter() {
+                            option { key: "{id}", value: "{id}", selected: *id == chosen, "{name}" }
+                        }
+                    }
+                    button {
+                        disabled: close_station.is_none(),
+                        onclick: move |_| {
+                            if let Some(scenario) = close_station.clone() {
+                                on_change.call(scenario);
+                            }
+                        },
+                        "Close"
+                    }
+                    button {
+                        disabled: add_crossover.is_none(),
+                        title: "Trains turned short may turn back here",
+                        onclick: move |_| {
+                            if let Some(scenario) = add_crossover.clone() {
+                                on_change.call(scenario);
+                            }
+                        },
+                        "Crossover"
+                    }
+                }
+                div {
+                    class: "scenario-add",
+                    "From there to "
+                    select {
+                        onchange: move |event| section_to.set(event.value()),
+                        option { value: "", "Station" }
+                        for (id, name) in line.stations.iter() {
+                            option { key: "{id}", value: "{id}", selected: *id == to, "{name}" }
+                        }
+                    }
+                    button {
+                        disabled: suspend_section.is_none(),
+                        onclick: move |_| {
+                            if let Some(scenario) = suspend_section.clone() {
+                                on_change.call(scenario);
+                            }
+                        },
+                        "Suspend"
+                    }
+                }
+                div {
+                    class: "scenario-add",
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        max: "100",
+                        step: "10",
+                        value: "{service_percent}",
+                        onchange: move |event| {
+                            if let Ok(percent) = event.value().parse::<f64>() {
+                                service_percent.set(percent.clamp(0.0, 100.0));
+                            }
+                        },
+                    }
+                    "% of trains "
+                    button {
+                        onclick: move |_| {
+                            if let Some(scenario) = reduce_service.clone() {
+                                on_change.call(scenario);
+                            }
+                        },
+                        "Reduce service"
+                    }
+                }
+            }
+
+            if items.is_empty() {
+                p { class: "scenario-note", "Nothing disrupted." }
+            } else {
+                ul {
+                    class: "scenario-items",
+                    for (index, (text, without)) in items.into_iter().enumerate() {
+                        li {
+                            key: "{index}",
+                            "{text}"
+                            button {
+                                title: "Remove",
+                                onclick: move |_| on_change.call(without.clone()),
+                                "×"
+                            }
+                        }
+                    }
+                }
+            }
+
+            div {
+                class: "scenario-files",
+                SaveScenario { file_name, json, message }
+                label {
+                    "Load "
+                    input {
+                        r#type: "file",
+                        accept: ".json,application/json",
+                        onchange: move |event| async move {
+                            let Some(files) = event.files() else {
+                                return;
+                            };
+                            for file in files.files() {
+                                let Some(text) = files.read_file_to_string(&file).await else {
+                                    message.set(Some(format!("Could not read {}", file)));
+                                    continue;
+                                };
+                                match Scenario::from_json(&file, &text) {
+                                    Ok(scenario) => {
+                                        message.set(None);
+                                        on_change.call(scenario);
+                                    }
+                                    Err(e) => message.set(Some(e.to_string())),
+                                }
+                            }
+                        },
+                    }
+                }
+                button {
+                    onclick: move |_| on_change.call(cleared.clone()),
+                    "Clear"
+                }
+            }
+            if let Some(message) = message.read().as_ref() {
+                p { class: "scenario-message", "{message}" }
+            }
+            p { class: "scenario-note", "(from the next reset)" }
+        }
+    }
+}
+
+/// Download the scenario as a JSON file
+#[cfg(target_arch = "wasm32")]
+#[component]
+fn SaveScenario(file_name: String, json: String, mut message: Signal<Option<String>>) -> Element {
+    rsx! {
+        a {
+            href: "data:application/json;charset=utf-8,{percent_encode(&json)}",
+            download: "{file_name}",
+            onclick: move |_| message.set(None),
+            "Save"
+        }
+    }
+}
+
+/// Write the scenario as a JSON file in the working directory
+#[cfg(not(target_arch = "wasm32"))]
+#[component]
+fn SaveScenario(file_name: String, json: String, mut message: Signal<Option<String>>) -> Element {
+    rsx! {
+        button {
+            onclick: move |_| {
+                let saved = match std::fs::write(&file_name, &json) {
+                    Ok(()) => format!("Saved to {}", file_name),
+                    Err(e) => format!("Could not save {}: {}", file_name, e),
+                };
+                message.set(Some(saved));
+            },
+            "Save"
+        }
+    }
+}
+
+fn line_name(line_id: &str) -> String {
+    line_info_as_of(line_id, None).map_or_else(|| line_id.to_string(), |info| info.name.to_string())
+}
+
+/// A file name for a scenario from its name
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    match stem.trim_matches('-') {
+        "" => "scenario".to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+/// Escape text for a `data:` URL
+#[cfg(target_arch = "wasm32")]
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
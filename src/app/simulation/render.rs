@@ -0,0 +1,534 @@
+//! Drawing the simulated vehicles on the MapLibre map and driving the
+//! animation from JavaScript timers.
+
+use super::{
+    TICK_INTERVAL_MS, clock, get_animation_frame_id, initialize_simulation, reset_simulation,
+    set_animation_frame_id, set_simulation_speed, set_simulation_start_time, simulation_clock,
+    single_step_simulation, step_simulation, toggle_simulation, with_simulation_state,
+    with_simulation_state_ref,
+};
+use crate::data::TflDataRepository;
+use crate::utils::geojson::{new_geojson_source, new_point_feature, to_js_value};
+use crate::utils::log::{self, LogCategory, with_context};
+use js_sys::{Object, Reflect};
+use my_map::simulation::Simulation;
+use my_map::simulation::model::VehicleType;
+use wasm_bindgen::{JsValue, closure::Closure};
+use web_sys::window;
+
+/// Expose initialization function globally
+// Expose Rust functions to JavaScript
+pub fn expose_simulation_functions(tfl_data: Option<TflDataRepository>) -> Result<(), JsValue> {
+    with_context(
+        "expose_simulation_functions",
+        LogCategory::Simulation,
+        |logger| {
+            logger.info("Exposing simulation functions to JavaScript");
+
+            // Clone tfl_data so the closure can be called more than once.
+            let tfl_data_for_closure = tfl_data.clone();
+            // Create initialize function
+            let init_closure = Closure::wrap(Box::new({
+                let tfl_data_inner = tfl_data_for_closure;
+                move || {
+                    log::info_with_category(
+                        LogCategory::Simulation,
+                        "rust_initialize_simulation called from JS",
+                    );
+                    initialize_simulation(tfl_data_inner.clone());
+                }
+            }) as Box<dyn FnMut()>);
+
+            // Create toggle function
+            let toggle_closure = Closure::wrap(Box::new(|| {
+                log::info_with_category(
+                    LogCategory::Simulation,
+                    "rust_toggle_simulation called from JS",
+                );
+                toggle_simulation();
+            }) as Box<dyn FnMut()>);
+
+            // Clone tfl_data for reset closure too
+            let tfl_data_for_reset = tfl_data.clone();
+            // Create reset function
+            let reset_closure = Closure::wrap(Box::new({
+                let tfl_data_inner = tfl_data_for_reset;
+                move || {
+                    log::info_with_category(
+                        LogCategory::Simulation,
+                        "rust_reset_simulation called from JS",
+                    );
+                    reset_simulation(tfl_data_inner.clone());
+                }
+            }) as Box<dyn FnMut()>);
+
+            // Create the dwell time setter, taking a mode and seconds
+            let dwell_closure = Closure::wrap(Box::new(|mode: String, seconds: f64| {
+                log::info_with_category(
+                    LogCategory::Simulation,
+                    &format!("Setting the dwell time for {} to {}s", mode, seconds),
+                );
+                with_simulation_state(|sim_state| {
+                    sim_state.simulation.profiles.set_dwell_secs(&mode, seconds)
+                });
+            }) as Box<dyn FnMut(String, f64)>);
+
+            // Create the clock controls: its speed, a single step, the start
+            // time as `HH:MM`, and reading the time
+            let speed_closure = Closure::wrap(Box::new(|speed: f64| {
+                set_simulation_speed(speed);
+            }) as Box<dyn FnMut(f64)>);
+            let step_closure = Closure::wrap(Box::new(|| {
+                single_step_simulation();
+            }) as Box<dyn FnMut()>);
+            let start_time_closure =
+                Closure::wrap(Box::new(|time: String| match clock::parse_clock(&time) {
+                    Some(time) => set_simulation_start_time(time),
+                    None => log::warn_with_category(
+                        LogCategory::Simulation,
+                        &format!("Not a time of day: {}", time),
+                    ),
+                }) as Box<dyn FnMut(String)>);
+            let clock_closure =
+                Closure::wrap(Box::new(simulation_clock) as Box<dyn FnMut() -> String>);
+
+            // Set them on the window object
+            if let Some(window) = window() {
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_initialize_simulation"),
+                    init_closure.as_ref(),
+                )
+                .expect("Could not set rust_initialize_simulation");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_toggle_simulation"),
+                    toggle_closure.as_ref(),
+                )
+                .expect("Could not set rust_toggle_simulation");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_reset_simulation"),
+                    reset_closure.as_ref(),
+                )
+                .expect("Could not set rust_reset_simulation");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_set_dwell_time"),
+                    dwell_closure.as_ref(),
+                )
+                .expect("Could not set rust_set_dwell_time");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_set_simulation_speed"),
+                    speed_closure.as_ref(),
+                )
+                .expect("Could not set rust_set_simulation_speed");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_step_simulation"),
+                    step_closure.as_ref(),
+                )
+                .expect("Could not set rust_step_simulation");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_set_simulation_start_time"),
+                    start_time_closure.as_ref(),
+                )
+                .expect("Could not set rust_set_simulation_start_time");
+
+                js_sys::Reflect::set(
+                    &window,
+                    &JsValue::from_str("rust_simulation_clock"),
+                    clock_closure.as_ref(),
+                )
+                .expect("Could not set rust_simulation_clock");
+
+                logger.info("Simulation functions exposed to JavaScript");
+            }
+
+            // Leak the closures (they will live for the lifetime of the page)
+            init_closure.forget();
+            toggle_closure.forget();
+            reset_closure.forget();
+            dwell_closure.forget();
+            speed_closure.forget();
+            step_closure.forget();
+            start_time_closure.forget();
+            clock_closure.forget();
+
+            Ok(())
+        },
+    )
+}
+
+/// Set a global flag to track simulation visibility
+pub fn set_simulation_visible() {
+    let js_code = r#"
+    window.simulationVisible = true;
+    console.log('Set window.simulationVisible = true');
+    "#;
+    let _ = js_sys::eval(js_code);
+}
+
+/// Cancel the current animation interval if one is active
+pub fn stop_animation_loop() {
+    if let Some(id) = get_animation_frame_id() {
+        let clear_js = format!(
+            r#"
+            if (window.__rustAnimIntervalId) {{
+                clearInterval(window.__rustAnimIntervalId);
+                window.__rustAnimIntervalId = null;
+                console.log("Cleared animation interval: {}")
+            }}
+        "#,
+            id
+        );
+        let _ = js_sys::eval(&clear_js);
+
+        log::debug_with_category(
+            LogCategory::Simulation,
+            &format!("Canceled animation interval ID: {}", id),
+        );
+    }
+}
+
+/// Register vehicle layers with MapLibre GL
+pub fn register_vehicle_layers() {
+    with_context(
+        "register_vehicle_layers",
+        LogCategory::Simulation,
+        |logger| {
+            logger.info("Registering vehicle layers with MapLibre");
+
+            // Get the map instance from window
+            if let Some(window) = window() {
+                if let Ok(map_instance) =
+                    js_sys::Reflect::get(&window, &JsValue::from_str("mapInstance"))
+                {
+                    let map: crate::maplibre::bindings::Map = map_instance.into();
+
+                    // Check if source already exists by checking if layer exists
+                    if map.get_layer("buses-layer").is_none() {
+                        // Create an empty source using our utility function
+                        let geojson_source = new_geojson_source(Vec::new());
+
+                        // Serialize to JsValue
+                        match to_js_value(&geojson_source) {
+                            Ok(source_js) => {
+                                // Add the source
+                                map.add_source("vehicles-source", &source_js);
+
+                                // Create and add bus layer
+                                let bus_layer = create_vehicle_layer("buses-layer", "Bus");
+                                map.add_layer(&bus_layer);
+
+                                // Create and add train layer
+                                let train_layer = create_vehicle_layer("trains-layer", "Train");
+                                map.add_layer(&train_layer);
+
+                                logger.info("Vehicle layers successfully added");
+                            }
+                            Err(err) => {
+                                logger
+                                    .error(&format!("Failed to create GeoJSON source: {:?}", err));
+                            }
+                        }
+                    } else {
+                        logger.info("Vehicle layers already exist, skipping creation");
+                    }
+                } else {
+                    logger.error("Could not get mapInstance from window");
+                }
+            } else {
+                logger.error("Window object not available");
+            }
+        },
+    )
+}
+
+/// Helper function to create a vehicle layer specification
+fn create_vehicle_layer(id: &str, vehicle_type: &str) -> JsValue {
+    let layer = Object::new();
+
+    // Set basic properties
+    Reflect::set(&layer, &JsValue::from_str("id"), &JsValue::from_str(id)).unwrap();
+    Reflect::set(
+        &layer,
+        &JsValue::from_str("type"),
+        &JsValue::from_str("circle"),
+    )
+    .unwrap();
+    Reflect::set(
+        &layer,
+        &JsValue::from_str("source"),
+        &JsValue::from_str("vehicles-source"),
+    )
+    .unwrap();
+
+    // Add filter for vehicle type
+    let filter = js_sys::Array::new();
+    filter.push(&JsValue::from_str("=="));
+
+    let get_expr = js_sys::Array::new();
+    get_expr.push(&JsValue::from_str("get"));
+    get_expr.push(&JsValue::from_str("vehicleType"));
+
+    filter.push(&get_expr);
+    filter.push(&JsValue::from_str(vehicle_type));
+
+    Reflect::set(&layer, &JsValue::from_str("filter"), &filter).unwrap();
+
+    // Add paint properties
+    let paint = Object::new();
+
+    Reflect::set(
+        &paint,
+        &JsValue::from_str("circle-radius"),
+        &JsValue::from_f64(6.0),
+    )
+    .unwrap();
+    // Use the color property directly
+    let color_expr = js_sys::Array::new();
+    color_expr.push(&JsValue::from_str("get"));
+    color_expr.push(&JsValue::from_str("lineColor"));
+
+    Reflect::set(&paint, &JsValue::from_str("circle-color"), &color_expr).unwrap();
+    Reflect::set(
+        &paint,
+        &JsValue::from_str("circle-stroke-color"),
+        &JsValue::from_str("#FFFFFF"),
+    )
+    .unwrap();
+    Reflect::set(
+        &paint,
+        &JsValue::from_str("circle-stroke-width"),
+        &JsValue::from_f64(2.0),
+    )
+    .unwrap();
+
+    Reflect::set(&layer, &JsValue::from_str("paint"), &paint).unwrap();
+
+    layer.into()
+}
+
+/// Start the animation loop for vehicle movement with throttled updates
+pub fn start_animation_loop() {
+    with_context("start_animation_loop", LogCategory::Simulation, |logger| {
+        logger.debug("Starting throttled animation loop for vehicle movement");
+
+        // Set a fixed interval for updates instead of using requestAnimationFrame
+        let update_interval_ms = TICK_INTERVAL_MS;
+
+        // Create a JavaScript setInterval to handle the animation loop
+        let js_code = format!(
+            r#"
+            // Clear any existing interval
+            if (window.__rustAnimIntervalId) {{
+                clearInterval(window.__rustAnimIntervalId);
+            }}
+
+            // Set new interval for animation updates
+            window.__rustAnimIntervalId = setInterval(function() {{
+                // Only call the Rust function if defined
+                if (typeof window.rust_animation_tick === 'function') {{
+                    window.rust_animation_tick();
+                }}
+            }}, {});
+
+            // Return the interval ID
+            window.__rustAnimIntervalId;
+        "#,
+            update_interval_ms
+        );
+
+        // Execute the JavaScript to start the interval
+        let interval_id = js_sys::eval(&js_code)
+            .unwrap_or(JsValue::from_f64(0.0))
+            .as_f64()
+            .unwrap_or(0.0) as i32;
+
+        // Store the interval ID where animation frame ID would normally go
+        set_animation_frame_id(interval_id);
+
+        // Create the animation tick function
+        let tick_closure = Closure::wrap(Box::new(move || {
+            // Process a single animation frame
+            step_simulation();
+
+            let should_continue = with_simulation_state_ref(|sim_state| {
+                if !sim_state.is_paused {
+                    // Update MapLibre with new positions
+                    update_maplibre_vehicles(&sim_state.simulation);
+                }
+
+                // Return true to keep the interval running
+                // The actual pause state is checked on each tick
+                true
+            });
+
+            // If the simulation should be completely stopped (not just paused)
+            if !should_continue {
+                // Clear the interval
+                let clear_js = r#"
+                    if (window.__rustAnimIntervalId) {
+                        clearInterval(window.__rustAnimIntervalId);
+                        window.__rustAnimIntervalId = null;
+                    }
+                "#;
+                let _ = js_sys::eval(clear_js);
+            }
+        }) as Box<dyn FnMut()>);
+
+        // Store the tick function in the window object
+        if let Some(window) = window() {
+            let _ = js_sys::Reflect::set(
+                &window,
+                &JsValue::from_str("rust_animation_tick"),
+                tick_closure.as_ref(),
+            );
+
+            // Forget the closure so it stays valid
+            tick_closure.forget();
+
+            logger.info(&format!(
+                "Animation loop started with throttled updates at {} FPS",
+                1000 / update_interval_ms
+            ));
+        } else {
+            logger.error("No global window exists, cannot start animation loop");
+        }
+    })
+}
+
+/// Redraw the vehicles where they are now, e.g. after a single step while
+/// the animation is paused
+pub fn refresh_vehicles() {
+    with_simulation_state_ref(|sim_state| update_maplibre_vehicles(&sim_state.simulation));
+}
+
+/// Update MapLibre with the current vehicle positions
+fn update_maplibre_vehicles(simulation: &Simulation) {
+    // This function is called less frequently now - adjust logging frequency
+    static mut MAPLIBRE_UPDATE_COUNTER: u32 = 0;
+    let should_log = unsafe {
+        MAPLIBRE_UPDATE_COUNTER += 1;
+        MAPLIBRE_UPDATE_COUNTER.is_multiple_of(150) // Log roughly every 10 seconds (assuming 15fps)
+    };
+
+    if should_log {
+        log::debug_with_category(
+            LogCategory::Simulation,
+            &format!(
+                "Updating MapLibre with {} vehicle positions",
+                simulation.vehicles.len()
+            ),
+        );
+    }
+
+    // Create features for all vehicles
+    let features: Vec<_> = simulation
+        .vehicles
+        .iter()
+        .map(|vehicle| {
+            let vehicle_type = match vehicle.vehicle_type {
+                VehicleType::Bus => "Bus",
+                VehicleType::Train => "Train",
+            };
+
+            // Determine color based on vehicle type
+            let color = match vehicle.vehicle_type {
+                VehicleType::Bus => "#0000FF".to_string(), // Blue for buses
+                VehicleType::Train => {
+                    // For trains, the colour of the line the route is on
+                    simulation
+                        .routes
+                        .get(vehicle.route_index)
+                        .map(|route| route.color.clone())
+                        .unwrap_or_else(|| "#777777".to_string())
+                }
+            };
+
+            // Which way round it goes, on a route with a loop
+            let rail = simulation
+                .routes
+                .get(vehicle.route_index)
+                .and_then(|route| route.loop_span)
+                .map(|span| span.rail.as_str());
+
+            // Create properties for this vehicle
+            let properties = serde_json::json!({
+                "id": vehicle.id,
+                "vehicleType": vehicle_type,
+                "lineId": vehicle.line_id,
+                "lineColor": color,
+                "lastStation": vehicle.last_station,
+                "nextStation": vehicle.next_station,
+                "rail": rail
+            });
+
+            // Create a point feature
+            new_point_feature(vehicle.lng, vehicle.lat, properties)
+        })
+        .collect();
+
+    // Create the GeoJSON source
+    let geojson_source = new_geojson_source(features);
+
+    // Try to get the map instance and update the source
+    let Some(window) = window() else {
+        return;
+    };
+    if js_sys::Reflect::get(&window, &JsValue::from_str("mapInstance")).is_err() {
+        return;
+    }
+
+    // Check if the source exists using JS eval for now
+    let has_source = js_sys::eval(
+        "window.mapInstance && window.mapInstance.getSource('vehicles-source') != null",
+    )
+    .unwrap_or(JsValue::from_bool(false))
+    .as_bool()
+    .unwrap_or(false);
+
+    if has_source {
+        // Serialize to JsValue - use .data to get just the FeatureCollection
+        match to_js_value(&geojson_source.data) {
+            Ok(data) => {
+                // Update the source data
+                let js_code = r#"
+                    window.mapInstance.getSource('vehicles-source').setData(arguments[0]);
+                "#;
+
+                if let Err(err) =
+                    js_sys::Function::new_with_args("data", js_code).call1(&JsValue::NULL, &data)
+                    && should_log
+                {
+                    log::error_with_category(
+                        LogCategory::Simulation,
+                        &format!("Failed to update vehicle source: {:?}", err),
+                    );
+                }
+            }
+            Err(err) => {
+                if should_log {
+                    log::error_with_category(
+                        LogCategory::Simulation,
+                        &format!("Failed to serialize vehicle data: {:?}", err),
+                    );
+                }
+            }
+        }
+    } else if should_log {
+        log::warn_with_category(
+            LogCategory::Simulation,
+            "Vehicles source does not exist yet",
+        );
+    }
+}
@@ -1,12 +1,21 @@
-use super::model::{Route, Vehicle};
 use crate::utils::log::{LogCategory, with_context};
+use my_map::simulation::Simulation;
+use my_map::simulation::scenario::{LineStations, Scenario};
 use std::cell::RefCell;
 
-/// Core shared state that contains all vehicles and simulation data
+/// The app's simulation, shared by the panel and the JavaScript callbacks
 #[derive(Default)]
 pub struct SimulationState {
-    pub vehicles: Vec<Vehicle>,
-    pub routes: Vec<Route>,
+    /// The engine, replaced on each reset
+    pub simulation: Simulation,
+    /// Seed to start the next run from, kept when the simulation is reset, or
+    /// `None` for a fresh one each time
+    pub requested_seed: Option<u64>,
+    /// Disruptions to run with from the next reset, kept when the
+    /// simulation is reset
+    pub scenario: Scenario,
+    /// Each line's stations before any disruption, for the scenario editor
+    pub network: Vec<LineStations>,
     pub is_paused: bool,
     pub animation_frame_id: Option<i32>,
 }
@@ -16,20 +25,19 @@ thread_local! {
     static SIMULATION_STATE: RefCell<SimulationState> = RefCell::new(SimulationState::default());
 }
 
-/// Initialize the simulation state with vehicles and routes
-pub fn initialize_state(routes: Vec<Route>, vehicles: Vec<Vehicle>) {
+/// Run a new simulation in place of the last
+pub fn initialize_state(simulation: Simulation) {
     with_context("initialize_state", LogCategory::Simulation, |logger| {
         logger.info(&format!(
-            "Initializing simulation state with {} routes and {} vehicles",
-            routes.len(),
-            vehicles.len()
+            "Initializing simulation state with {} routes and {} services",
+            simulation.routes.len(),
+            simulation.services.len()
         ));
 
         // Store in global state
         SIMULATION_STATE.with(|state| {
             let mut sim_state = state.borrow_mut();
-            sim_state.routes = routes;
-            sim_state.vehicles = vehicles;
+            sim_state.simulation = simulation;
             sim_state.is_paused = false;
             sim_state.animation_frame_id = None;
         });
@@ -37,6 +45,7 @@ pub fn initialize_state(routes: Vec<Route>, vehicles: Vec<Vehicle>) {
 }
 
 /// Set the animation frame ID in the state
+#[cfg(target_arch = "wasm32")]
 pub fn set_animation_frame_id(id: i32) {
     SIMULATION_STATE.with(|state| {
         let mut sim_state = state.borrow_mut();
@@ -53,14 +62,8 @@ pub fn toggle_pause() -> bool {
     })
 }
 
-pub fn is_paused() -> bool {
-    SIMULATION_STATE.with(|state| {
-        let sim_state = state.borrow();
-        sim_state.is_paused
-    })
-}
-
 /// Get a reference to the current animation frame ID if it exists
+#[cfg(target_arch = "wasm32")]
 pub fn get_animation_frame_id() -> Option<i32> {
     SIMULATION_STATE.with(|state| {
         let sim_state = state.borrow();
@@ -92,75 +95,10 @@ where
 
 /// Get the vehicle count
 pub fn get_vehicle_count() -> usize {
-    with_simulation_state_ref(|state| state.vehicles.len())
+    with_simulation_state_ref(|state| state.simulation.vehicles.len())
 }
 
-/// Debug function to log important simulation state
-pub fn debug_simulation_state() {
-    // Only log periodically to avoid console spam
-    static mut COUNTER: u32 = 0;
-    unsafe {
-        COUNTER += 1;
-        if COUNTER % 60 != 0 {
-            // Log every ~60 frames (roughly 1 second)
-            return;
-        }
-    }
-
-    with_simulation_state_ref(|sim_state| {
-        with_context(
-            "debug_simulation_state",
-            LogCategory::Simulation,
-            |logger| {
-                // Log general state
-                logger.debug(&format!(
-                    "Simulation state: {} vehicles, paused: {}",
-                    sim_state.vehicles.len(),
-                    sim_state.is_paused
-                ));
-
-                // Log a sample vehicle
-                if !sim_state.vehicles.is_empty() {
-                    let sample = &sim_state.vehicles[0];
-                    logger.debug(&format!(
-                        "Sample vehicle: id={}, type={:?}, pos=({:.4}, {:.4})",
-                        sample.id, sample.vehicle_type, sample.lng, sample.lat
-                    ));
-                }
-
-                // Check if vehicles source exists and log its state
-                let js_code = r#"
-            let result = "unknown";
-            if (window.mapInstance) {
-                const source = window.mapInstance.getSource('vehicles-source');
-                if (source) {
-                    try {
-                        const data = source._data;
-                        const features = data.features || [];
-                        result = `Source exists, ${features.length} features`;
-                    } catch (e) {
-                        result = `Source exists but error: ${e.message}`;
-                    }
-                } else {
-                    result = "Source does not exist";
-                }
-            } else {
-                result = "Map instance not found";
-            }
-            result;
-            "#;
-
-                match js_sys::eval(js_code) {
-                    Ok(result) => {
-                        if let Some(result_str) = result.as_string() {
-                            logger.debug(&format!("Vehicles source check: {}", result_str));
-                        }
-                    }
-                    Err(err) => {
-                        logger.error(&format!("Failed to check vehicles source: {:?}", err));
-                    }
-                }
-            },
-        )
-    });
+/// Get how many trains are held at signals
+pub fn get_held_count() -> usize {
+    with_simulation_state_ref(|state| state.simulation.held_vehicles())
 }
@@ -1,3 +1,6 @@
+use crate::app::scenario_editor::ScenarioEditor;
+use crate::app::simulation::clock::SPEEDS;
+use crate::app::simulation::scenario::{LineStations, Scenario};
 use dioxus::prelude::*;
 
 #[component]
@@ -6,8 +9,28 @@ pub fn SimulationPanel(
     on_close: EventHandler<()>,
     on_toggle: EventHandler<()>,
     on_reset: EventHandler<()>,
+    on_step: EventHandler<()>,
+    on_speed: EventHandler<f64>,
+    on_start_time: EventHandler<String>,
+    on_seed: EventHandler<Option<u64>>,
+    on_scenario: EventHandler<Scenario>,
     is_paused: bool,
     vehicle_count: Option<usize>,
+    /// Trains stopped at signals for the block ahead to clear
+    held_count: Option<usize>,
+    /// Simulated time of day, once the simulation has started
+    clock: Option<String>,
+    speed: f64,
+    /// `HH:MM` the simulation starts at when it's next reset
+    start_time: String,
+    /// Seed the current run started from, once the simulation has started
+    seed: Option<u64>,
+    /// Seed to start the next run from, or `None` for a fresh one
+    requested_seed: Option<u64>,
+    /// Disruptions to run with from the next reset
+    scenario: Scenario,
+    /// Each line's stations, to choose what the scenario disrupts
+    scenario_lines: Vec<LineStations>,
 ) -> Element {
     rsx! {
         div {
@@ -39,6 +62,63 @@ pub fn SimulationPanel(
                         onclick: move |_| on_reset.call(()),
                         "Reset"
                     }
+
+                    button {
+                        id: "step-simulation",
+                        disabled: !is_paused,
+                        title: "Move on 10 simulated seconds",
+                        onclick: move |_| on_step.call(()),
+                        "Step"
+                    }
+                }
+
+                div {
+                    class: "simulation-clock",
+                    span {
+                        class: "simulation-time",
+                        {clock.unwrap_or_else(|| "--:--:--".to_string())}
+                    }
+                    div {
+                        class: "simulation-speeds",
+                        for option in SPEEDS {
+                            button {
+                                key: "{option}",
+                                class: if option == speed { "active" } else { "" },
+                                onclick: move |_| on_speed.call(option),
+                                "{option}×"
+                            }
+                        }
+                    }
+                }
+
+                label {
+                    class: "simulation-start-time",
+                    "Start at "
+                    input {
+                        r#type: "time",
+                        value: "{start_time}",
+                        onchange: move |event| on_start_time.call(event.value()),
+                    }
+                    span { " (from the next reset)" }
+                }
+
+                label {
+                    class: "simulation-seed",
+                    "Seed "
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        placeholder: "random",
+                        value: requested_seed.map(|seed| seed.to_string()).unwrap_or_default(),
+                        onchange: move |event| on_seed.call(event.value().trim().parse().ok()),
+                    }
+                    span { " (from the next reset)" }
+                }
+
+                ScenarioEditor {
+                    scenario: scenario,
+                    lines: scenario_lines,
+                    on_change: move |scenario| on_scenario.call(scenario),
                 }
 
                 // Simulation information
@@ -51,6 +131,19 @@ pub fn SimulationPanel(
                         div {
                             class: "vehicle-stats",
                             p { "Active vehicles: {count}" }
+                            if let Some(held) = held_count {
+                                p {
+                                    class: if held > 0 { "held-count held" } else { "held-count" },
+                                    "Held at signals: {held}"
+                                }
+                            }
+                        }
+                    }
+
+                    if let Some(seed) = seed {
+                        p {
+                            class: "simulation-run-seed",
+                            "Running from seed {seed}"
                         }
                     }
 
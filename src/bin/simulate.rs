@@ -0,0 +1,219 @@
+//! Headless runner for the vehicle simulation.
+//!
+//! Runs the simulation over the bundled datasets (or another copy of them)
+//! for a number of simulated hours, as fast as it can, and writes what
+//! happened as JSON, in total and by line:
+//!
+//! ```sh
+//! cargo run --bin simulate -- HOURS [--data DIR] [--start HH:MM] [--seed N] [--step SECONDS] [--scenario FILE] [--out FILE]
+//! ```
+//!
+//! `--data` defaults to `assets/data`, `--start` to 08:00 and `--step` to a
+//! second. Without `--seed` the run takes a fresh one, which the report
+//! gives so the run can be repeated exactly. `--scenario` runs the network
+//! disrupted by a scenario saved from the app's simulation panel. Without
+//! `--out` the report goes to standard output.
+
+use my_map::data::DataError;
+use my_map::data::error::parse_json;
+use my_map::data::model::{PlatformsResponse, StationsResponse};
+use my_map::data::services::{SERVICES_PATH, ServicePatterns};
+use my_map::data::source::DataFile;
+use my_map::data::{TflDataRepository, loader};
+use my_map::simulation::Simulation;
+use my_map::simulation::clock::{format_clock, parse_clock};
+use my_map::simulation::scenario::Scenario;
+use my_map::utils::rng::random_seed;
+use my_map::utils::{LogLevel, set_log_level};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: simulate HOURS [--data DIR] [--start HH:MM] [--seed N] [--step SECONDS] [--scenario FILE]
+                [--out FILE]";
+
+struct Options {
+    hours: f64,
+    data_dir: PathBuf,
+    start_time: Option<f64>,
+    seed: Option<u64>,
+    step_secs: f64,
+    scenario: Option<PathBuf>,
+    out: Option<PathBuf>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut hours = None;
+        let mut options = Options {
+            hours: 0.0,
+            data_dir: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/data")),
+            start_time: None,
+            seed: None,
+            step_secs: 1.0,
+            scenario: None,
+            out: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--data" => options.data_dir = PathBuf::from(value()?),
+                "--start" => {
+                    let time = value()?;
+                    let time = parse_clock(&time)
+                        .ok_or_else(|| format!("--start expects HH:MM, not {:?}", time))?;
+                    options.start_time = Some(time);
+                }
+                "--seed" => {
+                    let seed = value()?;
+                    let seed = seed
+                        .parse()
+                        .map_err(|_| format!("--seed expects a number, not {:?}", seed))?;
+                    options.seed = Some(seed);
+                }
+                "--step" => {
+                    let seconds = value()?;
+                    options.step_secs = seconds
+                        .parse()
+                        .ok()
+                        .filter(|seconds: &f64| *seconds > 0.0)
+                        .ok_or_else(|| format!("--step expects seconds, not {:?}", seconds))?;
+                }
+                "--scenario" => options.scenario = Some(PathBuf::from(value()?)),
+                "--out" => options.out = Some(PathBuf::from(value()?)),
+                other if other.starts_with("--") => {
+                    return Err(format!("unexpected argument {:?}", other));
+                }
+                _ if hours.is_none() => {
+                    hours = Some(
+                        arg.parse()
+                            .ok()
+                            .filter(|hours: &f64| *hours > 0.0)
+                            .ok_or_else(|| format!("expected hours to run for, not {:?}", arg))?,
+                    );
+                }
+                _ => return Err(format!("unexpected argument {:?}", arg)),
+            }
+        }
+
+        options.hours = hours.ok_or("expected hours to run for")?;
+        Ok(options)
+    }
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> DataError {
+    DataError::Fetch {
+        file: path.display().to_string(),
+        message: format!("failed to read: {}", e),
+    }
+}
+
+/// Read one of the JSON datasets from a directory
+fn read_dataset<T: serde::de::DeserializeOwned>(
+    dir: &Path,
+    file: DataFile,
+) -> Result<T, DataError> {
+    let path = dir.join(file.file_name());
+    let text = std::fs::read_to_string(&path).map_err(|e| read_error(&path, e))?;
+    parse_json(file.file_name(), &text)
+}
+
+/// The rail network and its service patterns in a directory
+///
+/// Without a service patterns file, every line runs at the same headway.
+fn read_repository(dir: &Path) -> Result<TflDataRepository, DataError> {
+    let stations: StationsResponse = read_dataset(dir, DataFile::Stations)?;
+    let platforms: PlatformsResponse = read_dataset(dir, DataFile::Platforms)?;
+    let routes = read_dataset(dir, DataFile::RailRoutes)?;
+    let mut repository = TflDataRepository::from_datasets(
+        stations.results,
+        platforms.results,
+        loader::merge_routes([(DataFile::RailRoutes.file_name(), &routes)]),
+    );
+
+    let services_path = dir.join(SERVICES_PATH);
+    if services_path.exists() {
+        let text =
+            std::fs::read_to_string(&services_path).map_err(|e| read_error(&services_path, e))?;
+        repository.services = Some(ServicePatterns::parse(SERVICES_PATH, &text)?);
+    } else {
+        eprintln!(
+            "warning: no {}, running every line at the same headway",
+            services_path.display()
+        );
+    }
+    Ok(repository)
+}
+
+fn run(options: &Options) -> Result<(), DataError> {
+    let repository = read_repository(&options.data_dir)?;
+    let seed = options.seed.unwrap_or_else(random_seed);
+    let mut simulation = Simulation::from_repository(Some(&repository), seed);
+    if let Some(start_time) = options.start_time {
+        simulation.clock.start_time = start_time;
+    }
+    if let Some(path) = &options.scenario {
+        let text = std::fs::read_to_string(path).map_err(|e| read_error(path, e))?;
+        simulation.disrupt(Scenario::from_json(&path.display().to_string(), &text)?);
+    }
+
+    simulation.start();
+    simulation.run_for(options.hours * 3_600.0, options.step_secs);
+
+    let stats = &simulation.stats;
+    let report = serde_json::json!({
+        "seed": seed,
+        "start": format_clock(simulation.clock.start_time),
+        "end": format_clock(simulation.clock.time),
+        "hours": options.hours,
+        "scenario": simulation.scenario.name,
+        "routes": simulation.routes.len(),
+        "blocks": simulation.signalling.block_count(),
+        "shared_blocks": simulation.signalling.shared_block_count(),
+        "held_at_end": simulation.held_vehicles(),
+        "statistics": stats,
+    });
+    let mut text = serde_json::to_string_pretty(&report).expect("the report always serializes");
+    text.push('\n');
+
+    match &options.out {
+        Some(path) => std::fs::write(path, text).map_err(|e| DataError::Fetch {
+            file: path.display().to_string(),
+            message: format!("failed to write: {}", e),
+        })?,
+        None => print!("{}", text),
+    }
+    eprintln!(
+        "seed {}: {} departures, {} runs completed, {} stop calls, {:.0} km, at most {} vehicles at once, {:.0} minutes held at signals",
+        seed,
+        stats.departures,
+        stats.completed_runs,
+        stats.stop_calls,
+        stats.distance_km,
+        stats.peak_vehicles,
+        stats.held_mins
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    // Info goes to standard output, where the report may be going
+    set_log_level(LogLevel::Warn);
+
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
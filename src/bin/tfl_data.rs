@@ -0,0 +1,696 @@
+//! Offline tool for preparing the bundled TfL datasets.
+//!
+//! Works only on files that have already been downloaded (see
+//! `assets/data/download_bus_routes.sh`), so it can be run without network:
+//!
+//! ```sh
+//! # Combine the per-line route responses into rail_routes.json and bus/
+//! cargo run --bin tfl_data -- routes [--routes DIR] [--data DIR] [--compact] [--simplify METRES]
+//! # Write the compact binary files from the JSON
+//! cargo run --bin tfl_data -- compact [--data DIR] [--simplify METRES]
+//! # Check the datasets for missing lines, orphan platforms, duplicate IDs etc.
+//! cargo run --bin tfl_data -- validate [--data DIR] [--max-distance METRES]
+//! # Compare two copies of the datasets, e.g. before and after a refresh
+//! cargo run --bin tfl_data -- diff OLD_DIR NEW_DIR [--moved METRES] [--geojson FILE]
+//! # Keep a dated copy of the datasets, to show the network as it was later
+//! cargo run --bin tfl_data -- snapshot [--data DIR] [--date YYYY-MM-DD] [--label TEXT]
+//! # Write the datasets as a GTFS feed, with trips every MINUTES if given
+//! cargo run --bin tfl_data -- gtfs OUT_DIR [--data DIR] [--headway MINUTES] [--date YYYY-MM-DD]
+//! # Place the stops on the bus routes from NaPTAN's Stops.csv, into bus/stops.json
+//! cargo run --bin tfl_data -- stops NAPTAN_CSV [--routes DIR] [--data DIR]
+//! ```
+//!
+//! `--data` defaults to `assets/data` and `--routes` to `routes/` inside it.
+//! `--simplify` drops route points within that many metres of the simplified
+//! line, in the compact files only: the JSON keeps the full detail.
+//! `validate` exits with failure if it finds any errors, not just warnings,
+//! or compact files out of date with their JSON.
+//! `snapshot` dates the copy by when the stations were fetched, unless given
+//! `--date`, and `gtfs` starts the feed's calendar then for a year.
+
+use my_map::data::DataError;
+use my_map::data::compact;
+use my_map::data::diff::{self, DEFAULT_MOVE_THRESHOLD, Snapshot};
+use my_map::data::error::parse_json;
+use my_map::data::gtfs_export::{self, DEFAULT_FIRST_DEPARTURE, DEFAULT_LAST_DEPARTURE, Headways};
+use my_map::data::model::{
+    BusManifest, BusStopsFile, PlatformsResponse, RoutesFile, StationsResponse,
+};
+use my_map::data::naptan::{self, RouteStopIndex};
+use my_map::data::prepare::{self, LineResponses};
+use my_map::data::snapshots::{SNAPSHOT_INDEX, SnapshotEntry, SnapshotIndex, snapshot_dir};
+use my_map::data::source::DataFile;
+use my_map::data::validate::{self, DEFAULT_MAX_STATION_DISTANCE, Severity};
+use my_map::data::{TflDataRepository, loader};
+use my_map::utils::time::parse_utc_timestamp;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: tfl_data routes [--routes DIR] [--data DIR] [--compact] [--simplify METRES]
+       tfl_data compact [--data DIR] [--simplify METRES]
+       tfl_data validate [--data DIR] [--max-distance METRES]
+       tfl_data diff OLD_DIR NEW_DIR [--moved METRES] [--geojson FILE]
+       tfl_data snapshot [--data DIR] [--date YYYY-MM-DD] [--label TEXT]
+       tfl_data gtfs OUT_DIR [--data DIR] [--headway MINUTES] [--date YYYY-MM-DD]
+       tfl_data stops NAPTAN_CSV [--routes DIR] [--data DIR]";
+
+/// Options shared by the commands
+struct Options {
+    data_dir: PathBuf,
+    routes_dir: Option<PathBuf>,
+    compact: bool,
+    simplify: Option<f64>,
+    max_distance: f64,
+    moved: f64,
+    geojson: Option<PathBuf>,
+    date: Option<String>,
+    label: Option<String>,
+    headway: Option<u32>,
+    /// Arguments that aren't options, e.g. the directories to compare
+    paths: Vec<PathBuf>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            data_dir: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/data")),
+            routes_dir: None,
+            compact: false,
+            simplify: None,
+            max_distance: DEFAULT_MAX_STATION_DISTANCE,
+            moved: DEFAULT_MOVE_THRESHOLD,
+            geojson: None,
+            date: None,
+            label: None,
+            headway: None,
+            paths: Vec::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--data" => options.data_dir = PathBuf::from(value()?),
+                "--routes" => options.routes_dir = Some(PathBuf::from(value()?)),
+                "--compact" => options.compact = true,
+                "--simplify" => {
+                    let metres = value()?;
+                    let metres = metres
+                        .parse()
+                        .map_err(|_| format!("--simplify expects metres, not {:?}", metres))?;
+                    options.simplify = Some(metres);
+                }
+                "--max-distance" => {
+                    let metres = value()?;
+                    options.max_distance = metres
+                        .parse()
+                        .map_err(|_| format!("--max-distance expects metres, not {:?}", metres))?;
+                }
+                "--moved" => {
+                    let metres = value()?;
+                    options.moved = metres
+                        .parse()
+                        .map_err(|_| format!("--moved expects metres, not {:?}", metres))?;
+                }
+                "--geojson" => options.geojson = Some(PathBuf::from(value()?)),
+                "--date" => {
+                    let date = value()?;
+                    if date.len() != 10
+                        || parse_utc_timestamp(&format!("{}T00:00:00Z", date)).is_none()
+                    {
+                        return Err(format!("--date expects YYYY-MM-DD, not {:?}", date));
+                    }
+                    options.date = Some(date);
+                }
+                "--label" => options.label = Some(value()?),
+                "--headway" => {
+                    let minutes = value()?;
+                    let minutes = minutes
+                        .parse()
+                        .ok()
+                        .filter(|minutes| *minutes > 0)
+                        .ok_or_else(|| format!("--headway expects minutes, not {:?}", minutes))?;
+                    options.headway = Some(minutes);
+                }
+                other if other.starts_with("--") => {
+                    return Err(format!("unexpected argument {:?}", other));
+                }
+                _ => options.paths.push(PathBuf::from(arg)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn routes_dir(&self) -> PathBuf {
+        self.routes_dir
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("routes"))
+    }
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> DataError {
+    DataError::Fetch {
+        file: path.display().to_string(),
+        message: format!("failed to read: {}", e),
+    }
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), DataError> {
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(path, contents));
+    written.map_err(|e| DataError::Fetch {
+        file: path.display().to_string(),
+        message: format!("failed to write: {}", e),
+    })
+}
+
+fn write_json(path: &Path, value: &Value, pretty: bool) -> Result<(), DataError> {
+    let mut text = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .expect("JSON values always serialize");
+    text.push('\n');
+    write(path, text.as_bytes())
+}
+
+/// Read every `<line>.json` response in `<dir>/<direction>[/<subdir>]`
+///
+/// Every invalid file is reported, not just the first.
+fn read_responses(
+    dir: &Path,
+    subdir: Option<&str>,
+    errors: &mut Vec<DataError>,
+) -> Result<LineResponses, DataError> {
+    let mut lines = LineResponses::new();
+
+    for direction in ["inbound", "outbound"] {
+        let mut direction_dir = dir.join(direction);
+        if let Some(subdir) = subdir {
+            direction_dir.push(subdir);
+        }
+
+        let entries =
+            std::fs::read_dir(&direction_dir).map_err(|e| read_error(&direction_dir, e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| read_error(&direction_dir, e))?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(line_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            let text = std::fs::read_to_string(&path).map_err(|e| read_error(&path, e))?;
+            match prepare::prepare_route_response(&path.display().to_string(), &text) {
+                Ok(response) => {
+                    if !prepare::is_successful(&response) {
+                        eprintln!(
+                            "warning: {} says success: false, keeping it anyway",
+                            path.display()
+                        );
+                    }
+                    lines
+                        .entry(line_id.to_string())
+                        .or_default()
+                        .insert(direction.to_string(), response);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Combine the route responses into `rail_routes.json` and the bus files
+fn routes(options: &Options) -> Result<(), Vec<DataError>> {
+    let routes_dir = options.routes_dir();
+
+    // Check everything before writing anything
+    let mut errors = Vec::new();
+    let rail = read_responses(&routes_dir, None, &mut errors).map_err(|e| vec![e])?;
+    let bus = read_responses(&routes_dir, Some("bus"), &mut errors).map_err(|e| vec![e])?;
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let rail_path = options.data_dir.join(DataFile::RailRoutes.file_name());
+    write_json(&rail_path, &prepare::combine_routes(&rail), true).map_err(|e| vec![e])?;
+    println!("{}: {} lines", rail_path.display(), rail.len());
+
+    let mut manifest = BusManifest::default();
+    for (line_id, directions) in &bus {
+        let combined = prepare::combine_routes([(line_id, directions)]);
+        let path = options
+            .data_dir
+            .join(my_map::data::bus::route_path(line_id));
+        write_json(&path, &combined, false).map_err(|e| vec![e])?;
+
+        let routes_file: RoutesFile =
+            serde_json::from_value(combined).expect("responses were checked against RouteResponse");
+        match prepare::bus_manifest_entry(line_id, &routes_file) {
+            Some(entry) => manifest.routes.push(entry),
+            None => eprintln!(
+                "warning: bus route {} has no geometry, leaving it out of the manifest",
+                line_id
+            ),
+        }
+    }
+
+    let manifest_path = options.data_dir.join(DataFile::BusManifest.file_name());
+    let manifest_json = serde_json::to_value(&manifest).expect("the manifest always serializes");
+    write_json(&manifest_path, &manifest_json, false).map_err(|e| vec![e])?;
+    println!(
+        "{}: {} bus routes",
+        manifest_path.display(),
+        manifest.routes.len()
+    );
+
+    if options.compact {
+        compact_all(options)?;
+    }
+    Ok(())
+}
+
+/// Encode one dataset, returning its JSON and compact sizes
+fn compact_file(options: &Options, file: DataFile) -> Result<(usize, usize), DataError> {
+    let name = file.file_name();
+    let json_path = options.data_dir.join(name);
+    let json = std::fs::read_to_string(&json_path).map_err(|e| read_error(&json_path, e))?;
+
+    let unsuccessful = || DataError::Unsuccessful {
+        file: name.to_string(),
+        context: None,
+    };
+    let bytes = match file {
+        DataFile::Stations => {
+            let response: StationsResponse = parse_json(name, &json)?;
+            if !response.success {
+                return Err(unsuccessful());
+            }
+            compact::encode_stations(&response, &json)
+        }
+        DataFile::Platforms => {
+            let response: PlatformsResponse = parse_json(name, &json)?;
+            if !response.success {
+                return Err(unsuccessful());
+            }
+            compact::encode_platforms(&response, &json)
+        }
+        DataFile::RailRoutes => {
+            let mut routes_file: RoutesFile = parse_json(name, &json)?;
+            if let Some(tolerance) = options.simplify {
+                let dropped = prepare::simplify_routes(&mut routes_file, tolerance);
+                println!("{}: simplified away {} points", name, dropped);
+            }
+            compact::encode_routes(&routes_file, &json)
+        }
+        DataFile::BusManifest => unreachable!("the bus manifest has no compact form"),
+    };
+
+    let path = options
+        .data_dir
+        .join(file.compact_path().expect("dataset has a compact form"));
+    write(&path, &bytes)?;
+
+    Ok((json.len(), bytes.len()))
+}
+
+/// Write the compact binary file of every dataset that has one
+fn compact_all(options: &Options) -> Result<(), Vec<DataError>> {
+    let mut errors = Vec::new();
+
+    for file in DataFile::ALL {
+        let Some(compact_path) = file.compact_path() else {
+            continue;
+        };
+
+        match compact_file(options, file) {
+            Ok((json_size, compact_size)) => println!(
+                "{} -> {}: {} -> {} bytes ({:.0}%)",
+                file.file_name(),
+                compact_path,
+                json_size,
+                compact_size,
+                100.0 * compact_size as f64 / json_size as f64
+            ),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Read one of the JSON datasets from a directory
+fn read_dataset<T: serde::de::DeserializeOwned>(
+    dir: &Path,
+    file: DataFile,
+) -> Result<T, DataError> {
+    let path = dir.join(file.file_name());
+    let text = std::fs::read_to_string(&path).map_err(|e| read_error(&path, e))?;
+    parse_json(file.file_name(), &text)
+}
+
+/// Read the stations, platforms and rail routes in a directory
+fn read_snapshot(dir: &Path) -> Result<Snapshot, DataError> {
+    let stations: StationsResponse = read_dataset(dir, DataFile::Stations)?;
+    let platforms: PlatformsResponse = read_dataset(dir, DataFile::Platforms)?;
+    Ok(Snapshot {
+        stations: stations.results,
+        platforms: platforms.results,
+        routes: read_dataset(dir, DataFile::RailRoutes)?,
+    })
+}
+
+/// The compact files in a directory written from other JSON than is there
+/// now, or in an older format, so the app would read stale data from them
+fn stale_compact_files(dir: &Path) -> Result<Vec<&'static str>, DataError> {
+    let mut stale = Vec::new();
+    for file in DataFile::ALL {
+        let Some(compact_path) = file.compact_path() else {
+            continue;
+        };
+        let path = dir.join(compact_path);
+        if !path.exists() {
+            continue;
+        }
+        let bytes = std::fs::read(&path).map_err(|e| read_error(&path, e))?;
+        let json_path = dir.join(file.file_name());
+        let json = std::fs::read_to_string(&json_path).map_err(|e| read_error(&json_path, e))?;
+        if !compact::is_current(&bytes, &json) {
+            stale.push(compact_path);
+        }
+    }
+    Ok(stale)
+}
+
+/// Report integrity issues in the JSON datasets, and compact files out of
+/// date with them
+fn validate(options: &Options) -> Result<(), Vec<DataError>> {
+    let snapshot = read_snapshot(&options.data_dir).map_err(|e| vec![e])?;
+    let stale = stale_compact_files(&options.data_dir).map_err(|e| vec![e])?;
+
+    let report = validate::validate(
+        &snapshot.stations,
+        &snapshot.platforms,
+        &snapshot.routes,
+        options.max_distance,
+    );
+    for issue in &report.issues {
+        match issue.severity() {
+            Severity::Error => println!("error: {}", issue),
+            Severity::Warning => println!("warning: {}", issue),
+        }
+    }
+    println!("{}", report.summary());
+    for path in &stale {
+        println!(
+            "error: {} is out of date with its JSON, run `tfl_data compact`",
+            path
+        );
+    }
+
+    if report.has_errors() || !stale.is_empty() {
+        Err(Vec::new())
+    } else {
+        Ok(())
+    }
+}
+
+/// Compare the datasets in two directories
+fn diff(options: &Options) -> Result<(), Vec<DataError>> {
+    let [old_dir, new_dir] = options.paths.as_slice() else {
+        unreachable!("main checks diff is given two directories");
+    };
+
+    // Read both before reporting, so every unreadable file is listed
+    let (old, new) = (read_snapshot(old_dir), read_snapshot(new_dir));
+    let (old, new) = match (old, new) {
+        (Ok(old), Ok(new)) => (old, new),
+        (old, new) => return Err([old.err(), new.err()].into_iter().flatten().collect()),
+    };
+
+    let report = diff::diff(&old, &new, options.moved);
+    for change in &report.changes {
+        println!("{}", change);
+    }
+    println!("{}", report.summary());
+
+    if let Some(path) = &options.geojson {
+        write_json(path, &diff::to_geojson(&report, &old, &new), false).map_err(|e| vec![e])?;
+        println!("{}: {} changes", path.display(), report.changes.len());
+    }
+    Ok(())
+}
+
+/// The `--date` given, or when the stations were fetched, `YYYY-MM-DD`
+fn dataset_date(options: &Options) -> Result<String, DataError> {
+    if let Some(date) = &options.date {
+        return Ok(date.clone());
+    }
+    let stations: StationsResponse = read_dataset(&options.data_dir, DataFile::Stations)?;
+    stations
+        .context
+        .as_ref()
+        .and_then(|context| context.request_time.get(..10))
+        .map(str::to_string)
+        .ok_or_else(|| DataError::Fetch {
+            file: DataFile::Stations.file_name().to_string(),
+            message: "doesn't say when it was fetched, so give a --date".to_string(),
+        })
+}
+
+/// Copy the datasets into a dated snapshot and add it to the index
+///
+/// Bus routes aren't copied: the app always shows the current ones.
+fn snapshot(options: &Options) -> Result<(), Vec<DataError>> {
+    let date = dataset_date(options).map_err(|e| vec![e])?;
+
+    let dir = options.data_dir.join(snapshot_dir(&date));
+    let files = DataFile::ALL
+        .iter()
+        .filter(|file| **file != DataFile::BusManifest)
+        .flat_map(|file| [Some(file.file_name()), file.compact_path()])
+        .flatten();
+    for name in files {
+        let from = options.data_dir.join(name);
+        // The compact files are optional
+        if !from.exists() && name.starts_with("compact/") {
+            continue;
+        }
+        let bytes = std::fs::read(&from).map_err(|e| vec![read_error(&from, e)])?;
+        write(&dir.join(name), &bytes).map_err(|e| vec![e])?;
+    }
+    println!("{}: copied the datasets", dir.display());
+
+    let index_path = options.data_dir.join(SNAPSHOT_INDEX);
+    let mut index = if index_path.exists() {
+        let text =
+            std::fs::read_to_string(&index_path).map_err(|e| vec![read_error(&index_path, e)])?;
+        parse_json(SNAPSHOT_INDEX, &text).map_err(|e| vec![e])?
+    } else {
+        SnapshotIndex::default()
+    };
+    index.insert(SnapshotEntry {
+        date,
+        label: options.label.clone(),
+    });
+    let index_json = serde_json::to_value(&index).expect("the index always serializes");
+    write_json(&index_path, &index_json, true).map_err(|e| vec![e])?;
+    println!(
+        "{}: {} snapshots",
+        index_path.display(),
+        index.snapshots.len()
+    );
+    Ok(())
+}
+
+/// Write the datasets as a GTFS feed in a directory
+fn gtfs(options: &Options) -> Result<(), Vec<DataError>> {
+    let [out_dir] = options.paths.as_slice() else {
+        unreachable!("main checks gtfs is given a directory");
+    };
+
+    let snapshot = read_snapshot(&options.data_dir).map_err(|e| vec![e])?;
+    let date = dataset_date(options).map_err(|e| vec![e])?;
+    let repository = TflDataRepository::from_datasets(
+        snapshot.stations,
+        snapshot.platforms,
+        loader::merge_routes([(DataFile::RailRoutes.file_name(), &snapshot.routes)]),
+    );
+
+    // A year from the start, or the day before if that's 29 February
+    let start_date = date.replace('-', "");
+    let year: u32 = start_date[..4].parse().expect("dates are checked");
+    let month_day = match &start_date[4..] {
+        "0229" => "0228",
+        month_day => month_day,
+    };
+    let options = gtfs_export::ExportOptions {
+        headways: options.headway.map(|minutes| Headways {
+            headway_secs: minutes * 60,
+            start_time: DEFAULT_FIRST_DEPARTURE.to_string(),
+            end_time: DEFAULT_LAST_DEPARTURE.to_string(),
+        }),
+        end_date: format!("{}{}", year + 1, month_day),
+        start_date,
+    };
+
+    let feed = gtfs_export::export(&repository, &options);
+    for (name, text) in feed.files() {
+        write(&out_dir.join(name), text.as_bytes()).map_err(|e| vec![e])?;
+    }
+    println!(
+        "{}: {} stops, {} routes, {} shapes, {} trips",
+        out_dir.display(),
+        feed.stops.len(),
+        feed.routes.len(),
+        feed.shapes
+            .iter()
+            .map(|point| &point.shape_id)
+            .collect::<std::collections::HashSet<_>>()
+            .len(),
+        feed.trips.len()
+    );
+    Ok(())
+}
+
+/// The first few IDs of a list, for a warning that might otherwise run on
+fn some_of(ids: &[String]) -> String {
+    const SHOWN: usize = 10;
+    match ids.len().checked_sub(SHOWN) {
+        Some(more) if more > 0 => format!("{} and {} more", ids[..SHOWN].join(", "), more),
+        _ => ids.join(", "),
+    }
+}
+
+/// Place the stops the bus routes call at from NaPTAN, writing `bus/stops.json`
+///
+/// The stops come from the downloaded bus route responses, which still have
+/// their stop sequences.
+fn stops(options: &Options) -> Result<(), Vec<DataError>> {
+    let [naptan_path] = options.paths.as_slice() else {
+        unreachable!("main checks stops is given a NaPTAN file");
+    };
+    let routes_dir = options.routes_dir();
+
+    let mut index = RouteStopIndex::new();
+    let mut errors = Vec::new();
+    for direction in ["inbound", "outbound"] {
+        let dir = routes_dir.join(direction).join("bus");
+        let entries = std::fs::read_dir(&dir).map_err(|e| vec![read_error(&dir, e)])?;
+        for entry in entries {
+            let path = entry.map_err(|e| vec![read_error(&dir, e)])?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).map_err(|e| vec![read_error(&path, e)])?;
+            if let Err(e) = index.add_response(&path.display().to_string(), &text) {
+                errors.push(e);
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let text =
+        std::fs::read_to_string(naptan_path).map_err(|e| vec![read_error(naptan_path, e)])?;
+    let naptan_stops = naptan::parse_naptan_stops(&naptan_path.display().to_string(), &text)
+        .map_err(|e| vec![e])?;
+    let linked = index.link(&naptan_stops);
+    if !linked.not_in_naptan.is_empty() {
+        eprintln!(
+            "warning: {} stops aren't in NaPTAN, placing them from the route data: {}",
+            linked.not_in_naptan.len(),
+            some_of(&linked.not_in_naptan)
+        );
+    }
+    if !linked.unplaced.is_empty() {
+        eprintln!(
+            "warning: {} stops have no position, leaving them out: {}",
+            linked.unplaced.len(),
+            some_of(&linked.unplaced)
+        );
+    }
+
+    let path = options.data_dir.join(my_map::data::bus::BUS_STOPS_PATH);
+    let stops_file = BusStopsFile {
+        stops: linked.stops,
+    };
+    let json = serde_json::to_value(&stops_file).expect("bus stops always serialize");
+    write_json(&path, &json, false).map_err(|e| vec![e])?;
+    println!(
+        "{}: {} of the {} stops on the bus routes",
+        path.display(),
+        stops_file.stops.len(),
+        index.len()
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    // Only diff, gtfs and stops take paths without an option before them
+    let is_diff = command.as_deref() == Some("diff");
+    let is_gtfs = command.as_deref() == Some("gtfs");
+    let is_stops = command.as_deref() == Some("stops");
+    let paths_error = match options.paths.as_slice() {
+        [_, _] if is_diff => None,
+        _ if is_diff => Some("diff needs two directories".to_string()),
+        [_] if is_gtfs => None,
+        _ if is_gtfs => Some("gtfs needs a directory to write to".to_string()),
+        [_] if is_stops => None,
+        _ if is_stops => Some("stops needs NaPTAN's Stops.csv".to_string()),
+        [] => None,
+        [path, ..] => Some(format!("unexpected argument {:?}", path)),
+    };
+    if let Some(message) = paths_error {
+        eprintln!("{}\n{}", message, USAGE);
+        return ExitCode::from(2);
+    }
+
+    let result = match command.as_deref() {
+        Some("routes") => routes(&options),
+        Some("compact") => compact_all(&options),
+        Some("validate") => validate(&options),
+        Some("diff") => diff(&options),
+        Some("snapshot") => snapshot(&options),
+        Some("gtfs") => gtfs(&options),
+        Some("stops") => stops(&options),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Bus routes, loaded one route at a time.
+//!
+//! There are hundreds of bus routes, so rather than reading them all when
+//! buses are switched on, only the manifest is read up front. A route is
+//! read when it comes into view or is searched for, and the least recently
+//! used routes are dropped once more than [`BUS_ROUTE_CACHE_SIZE`] are loaded.
+
+use super::model::{BusManifest, BusManifestEntry, BusStop};
+use crate::utils::lru::LruSet;
+
+/// Zoom level below which bus routes in view are not loaded
+// Only the map reads the viewport, which is wasm32-only
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const BUS_ROUTE_MIN_ZOOM: f64 = 12.0;
+
+/// Zoom level below which bus stops are not shown
+// Only the map reads the zoom, which is wasm32-only
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const BUS_STOP_MIN_ZOOM: f64 = 15.0;
+
+/// How many bus routes are kept in memory at once
+pub const BUS_ROUTE_CACHE_SIZE: usize = 250;
+
+/// Path of a bus route's file, relative to the dataset root
+pub fn route_path(id: &str) -> String {
+    format!("bus/{}.json", id)
+}
+
+/// Path of the bus stops file, relative to the dataset root, see
+/// [`super::naptan`]
+pub const BUS_STOPS_PATH: &str = "bus/stops.json";
+
+/// The bus routes available, and which of them are loaded
+#[derive(Debug, Clone)]
+pub struct BusRoutes {
+    pub manifest: BusManifest,
+    pub(super) loaded: LruSet<String>,
+    /// The bus stops, once read from [`BUS_STOPS_PATH`]
+    pub stops: Vec<BusStop>,
+    /// Whether the stops have been asked for, so they're only read once
+    pub(super) stops_requested: bool,
+}
+
+impl BusRoutes {
+    pub fn new(manifest: BusManifest) -> Self {
+        Self {
+            manifest,
+            loaded: LruSet::new(BUS_ROUTE_CACHE_SIZE),
+            stops: Vec::new(),
+            stops_requested: false,
+        }
+    }
+
+    /// IDs of the routes that pass through a `[west, south, east, north]` extent
+    ///
+    /// This goes by each route's bounding box, so may include a route that
+    /// only passes near the extent.
+    pub fn in_bounds(&self, bounds: [f64; 4]) -> Vec<String> {
+        let [west, south, east, north] = bounds;
+        self.manifest
+            .routes
+            .iter()
+            .filter(|route| {
+                let [w, s, e, n] = route.bbox;
+                w <= east && e >= west && s <= north && n >= south
+            })
+            .map(|route| route.id.clone())
+            .collect()
+    }
+
+    /// Look up a route by its ID, ignoring case and surrounding whitespace
+    pub fn find(&self, query: &str) -> Option<&BusManifestEntry> {
+        let query = query.trim();
+        self.manifest
+            .routes
+            .iter()
+            .find(|route| route.id.eq_ignore_ascii_case(query))
+    }
+
+    pub fn is_loaded(&self, id: &str) -> bool {
+        self.loaded.contains(&id.to_string())
+    }
+
+    /// How many routes are loaded
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+}
@@ -0,0 +1,826 @@
+//! A compact binary encoding of the datasets.
+//!
+//! The JSON responses repeat long PascalCase keys for every record and carry
+//! coordinates to full `f64` precision, e.g. `51.600680000000004`. The compact
+//! files hold the same records with:
+//!
+//! - every string stored once in a table, and referred to by its index
+//! - coordinates as whole millionths of a degree (about 10 cm)
+//! - route polylines, and the stops along each branch of a line, as the
+//!   difference from the previous point
+//! - integers as variable-length (LEB128) numbers, zigzag encoded if signed
+//!
+//! Each file starts with a header: the magic bytes `TFLC`, the format
+//! [`VERSION`], the kind of records it holds and a digest of the JSON it was
+//! written from. Files in any other version are rejected, and the loader
+//! reads the JSON instead. The `success` flag and `context` of each response
+//! (when and how it was fetched) are kept alongside its records.
+//!
+//! The files are written offline by `cargo run --bin tfl_data -- compact`.
+//! The app reads them in place of the JSON without reading the JSON too, so
+//! it can't tell when one is out of date: `tfl_data validate` checks each
+//! against its JSON with [`is_current`], and fails if it isn't.
+
+use super::error::DataError;
+use super::loader::parse_line_string;
+use super::model::{
+    MatchedStop, Platform, PlatformsResponse, ResponseContext, RouteResponse, RouteSequence,
+    RoutesFile, Station, StationsResponse, StopPointSequence,
+};
+use std::collections::HashMap;
+
+const MAGIC: &[u8; 4] = b"TFLC";
+
+/// Version of the format, bumped whenever the layout changes
+pub const VERSION: u8 = 5;
+
+/// Coordinates are stored as whole multiples of 1 / `COORDINATE_SCALE` degrees
+const COORDINATE_SCALE: f64 = 1_000_000.0;
+
+/// What a file holds, recorded in its header
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Stations = 1,
+    Platforms = 2,
+    Routes = 3,
+}
+
+/// How a route's line string is stored
+const LINE_STRING_RAW: u8 = 0;
+const LINE_STRING_POLYLINE: u8 = 1;
+
+/// Digest of the JSON a file was written from: its 64-bit FNV-1a hash
+fn source_digest(json: &str) -> u64 {
+    json.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether a compact file is in the current format and was written from
+/// `json`, rather than from an older copy of it
+pub fn is_current(bytes: &[u8], json: &str) -> bool {
+    let digest = MAGIC.len() + 2;
+    bytes.len() >= digest + 8
+        && bytes[..MAGIC.len()] == MAGIC[..]
+        && bytes[MAGIC.len()] == VERSION
+        && bytes[digest..digest + 8] == source_digest(json).to_le_bytes()
+}
+
+fn to_fixed(degrees: f64) -> i64 {
+    (degrees * COORDINATE_SCALE).round() as i64
+}
+
+fn from_fixed(units: i64) -> f64 {
+    units as f64 / COORDINATE_SCALE
+}
+
+/// Writes records, collecting their strings into the table
+#[derive(Default)]
+struct Encoder {
+    body: Vec<u8>,
+    strings: Vec<String>,
+    string_index: HashMap<String, u64>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.body.push(byte);
+                return;
+            }
+            self.body.push(byte | 0x80);
+        }
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.varint(len as u64);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.body.push(value as u8);
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.body.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn context(&mut self, context: Option<&ResponseContext>) {
+        self.bool(context.is_some());
+        if let Some(context) = context {
+            self.string(&context.request_time);
+            self.string(&context.response_time);
+            self.f64(context.response_latency);
+            self.string(&context.query);
+        }
+    }
+
+    fn intern(&mut self, value: &str) -> u64 {
+        if let Some(index) = self.string_index.get(value) {
+            return *index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.push(value.to_string());
+        self.string_index.insert(value.to_string(), index);
+        index
+    }
+
+    fn string(&mut self, value: &str) {
+        let index = self.intern(value);
+        self.varint(index);
+    }
+
+    /// `None` is stored as 0, so the index of a string is shifted up by one
+    fn optional_string(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                let index = self.intern(value);
+                self.varint(index + 1);
+            }
+            None => self.varint(0),
+        }
+    }
+
+    /// The whole file: header, string table, then the records
+    fn finish(self, kind: Kind, source: &str) -> Vec<u8> {
+        let mut file = Encoder::default();
+        file.body.extend_from_slice(MAGIC);
+        file.body.push(VERSION);
+        file.body.push(kind as u8);
+        file.body
+            .extend_from_slice(&source_digest(source).to_le_bytes());
+
+        file.len(self.strings.len());
+        for string in &self.strings {
+            file.len(string.len());
+            file.body.extend_from_slice(string.as_bytes());
+        }
+
+        file.body.extend_from_slice(&self.body);
+        file.body
+    }
+}
+
+/// Reads records back, checking every read against the end of the data
+struct Decoder<'a> {
+    file: &'a str,
+    bytes: &'a [u8],
+    position: usize,
+    strings: Vec<String>,
+}
+
+impl<'a> Decoder<'a> {
+    /// Check the header and read the string table
+    fn new(file: &'a str, bytes: &'a [u8], kind: Kind) -> Result<Self, DataError> {
+        let mut decoder = Self {
+            file,
+            bytes,
+            position: 0,
+            strings: Vec::new(),
+        };
+
+        if decoder.take(MAGIC.len())? != MAGIC {
+            return Err(decoder.error("not a compact data file"));
+        }
+        let version = decoder.byte()?;
+        if version != VERSION {
+            return Err(decoder.error(format!(
+                "unsupported format version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        let found = decoder.byte()?;
+        if found != kind as u8 {
+            return Err(decoder.error(format!("holds record kind {}, not {:?}", found, kind)));
+        }
+        // The digest of the JSON is only for checking the file is current
+        decoder.take(8)?;
+
+        let count = decoder.len()?;
+        let mut strings = Vec::with_capacity(count);
+        for _ in 0..count {
+            let len = decoder.len()?;
+            let bytes = decoder.take(len)?;
+            let string = std::str::from_utf8(bytes)
+                .map_err(|e| decoder.error(format!("string is not valid UTF-8: {}", e)))?;
+            strings.push(string.to_string());
+        }
+        decoder.strings = strings;
+
+        Ok(decoder)
+    }
+
+    fn error(&self, message: impl Into<String>) -> DataError {
+        DataError::Decode {
+            file: self.file.to_string(),
+            message: format!("{} at byte {}", message.into(), self.position),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DataError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, DataError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, DataError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.error("variable-length number is too long"))
+    }
+
+    fn signed(&mut self) -> Result<i64, DataError> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// A count of items, which can't be more than the bytes left to hold them
+    fn len(&mut self) -> Result<usize, DataError> {
+        let len = self.varint()?;
+        if len > (self.bytes.len() - self.position) as u64 {
+            return Err(self.error(format!("length {} is longer than the data", len)));
+        }
+        Ok(len as usize)
+    }
+
+    fn bool(&mut self) -> Result<bool, DataError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(self.error(format!("invalid boolean {}", other))),
+        }
+    }
+
+    fn f64(&mut self) -> Result<f64, DataError> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(
+            bytes.try_into().expect("took exactly 8 bytes"),
+        ))
+    }
+
+    fn context(&mut self) -> Result<Option<ResponseContext>, DataError> {
+        if !self.bool()? {
+            return Ok(None);
+        }
+        Ok(Some(ResponseContext {
+            request_time: self.string()?,
+            response_time: self.string()?,
+            response_latency: self.f64()?,
+            query: self.string()?,
+        }))
+    }
+
+    fn string_at(&self, index: u64) -> Result<String, DataError> {
+        self.strings
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| self.error(format!("string index {} is out of range", index)))
+    }
+
+    fn string(&mut self) -> Result<String, DataError> {
+        let index = self.varint()?;
+        self.string_at(index)
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, DataError> {
+        match self.varint()? {
+            0 => Ok(None),
+            index => self.string_at(index - 1).map(Some),
+        }
+    }
+
+    /// Check that nothing follows the records
+    fn finish(self) -> Result<(), DataError> {
+        if self.position != self.bytes.len() {
+            return Err(self.error("unexpected data after the records"));
+        }
+        Ok(())
+    }
+}
+
+/// Encode stations, with each one's position relative to the one before
+///
+/// `source` is the JSON they were read from, for [`is_current`].
+pub fn encode_stations(response: &StationsResponse, source: &str) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    let mut previous = (0, 0);
+
+    encoder.bool(response.success);
+    encoder.context(response.context.as_ref());
+    encoder.len(response.results.len());
+    for station in &response.results {
+        encoder.string(&station.station_unique_id);
+        encoder.string(&station.station_name);
+        encoder.string(&station.fare_zones);
+        encoder.optional_string(station.hub_naptan_code.as_deref());
+        encoder.bool(station.wifi);
+        encoder.string(&station.outside_station_unique_id);
+
+        let position = (to_fixed(station.lat), to_fixed(station.lon));
+        encoder.signed(position.0 - previous.0);
+        encoder.signed(position.1 - previous.1);
+        previous = position;
+
+        encoder.len(station.component_stations.len());
+        for component in &station.component_stations {
+            encoder.string(component);
+        }
+    }
+
+    encoder.finish(Kind::Stations, source)
+}
+
+pub fn decode_stations(file: &str, bytes: &[u8]) -> Result<StationsResponse, DataError> {
+    let mut decoder = Decoder::new(file, bytes, Kind::Stations)?;
+    let mut previous = (0, 0);
+
+    let success = decoder.bool()?;
+    let context = decoder.context()?;
+    let count = decoder.len()?;
+    let mut stations = Vec::with_capacity(count);
+    for _ in 0..count {
+        let station_unique_id = decoder.string()?;
+        let station_name = decoder.string()?;
+        let fare_zones = decoder.string()?;
+        let hub_naptan_code = decoder.optional_string()?;
+        let wifi = decoder.bool()?;
+        let outside_station_unique_id = decoder.string()?;
+
+        let position = (
+            previous.0 + decoder.signed()?,
+            previous.1 + decoder.signed()?,
+        );
+        previous = position;
+
+        let component_count = decoder.len()?;
+        let component_stations = (0..component_count)
+            .map(|_| decoder.string())
+            .collect::<Result<_, _>>()?;
+
+        stations.push(Station {
+            station_unique_id,
+            station_name,
+            fare_zones,
+            hub_naptan_code,
+            wifi,
+            outside_station_unique_id,
+            lat: from_fixed(position.0),
+            lon: from_fixed(position.1),
+            component_stations,
+        });
+    }
+
+    decoder.finish()?;
+    Ok(StationsResponse {
+        context,
+        success,
+        results: stations,
+    })
+}
+
+/// Encode platforms, see [`encode_stations`]
+pub fn encode_platforms(response: &PlatformsResponse, source: &str) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+
+    encoder.bool(response.success);
+    encoder.context(response.context.as_ref());
+    encoder.len(response.results.len());
+    for platform in &response.results {
+        encoder.string(&platform.platform_unique_id);
+        encoder.string(&platform.station_unique_id);
+        encoder.optional_string(platform.platform_number.as_deref());
+        encoder.optional_string(platform.cardinal_direction.as_deref());
+        encoder.optional_string(platform.platform_naptan_code.as_deref());
+        encoder.string(&platform.platform_friendly_name);
+        encoder.bool(platform.is_customer_facing);
+        encoder.bool(platform.has_service_interchange);
+        encoder.string(&platform.station_name);
+        encoder.string(&platform.fare_zones);
+        encoder.optional_string(platform.hub_naptan_code.as_deref());
+        encoder.bool(platform.wifi);
+        encoder.string(&platform.outside_station_unique_id);
+        encoder.string(&platform.stop_area_naptan_code);
+        encoder.string(&platform.line);
+        encoder.optional_string(platform.direction_towards.as_deref());
+        encoder.optional_string(platform.platform_service_group_name.as_deref());
+    }
+
+    encoder.finish(Kind::Platforms, source)
+}
+
+pub fn decode_platforms(file: &str, bytes: &[u8]) -> Result<PlatformsResponse, DataError> {
+    let mut decoder = Decoder::new(file, bytes, Kind::Platforms)?;
+
+    let success = decoder.bool()?;
+    let context = decoder.context()?;
+    let count = decoder.len()?;
+    let mut platforms = Vec::with_capacity(count);
+    for _ in 0..count {
+        platforms.push(Platform {
+            platform_unique_id: decoder.string()?,
+            station_unique_id: decoder.string()?,
+            platform_number: decoder.optional_string()?,
+            cardinal_direction: decoder.optional_string()?,
+            platform_naptan_code: decoder.optional_string()?,
+            platform_friendly_name: decoder.string()?,
+            is_customer_facing: decoder.bool()?,
+            has_service_interchange: decoder.bool()?,
+            station_name: decoder.string()?,
+            fare_zones: decoder.string()?,
+            hub_naptan_code: decoder.optional_string()?,
+            wifi: decoder.bool()?,
+            outside_station_unique_id: decoder.string()?,
+            stop_area_naptan_code: decoder.string()?,
+            line: decoder.string()?,
+            direction_towards: decoder.optional_string()?,
+            platform_service_group_name: decoder.optional_string()?,
+        });
+    }
+
+    decoder.finish()?;
+    Ok(PlatformsResponse {
+        context,
+        success,
+        results: platforms,
+    })
+}
+
+/// Encode a routes file, with lines and directions sorted so the output is
+/// the same every time, see [`encode_stations`]
+pub fn encode_routes(routes_file: &RoutesFile, source: &str) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+
+    let mut lines: Vec<_> = routes_file.routes.iter().collect();
+    lines.sort_by_key(|(line_id, _)| *line_id);
+
+    encoder.len(lines.len());
+    for (line_id, directions) in lines {
+        encoder.string(line_id);
+
+        let mut directions: Vec<_> = directions.iter().collect();
+        directions.sort_by_key(|(direction, _)| *direction);
+
+        encoder.len(directions.len());
+        for (direction, response) in directions {
+            encoder.string(direction);
+            encoder.bool(response.success);
+            encoder.context(response.context.as_ref());
+
+            encoder.len(response.results.len());
+            for sequence in &response.results {
+                encoder.string(&sequence.line_id);
+                encoder.string(&sequence.direction);
+                encoder.string(&sequence.mode);
+
+                encoder.len(sequence.line_strings.len());
+                for line_string in &sequence.line_strings {
+                    encode_line_string(&mut encoder, line_string);
+                }
+
+                encoder.len(sequence.stop_point_sequences.len());
+                for branch in &sequence.stop_point_sequences {
+                    encode_branch(&mut encoder, branch);
+                }
+            }
+        }
+    }
+
+    encoder.finish(Kind::Routes, source)
+}
+
+/// Store a line string as a delta-encoded polyline where possible
+///
+/// Anything that isn't a single line of points (which the app would flatten
+/// when drawing it) is kept as the original text.
+fn encode_line_string(encoder: &mut Encoder, line_string: &str) {
+    let points = match parse_line_string(line_string) {
+        Ok(points) if !line_string.contains("]],[[") => points,
+        _ => {
+            encoder.body.push(LINE_STRING_RAW);
+            encoder.string(line_string);
+            return;
+        }
+    };
+
+    encoder.body.push(LINE_STRING_POLYLINE);
+    encoder.len(points.len());
+    let mut previous = (0, 0);
+    for [lon, lat] in points {
+        let point = (to_fixed(lon), to_fixed(lat));
+        encoder.signed(point.0 - previous.0);
+        encoder.signed(point.1 - previous.1);
+        previous = point;
+    }
+}
+
+/// Store a branch of a line with the fields the bundled routes keep of it,
+/// see [`super::prepare::KEPT_BRANCH_FIELDS`]
+fn encode_branch(encoder: &mut Encoder, branch: &StopPointSequence) {
+    encoder.string(&branch.line_id);
+    encoder.string(&branch.line_name);
+    encoder.string(&branch.direction);
+    encoder.signed(branch.branch_id.into());
+    for branch_ids in [&branch.next_branch_ids, &branch.prev_branch_ids] {
+        encoder.len(branch_ids.len());
+        for branch_id in branch_ids {
+            encoder.signed((*branch_id).into());
+        }
+    }
+    encoder.optional_string(branch.service_type.as_deref());
+
+    encoder.len(branch.stop_point.len());
+    let mut previous = (0, 0);
+    for stop in &branch.stop_point {
+        encoder.optional_string(stop.id.as_deref());
+        encoder.optional_string(stop.station_id.as_deref());
+        encoder.optional_string(stop.top_most_parent_id.as_deref());
+        encoder.optional_string(stop.name.as_deref());
+
+        let position = stop.lat.zip(stop.lon);
+        encoder.bool(position.is_some());
+        if let Some((lat, lon)) = position {
+            let position = (to_fixed(lat), to_fixed(lon));
+            encoder.signed(position.0 - previous.0);
+            encoder.signed(position.1 - previous.1);
+            previous = position;
+        }
+    }
+}
+
+fn decode_branch_id(decoder: &mut Decoder) -> Result<i32, DataError> {
+    let value = decoder.signed()?;
+    i32::try_from(value).map_err(|_| decoder.error(format!("branch ID {} is out of range", value)))
+}
+
+fn decode_branch(decoder: &mut Decoder) -> Result<StopPointSequence, DataError> {
+    let line_id = decoder.string()?;
+    let line_name = decoder.string()?;
+    let direction = decoder.string()?;
+    let branch_id = decode_branch_id(decoder)?;
+    let mut branch_ids = [Vec::new(), Vec::new()];
+    for ids in &mut branch_ids {
+        let count = decoder.len()?;
+        *ids = (0..count)
+            .map(|_| decode_branch_id(decoder))
+            .collect::<Result<_, _>>()?;
+    }
+    let [next_branch_ids, prev_branch_ids] = branch_ids;
+    let service_type = decoder.optional_string()?;
+
+    let stop_count = decoder.len()?;
+    let mut stop_point = Vec::with_capacity(stop_count);
+    let mut previous = (0, 0);
+    for _ in 0..stop_count {
+        let mut stop = MatchedStop {
+            id: decoder.optional_string()?,
+            station_id: decoder.optional_string()?,
+            top_most_parent_id: decoder.optional_string()?,
+            name: decoder.optional_string()?,
+            ..MatchedStop::default()
+        };
+        if decoder.bool()? {
+            let position = (
+                previous.0 + decoder.signed()?,
+                previous.1 + decoder.signed()?,
+            );
+            previous = position;
+            stop.lat = Some(from_fixed(position.0));
+            stop.lon = Some(from_fixed(position.1));
+        }
+        stop_point.push(stop);
+    }
+
+    Ok(StopPointSequence {
+        line_id,
+        line_name,
+        direction,
+        branch_id,
+        next_branch_ids,
+        prev_branch_ids,
+        stop_point,
+        service_type,
+    })
+}
+
+/// Read a line string back in the API's `[[[lon,lat],...]]` form
+fn decode_line_string(decoder: &mut Decoder) -> Result<String, DataError> {
+    match decoder.byte()? {
+        LINE_STRING_RAW => decoder.string(),
+        LINE_STRING_POLYLINE => {
+            let count = decoder.len()?;
+            let mut points = Vec::with_capacity(count);
+            let mut previous = (0, 0);
+            for _ in 0..count {
+                let point = (
+                    previous.0 + decoder.signed()?,
+                    previous.1 + decoder.signed()?,
+                );
+                points.push(format!("[{},{}]", from_fixed(point.0), from_fixed(point.1)));
+                previous = point;
+            }
+            Ok(format!("[[{}]]", points.join(",")))
+        }
+        other => Err(decoder.error(format!("invalid line string tag {}", other))),
+    }
+}
+
+pub fn decode_routes(file: &str, bytes: &[u8]) -> Result<RoutesFile, DataError> {
+    let mut decoder = Decoder::new(file, bytes, Kind::Routes)?;
+
+    let line_count = decoder.len()?;
+    let mut routes = HashMap::with_capacity(line_count);
+    for _ in 0..line_count {
+        let line_id = decoder.string()?;
+
+        let direction_count = decoder.len()?;
+        let mut directions = HashMap::with_capacity(direction_count);
+        for _ in 0..direction_count {
+            let direction = decoder.string()?;
+            let success = decoder.bool()?;
+            let context = decoder.context()?;
+
+            let sequence_count = decoder.len()?;
+            let mut results = Vec::with_capacity(sequence_count);
+            for _ in 0..sequence_count {
+                let line_id = decoder.string()?;
+                let direction = decoder.string()?;
+                let mode = decoder.string()?;
+                let line_string_count = decoder.len()?;
+                let line_strings = (0..line_string_count)
+                    .map(|_| decode_line_string(&mut decoder))
+                    .collect::<Result<_, _>>()?;
+                let branch_count = decoder.len()?;
+                let stop_point_sequences = (0..branch_count)
+                    .map(|_| decode_branch(&mut decoder))
+                    .collect::<Result<_, _>>()?;
+
+                results.push(RouteSequence {
+                    line_id,
+                    direction,
+                    mode,
+                    line_strings,
+                    stop_point_sequences,
+                });
+            }
+
+            directions.insert(
+                direction,
+                RouteResponse {
+                    context,
+                    success,
+                    results,
+                },
+            );
+        }
+
+        routes.insert(line_id, directions);
+    }
+
+    decoder.finish()?;
+    Ok(RoutesFile { routes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::error::parse_json;
+
+    #[test]
+    fn stations_round_trip_to_fixed_precision() {
+        let stations: StationsResponse = parse_json(
+            "stations.json",
+            r#"{"success":true,"context":{"request_time":"2025-04-04T10:58:23Z",
+                "response_time":"2025-04-04T10:58:24Z","response_latency":0.5,"query":"SELECT"},
+               "results":[
+                {"StationUniqueId":"940GZZLUACT","StationName":"Acton Town","FareZones":"3",
+                 "Wifi":true,"OutsideStationUniqueId":"940GZZLUACT-OUT",
+                 "Lat":51.502500000000005,"Lon":-0.278126,"ComponentStations":["940GZZLUACT"]},
+                {"StationUniqueId":"910GACTNCTL","StationName":"Acton Central","FareZones":"2|3",
+                 "HubNaptanCode":"HUBACC","OutsideStationUniqueId":"910GACTNCTL-OUT",
+                 "Lat":51.508757,"Lon":-0.26343}
+            ]}"#,
+        )
+        .unwrap();
+
+        let bytes = encode_stations(&stations, "");
+        let decoded = decode_stations("stations.bin", &bytes).unwrap();
+        assert_eq!(decoded.context, stations.context);
+        let decoded = decoded.results;
+
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].lat, 51.5025);
+        assert_eq!(decoded[0].component_stations, vec!["940GZZLUACT"]);
+        assert_eq!(decoded[1].lon, -0.26343);
+        assert_eq!(decoded[1].hub_naptan_code.as_deref(), Some("HUBACC"));
+        assert!(!decoded[1].wifi);
+    }
+
+    #[test]
+    fn routes_round_trip() {
+        let routes: RoutesFile = parse_json(
+            "rail_routes.json",
+            r#"{"victoria":{"inbound":{"success":true,"results":[
+                {"LineId":"victoria","Direction":"inbound","Mode":"tube",
+                 "LineStrings":["[[[-0.1,51.5],[-0.125,51.53]]]", "not a line string"],
+                 "StopPointSequences":[{"LineId":"victoria","LineName":"Victoria",
+                  "Direction":"inbound","BranchId":-1,"NextBranchIds":[2],"PrevBranchIds":[],
+                  "StopPoint":[{"Id":"940GZZLUBXN","Name":"Brixton","Lat":51.4627,"Lon":-0.1145},
+                               {"Id":"940GZZLUSKW","StationId":"940GZZLUSKW"}]}]}
+            ]},"outbound":{"success":false,"results":[]}}}"#,
+        )
+        .unwrap();
+
+        let decoded = decode_routes("rail_routes.bin", &encode_routes(&routes, "")).unwrap();
+
+        let inbound = &decoded.routes["victoria"]["inbound"];
+        assert!(inbound.success);
+        assert_eq!(inbound.results[0].mode, "tube");
+        assert_eq!(
+            inbound.results[0].line_strings,
+            vec!["[[[-0.1,51.5],[-0.125,51.53]]]", "not a line string"]
+        );
+        let branch = &inbound.results[0].stop_point_sequences[0];
+        assert_eq!(
+            (branch.branch_id, branch.next_branch_ids.as_slice()),
+            (-1, &[2][..])
+        );
+        assert_eq!(branch.stop_point[0].name.as_deref(), Some("Brixton"));
+        assert_eq!(branch.stop_point[0].lon, Some(-0.1145));
+        assert_eq!(
+            branch.stop_point[1].station_id.as_deref(),
+            Some("940GZZLUSKW")
+        );
+        assert_eq!(branch.stop_point[1].lat, None);
+        assert!(!decoded.routes["victoria"]["outbound"].success);
+    }
+
+    #[test]
+    fn rejects_other_versions_and_truncated_data() {
+        let mut bytes = encode_platforms(
+            &PlatformsResponse {
+                context: None,
+                success: true,
+                results: Vec::new(),
+            },
+            "",
+        );
+        bytes[MAGIC.len()] = VERSION + 1;
+        let err = decode_platforms("platforms.bin", &bytes).unwrap_err();
+        assert!(err.to_string().contains("unsupported format version"));
+
+        let bytes = encode_stations(
+            &StationsResponse {
+                context: None,
+                success: true,
+                results: Vec::new(),
+            },
+            "",
+        );
+        let err = decode_stations("stations.bin", &bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err, DataError::Decode { .. }));
+    }
+
+    #[test]
+    fn keeps_an_unsuccessful_response_unsuccessful() {
+        let bytes = encode_platforms(
+            &PlatformsResponse {
+                context: None,
+                success: false,
+                results: Vec::new(),
+            },
+            "",
+        );
+        assert!(!decode_platforms("platforms.bin", &bytes).unwrap().success);
+    }
+
+    #[test]
+    fn tells_when_written_from_other_json() {
+        let json = r#"{"success":true,"results":[]}"#;
+        let response: StationsResponse = parse_json("stations.json", json).unwrap();
+        let bytes = encode_stations(&response, json);
+        assert!(is_current(&bytes, json));
+        assert!(!is_current(&bytes, r#"{"success":true,"results":[{}]}"#));
+        assert!(!is_current(&bytes[..6], json));
+    }
+}
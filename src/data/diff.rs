@@ -0,0 +1,568 @@
+//! Comparing two snapshots of the datasets.
+//!
+//! When the datasets are refreshed from the API, [`diff`] lists what changed
+//! between the old and new files: stations added, removed, renamed, moved or
+//! rezoned, platforms added, removed or changed, and route geometry per line.
+//! The changes can be written as GeoJSON with [`to_geojson`], to overlay on a
+//! map. Used by `tfl_data diff`.
+
+use super::loader::parse_line_string;
+use super::model::{Platform, RoutesFile, Station};
+use crate::utils::geo::haversine_distance;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Default distance in metres a station must move by to be reported
+pub const DEFAULT_MOVE_THRESHOLD: f64 = 50.0;
+
+/// One version of the datasets
+pub struct Snapshot {
+    pub stations: Vec<Station>,
+    pub platforms: Vec<Platform>,
+    pub routes: RoutesFile,
+}
+
+/// A station as it is in a snapshot, for reporting
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationRef {
+    pub id: String,
+    pub name: String,
+    pub point: [f64; 2],
+}
+
+impl From<&Station> for StationRef {
+    fn from(station: &Station) -> Self {
+        Self {
+            id: station.station_unique_id.clone(),
+            name: station.station_name.clone(),
+            point: [station.lon, station.lat],
+        }
+    }
+}
+
+/// Something that differs between two snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    StationAdded(StationRef),
+    StationRemoved(StationRef),
+    StationRenamed {
+        station: StationRef,
+        old_name: String,
+    },
+    StationMoved {
+        station: StationRef,
+        old_point: [f64; 2],
+        distance: f64,
+    },
+    FareZonesChanged {
+        station: StationRef,
+        old_zones: String,
+        new_zones: String,
+    },
+    /// Platforms are identified by their ID and line, as a platform shared
+    /// by several lines is listed once for each
+    PlatformAdded {
+        id: String,
+        line: String,
+        station_id: String,
+    },
+    PlatformRemoved {
+        id: String,
+        line: String,
+        station_id: String,
+    },
+    PlatformChanged {
+        id: String,
+        line: String,
+        station_id: String,
+        fields: Vec<&'static str>,
+    },
+    /// Line strings of a line's routes that are only in one snapshot
+    RouteChanged {
+        line_id: String,
+        added: Vec<Vec<[f64; 2]>>,
+        removed: Vec<Vec<[f64; 2]>>,
+    },
+}
+
+impl Change {
+    /// A short name for the kind of change, used in the GeoJSON properties
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::StationAdded(_) => "station-added",
+            Change::StationRemoved(_) => "station-removed",
+            Change::StationRenamed { .. } => "station-renamed",
+            Change::StationMoved { .. } => "station-moved",
+            Change::FareZonesChanged { .. } => "fare-zones-changed",
+            Change::PlatformAdded { .. } => "platform-added",
+            Change::PlatformRemoved { .. } => "platform-removed",
+            Change::PlatformChanged { .. } => "platform-changed",
+            Change::RouteChanged { .. } => "route-changed",
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::StationAdded(station) => {
+                write!(f, "+ station {} ({})", station.id, station.name)
+            }
+            Change::StationRemoved(station) => {
+                write!(f, "- station {} ({})", station.id, station.name)
+            }
+            Change::StationRenamed { station, old_name } => write!(
+                f,
+                "~ station {} renamed from {} to {}",
+                station.id, old_name, station.name
+            ),
+            Change::StationMoved {
+                station, distance, ..
+            } => write!(
+                f,
+                "~ station {} ({}) moved {:.0} m",
+                station.id, station.name, distance
+            ),
+            Change::FareZonesChanged {
+                station,
+                old_zones,
+                new_zones,
+            } => write!(
+                f,
+                "~ station {} ({}) fare zones {} -> {}",
+                station.id, station.name, old_zones, new_zones
+            ),
+            Change::PlatformAdded {
+                id,
+                line,
+                station_id,
+            } => write!(f, "+ platform {} on {} at {}", id, line, station_id),
+            Change::PlatformRemoved {
+                id,
+                line,
+                station_id,
+            } => write!(f, "- platform {} on {} at {}", id, line, station_id),
+            Change::PlatformChanged {
+                id, line, fields, ..
+            } => write!(
+                f,
+                "~ platform {} on {} changed {}",
+                id,
+                line,
+                fields.join(", ")
+            ),
+            Change::RouteChanged {
+                line_id,
+                added,
+                removed,
+            } => write!(
+                f,
+                "~ line {} routes: {} line strings added, {} removed",
+                line_id,
+                added.len(),
+                removed.len()
+            ),
+        }
+    }
+}
+
+/// The changes found by [`diff`], in a stable order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffReport {
+    pub changes: Vec<Change>,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Counts of each kind of change, e.g. "2 station-added, 1 route-changed"
+    pub fn summary(&self) -> String {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for change in &self.changes {
+            *counts.entry(change.kind()).or_default() += 1;
+        }
+        if counts.is_empty() {
+            return "no changes".to_string();
+        }
+        counts
+            .into_iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn stations_by_id(stations: &[Station]) -> BTreeMap<&str, &Station> {
+    stations
+        .iter()
+        .map(|s| (s.station_unique_id.as_str(), s))
+        .collect()
+}
+
+fn platforms_by_key(platforms: &[Platform]) -> BTreeMap<(&str, &str), &Platform> {
+    platforms
+        .iter()
+        .map(|p| ((p.platform_unique_id.as_str(), p.line.as_str()), p))
+        .collect()
+}
+
+/// Names of the fields that differ between two versions of a platform
+fn changed_platform_fields(old: &Platform, new: &Platform) -> Vec<&'static str> {
+    [
+        (
+            "StationUniqueId",
+            old.station_unique_id != new.station_unique_id,
+        ),
+        ("PlatformNumber", old.platform_number != new.platform_number),
+        (
+            "CardinalDirection",
+            old.cardinal_direction != new.cardinal_direction,
+        ),
+        (
+            "DirectionTowards",
+            old.direction_towards != new.direction_towards,
+        ),
+        (
+            "PlatformFriendlyName",
+            old.platform_friendly_name != new.platform_friendly_name,
+        ),
+        (
+            "IsCustomerFacing",
+            old.is_customer_facing != new.is_customer_facing,
+        ),
+        (
+            "HasServiceInterchange",
+            old.has_service_interchange != new.has_service_interchange,
+        ),
+        (
+            "PlatformServiceGroupName",
+            old.platform_service_group_name != new.platform_service_group_name,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, changed)| changed.then_some(field))
+    .collect()
+}
+
+/// Every line string of a line's routes, in either direction
+fn line_strings(routes: &RoutesFile, line_id: &str) -> BTreeSet<String> {
+    routes
+        .routes
+        .get(line_id)
+        .into_iter()
+        .flat_map(|directions| directions.values())
+        .flat_map(|response| &response.results)
+        .flat_map(|sequence| &sequence.line_strings)
+        .map(|line_string| line_string.trim().to_string())
+        .collect()
+}
+
+fn parse_all<'a>(line_strings: impl Iterator<Item = &'a String>) -> Vec<Vec<[f64; 2]>> {
+    line_strings
+        .filter_map(|line_string| parse_line_string(line_string).ok())
+        .collect()
+}
+
+/// Compare two snapshots
+///
+/// Stations moved by less than `move_threshold` metres are not reported.
+pub fn diff(old: &Snapshot, new: &Snapshot, move_threshold: f64) -> DiffReport {
+    let mut changes = Vec::new();
+
+    // Stations
+    let old_stations = stations_by_id(&old.stations);
+    let new_stations = stations_by_id(&new.stations);
+    for (id, old_station) in &old_stations {
+        if !new_stations.contains_key(id) {
+            changes.push(Change::StationRemoved(StationRef::from(*old_station)));
+        }
+    }
+    for (id, new_station) in &new_stations {
+        let station = StationRef::from(*new_station);
+        let Some(old_station) = old_stations.get(id) else {
+            changes.push(Change::StationAdded(station));
+            continue;
+        };
+
+        if old_station.station_name != new_station.station_name {
+            changes.push(Change::StationRenamed {
+                station: station.clone(),
+                old_name: old_station.station_name.clone(),
+            });
+        }
+        let old_point = [old_station.lon, old_station.lat];
+        let distance = haversine_distance(old_point, station.point);
+        if distance > move_threshold {
+            changes.push(Change::StationMoved {
+                station: station.clone(),
+                old_point,
+                distance,
+            });
+        }
+        if old_station.fare_zones != new_station.fare_zones {
+            changes.push(Change::FareZonesChanged {
+                station,
+                old_zones: old_station.fare_zones.clone(),
+                new_zones: new_station.fare_zones.clone(),
+            });
+        }
+    }
+
+    // Platforms
+    let old_platforms = platforms_by_key(&old.platforms);
+    let new_platforms = platforms_by_key(&new.platforms);
+    for ((id, line), platform) in &old_platforms {
+        if !new_platforms.contains_key(&(id, line)) {
+            changes.push(Change::PlatformRemoved {
+                id: id.to_string(),
+                line: line.to_string(),
+                station_id: platform.station_unique_id.clone(),
+            });
+        }
+    }
+    for ((id, line), platform) in &new_platforms {
+        let station_id = platform.station_unique_id.clone();
+        match old_platforms.get(&(id, line)) {
+            None => changes.push(Change::PlatformAdded {
+                id: id.to_string(),
+                line: line.to_string(),
+                station_id,
+            }),
+            Some(old_platform) => {
+                let fields = changed_platform_fields(old_platform, platform);
+                if !fields.is_empty() {
+                    changes.push(Change::PlatformChanged {
+                        id: id.to_string(),
+                        line: line.to_string(),
+                        station_id,
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+
+    // Routes
+    let line_ids: BTreeSet<&String> = old
+        .routes
+        .routes
+        .keys()
+        .chain(new.routes.routes.keys())
+        .collect();
+    for line_id in line_ids {
+        let old_strings = line_strings(&old.routes, line_id);
+        let new_strings = line_strings(&new.routes, line_id);
+        if old_strings != new_strings {
+            changes.push(Change::RouteChanged {
+                line_id: line_id.clone(),
+                added: parse_all(new_strings.difference(&old_strings)),
+                removed: parse_all(old_strings.difference(&new_strings)),
+            });
+        }
+    }
+
+    DiffReport { changes }
+}
+
+fn feature(change: &Change, geometry: Value, extra: Value) -> Value {
+    let mut properties = json!({
+        "change": change.kind(),
+        "description": change.to_string(),
+    });
+    if let (Some(properties), Value::Object(extra)) = (properties.as_object_mut(), extra) {
+        properties.extend(extra);
+    }
+    json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+}
+
+fn point(point: [f64; 2]) -> Value {
+    json!({ "type": "Point", "coordinates": point })
+}
+
+/// The changes as a GeoJSON `FeatureCollection`
+///
+/// Station changes are points (and a line from the old to the new position
+/// for a move), platform changes are points at their station, and route
+/// changes are a `MultiLineString` each of the added and removed geometry.
+/// Every feature has `change` and `description` properties.
+pub fn to_geojson(report: &DiffReport, old: &Snapshot, new: &Snapshot) -> Value {
+    let old_stations = stations_by_id(&old.stations);
+    let new_stations = stations_by_id(&new.stations);
+    let station_point = |id: &str| {
+        new_stations
+            .get(id)
+            .or_else(|| old_stations.get(id))
+            .map(|s| [s.lon, s.lat])
+    };
+
+    let mut features = Vec::new();
+    for change in &report.changes {
+        match change {
+            Change::StationAdded(station)
+            | Change::StationRemoved(station)
+            | Change::StationRenamed { station, .. }
+            | Change::FareZonesChanged { station, .. } => features.push(feature(
+                change,
+                point(station.point),
+                json!({ "id": station.id, "name": station.name }),
+            )),
+            Change::StationMoved {
+                station,
+                old_point,
+                distance,
+            } => {
+                let extra = json!({ "id": station.id, "name": station.name, "distance": distance });
+                features.push(feature(change, point(station.point), extra.clone()));
+                features.push(feature(
+                    change,
+                    json!({ "type": "LineString", "coordinates": [old_point, station.point] }),
+                    extra,
+                ));
+            }
+            Change::PlatformAdded {
+                id,
+                line,
+                station_id,
+            }
+            | Change::PlatformRemoved {
+                id,
+                line,
+                station_id,
+            }
+            | Change::PlatformChanged {
+                id,
+                line,
+                station_id,
+                ..
+            } => {
+                if let Some(location) = station_point(station_id) {
+                    features.push(feature(
+                        change,
+                        point(location),
+                        json!({ "id": id, "line": line, "station": station_id }),
+                    ));
+                }
+            }
+            Change::RouteChanged {
+                line_id,
+                added,
+                removed,
+            } => {
+                for (side, geometry) in [("added", added), ("removed", removed)] {
+                    if !geometry.is_empty() {
+                        features.push(feature(
+                            change,
+                            json!({ "type": "MultiLineString", "coordinates": geometry }),
+                            json!({ "line": line_id, "side": side }),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::error::parse_json;
+
+    fn snapshot(stations: &str, platforms: &str, routes: &str) -> Snapshot {
+        Snapshot {
+            stations: parse_json("stations.json", stations).unwrap(),
+            platforms: parse_json("platforms.json", platforms).unwrap(),
+            routes: parse_json("rail_routes.json", routes).unwrap(),
+        }
+    }
+
+    fn station(id: &str, name: &str, zones: &str, lat: f64) -> String {
+        format!(
+            r#"{{"StationUniqueId":"{id}","StationName":"{name}","FareZones":"{zones}",
+                "OutsideStationUniqueId":"","Lat":{lat},"Lon":-0.1}}"#
+        )
+    }
+
+    fn platform(id: &str, line: &str, towards: &str) -> String {
+        format!(
+            r#"{{"PlatformUniqueId":"{id}","StationUniqueId":"A","PlatformFriendlyName":"",
+                "IsCustomerFacing":true,"HasServiceInterchange":false,"StationName":"",
+                "FareZones":"1","OutsideStationUniqueId":"","StopAreaNaptanCode":"",
+                "Line":"{line}","DirectionTowards":"{towards}"}}"#
+        )
+    }
+
+    fn routes(line_string: &str) -> String {
+        format!(
+            r#"{{"central":{{"inbound":{{"success":true,"results":[{{"LineId":"central",
+                "Direction":"inbound","Mode":"tube","LineStrings":["{line_string}"]}}]}}}}}}"#
+        )
+    }
+
+    #[test]
+    fn lists_changes_and_writes_geojson() {
+        let old = snapshot(
+            &format!(
+                "[{},{},{}]",
+                station("A", "Aldgate", "1", 51.5),
+                station("B", "Bank", "1", 51.51),
+                station("C", "Chesham", "9", 51.7),
+            ),
+            &format!(
+                "[{},{}]",
+                platform("A-1", "district", "West"),
+                platform("A-1", "circle", "West"),
+            ),
+            &routes("[[[-0.1,51.5],[-0.1,51.51]]]"),
+        );
+        let new = snapshot(
+            &format!(
+                "[{},{},{}]",
+                station("A", "Aldgate East", "1", 51.5),
+                station("B", "Bank", "1", 51.52),
+                station("D", "Dalston", "2", 51.55),
+            ),
+            &format!(
+                "[{},{}]",
+                platform("A-1", "district", "East"),
+                platform("A-1", "hammersmith-city", "West"),
+            ),
+            &routes("[[[-0.1,51.5],[-0.1,51.52]]]"),
+        );
+
+        let report = diff(&old, &new, DEFAULT_MOVE_THRESHOLD);
+        let kinds: Vec<&str> = report.changes.iter().map(Change::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                "station-removed",
+                "station-renamed",
+                "station-moved",
+                "station-added",
+                "platform-removed",
+                "platform-changed",
+                "platform-added",
+                "route-changed",
+            ]
+        );
+        assert!(matches!(
+            &report.changes[5],
+            Change::PlatformChanged { fields, .. } if fields == &["DirectionTowards"]
+        ));
+        assert!(matches!(
+            &report.changes[7],
+            Change::RouteChanged { added, removed, .. } if added.len() == 1 && removed.len() == 1
+        ));
+
+        let geojson = to_geojson(&report, &old, &new);
+        let features = geojson["features"].as_array().unwrap();
+        // The move is a point and a line, and the route change is two features
+        assert_eq!(features.len(), report.changes.len() + 2);
+        assert_eq!(features[0]["properties"]["change"], "station-removed");
+        assert_eq!(features[0]["geometry"]["coordinates"][1], 51.7);
+    }
+}
@@ -0,0 +1,114 @@
+//! Errors from reading and parsing the TfL datasets.
+
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Why a dataset file could not be loaded
+///
+/// Every variant carries the name of the file involved, so the message shown
+/// to the user says which dataset to look at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataError {
+    /// The file could not be fetched or read at all
+    Fetch { file: String, message: String },
+    /// The server answered, but not with a success status
+    // Only the fetching sources, which are wasm32-only, see HTTP statuses
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    HttpStatus { file: String, status: u16 },
+    /// The file is not valid JSON, or doesn't match the expected structure
+    Parse {
+        file: String,
+        /// Where in the document the problem is, e.g. `results[12].lat`
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A compact binary file is corrupt, or in a format version we can't read
+    Decode { file: String, message: String },
+    /// The file parsed, but the API response says `success: false`
+    Unsuccessful {
+        file: String,
+        /// Which part of the file was unsuccessful, for combined route files
+        context: Option<String>,
+    },
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::Fetch { file, message } => {
+                write!(f, "Could not load {}: {}", file, message)
+            }
+            DataError::HttpStatus { file, status } => {
+                write!(
+                    f,
+                    "Could not load {}: server returned HTTP {}",
+                    file, status
+                )
+            }
+            DataError::Parse {
+                file,
+                path,
+                line,
+                column,
+                message,
+            } => {
+                write!(
+                    f,
+                    "Invalid data in {} at line {}, column {}",
+                    file, line, column
+                )?;
+                if !path.is_empty() && path != "." {
+                    write!(f, " ({})", path)?;
+                }
+                write!(f, ": {}", message)
+            }
+            DataError::Decode { file, message } => {
+                write!(f, "Invalid binary data in {}: {}", file, message)
+            }
+            DataError::Unsuccessful { file, context } => match context {
+                Some(context) => write!(f, "Unsuccessful response in {} for {}", file, context),
+                None => write!(f, "Unsuccessful response in {}", file),
+            },
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+/// Parse a JSON document, reporting the path to any value that fails
+pub fn parse_json<T: DeserializeOwned>(file: &str, text: &str) -> Result<T, DataError> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        parse_error(file, path, e.into_inner())
+    })?;
+
+    // Like serde_json::from_str, reject anything after the document
+    deserializer
+        .end()
+        .map_err(|e| parse_error(file, String::new(), e))?;
+
+    Ok(value)
+}
+
+fn parse_error(file: &str, path: String, error: serde_json::Error) -> DataError {
+    let (line, column) = (error.line(), error.column());
+
+    // serde_json appends the position to its message, but we report it separately
+    let message = error.to_string();
+    let message = message
+        .strip_suffix(&format!(" at line {} column {}", line, column))
+        .unwrap_or(&message)
+        .to_string();
+
+    DataError::Parse {
+        file: file.to_string(),
+        path,
+        line,
+        column,
+        message,
+    }
+}
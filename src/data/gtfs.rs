@@ -0,0 +1,717 @@
+//! Networks from a GTFS static feed, in place of the TfL datasets.
+//!
+//! A feed is a set of CSV tables; the ones read here are `stops.txt`,
+//! `routes.txt`, `trips.txt`, `stop_times.txt` and, if there is one,
+//! `shapes.txt`. They're read through a [`DataSource`], so a feed can be a
+//! directory, a zip (see [`super::source::ZipSource`]) or a URL.
+//!
+//! [`to_repository`] maps the feed onto the structures the TfL data uses:
+//!
+//! - Stations are the feed's stations (`location_type` 1) and any stops
+//!   that aren't part of one, and platforms the stops vehicles call at, one
+//!   per line
+//! - Each GTFS route is a line, with a route sequence per direction made of
+//!   its distinct shapes, or of its distinct stop patterns without shapes
+//! - A route's `route_color` is kept as the line's colour, see
+//!   [`TflDataRepository::line_color`]
+//! - Its trips give how often it runs, see [`ServicePatterns::from_gtfs`]
+//!
+//! The records also serialize, with [`write_table`], for writing feeds; see
+//! [`super::gtfs_export`].
+
+use super::error::DataError;
+use super::model::{Platform, RouteSequence, Station};
+use super::services::ServicePatterns;
+use super::source::DataSource;
+use super::{LineDetails, TflDataRepository};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A row of `stops.txt`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsStop {
+    pub stop_id: String,
+    #[serde(default)]
+    pub stop_code: Option<String>,
+    #[serde(default)]
+    pub stop_name: String,
+    /// Entrances and generic nodes may not have a position
+    #[serde(default)]
+    pub stop_lat: Option<f64>,
+    #[serde(default)]
+    pub stop_lon: Option<f64>,
+    #[serde(default)]
+    pub zone_id: Option<String>,
+    /// 0 or empty for a stop or platform, 1 for a station
+    #[serde(default)]
+    pub location_type: Option<u8>,
+    #[serde(default)]
+    pub parent_station: Option<String>,
+    #[serde(default)]
+    pub platform_code: Option<String>,
+}
+
+impl GtfsStop {
+    fn is_station(&self) -> bool {
+        self.location_type == Some(1)
+    }
+
+    /// Whether vehicles call here, rather than it being a station or entrance
+    fn is_boarding_point(&self) -> bool {
+        matches!(self.location_type, None | Some(0))
+    }
+}
+
+/// A row of `routes.txt`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsRoute {
+    pub route_id: String,
+    #[serde(default)]
+    pub agency_id: Option<String>,
+    #[serde(default)]
+    pub route_short_name: String,
+    #[serde(default)]
+    pub route_long_name: String,
+    pub route_type: u16,
+    /// Hex colour without a `#`, e.g. `E32017`
+    #[serde(default)]
+    pub route_color: Option<String>,
+    #[serde(default)]
+    pub route_text_color: Option<String>,
+}
+
+impl GtfsRoute {
+    /// The name passengers know the route by
+    pub fn name(&self) -> &str {
+        [&self.route_short_name, &self.route_long_name]
+            .into_iter()
+            .find(|name| !name.is_empty())
+            .unwrap_or(&self.route_id)
+    }
+
+    /// The route's colour as `#RRGGBB`, if it has a valid one
+    pub fn color(&self) -> Option<String> {
+        let color = self.route_color.as_deref()?.trim_start_matches('#');
+        (color.len() == 6 && color.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| format!("#{}", color.to_ascii_uppercase()))
+    }
+
+    /// The TfL mode closest to the route type, basic or extended
+    pub fn mode(&self) -> &'static str {
+        match self.route_type {
+            0 | 900..=999 => "tram",
+            1 | 400..=499 => "tube",
+            3 | 11 | 700..=899 => "bus",
+            4 | 1000..=1099 | 1200 => "river-bus",
+            5 | 6 | 1300..=1399 => "cable-car",
+            7 | 1400 => "funicular",
+            _ => "national-rail",
+        }
+    }
+}
+
+/// A row of `trips.txt`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsTrip {
+    pub route_id: String,
+    pub service_id: String,
+    pub trip_id: String,
+    #[serde(default)]
+    pub trip_headsign: Option<String>,
+    /// 0 and 1 for travel in opposite directions, if given
+    #[serde(default)]
+    pub direction_id: Option<u8>,
+    #[serde(default)]
+    pub shape_id: Option<String>,
+}
+
+impl GtfsTrip {
+    /// The TfL direction the trip's `direction_id` stands for
+    pub fn direction(&self) -> &'static str {
+        match self.direction_id {
+            Some(1) => "inbound",
+            _ => "outbound",
+        }
+    }
+}
+
+/// A row of `shapes.txt`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsShapePoint {
+    pub shape_id: String,
+    pub shape_pt_lat: f64,
+    pub shape_pt_lon: f64,
+    pub shape_pt_sequence: u32,
+}
+
+/// A row of `stop_times.txt`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsStopTime {
+    pub trip_id: String,
+    /// `HH:MM:SS`, which may be past 24:00:00 for trips after midnight
+    #[serde(default)]
+    pub arrival_time: Option<String>,
+    #[serde(default)]
+    pub departure_time: Option<String>,
+    pub stop_id: String,
+    pub stop_sequence: u32,
+}
+
+/// A row of `agency.txt`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsAgency {
+    pub agency_id: String,
+    pub agency_name: String,
+    pub agency_url: String,
+    pub agency_timezone: String,
+}
+
+/// A row of `calendar.txt`: the days of the week a service runs, 1 or 0,
+/// between two `YYYYMMDD` dates
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsCalendar {
+    pub service_id: String,
+    pub monday: u8,
+    pub tuesday: u8,
+    pub wednesday: u8,
+    pub thursday: u8,
+    pub friday: u8,
+    pub saturday: u8,
+    pub sunday: u8,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+/// A row of `frequencies.txt`: a trip repeated every `headway_secs`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GtfsFrequency {
+    pub trip_id: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub headway_secs: u32,
+    /// 0 for a headway, rather than a timetable at exactly these intervals
+    pub exact_times: u8,
+}
+
+/// Seconds since midnight from `HH:MM:SS`, or `HH:MM`
+pub fn parse_time(time: &str) -> Option<u32> {
+    let mut parts = time.splitn(3, ':').map(str::parse::<u32>);
+    let (hours, minutes) = (parts.next()?.ok()?, parts.next()?.ok()?);
+    let seconds = parts.next().map_or(Some(0), Result::ok)?;
+    Some(hours * 3_600 + minutes * 60 + seconds)
+}
+
+/// `HH:MM:SS` from seconds since midnight, past 24:00:00 if need be
+pub fn format_time(seconds: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// A GTFS table: its file name, and its columns in the record's field order
+pub trait GtfsTable: Serialize {
+    const FILE: &'static str;
+    const COLUMNS: &'static [&'static str];
+}
+
+impl GtfsTable for GtfsAgency {
+    const FILE: &'static str = "agency.txt";
+    const COLUMNS: &'static [&'static str] =
+        &["agency_id", "agency_name", "agency_url", "agency_timezone"];
+}
+
+impl GtfsTable for GtfsStop {
+    const FILE: &'static str = "stops.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "stop_id",
+        "stop_code",
+        "stop_name",
+        "stop_lat",
+        "stop_lon",
+        "zone_id",
+        "location_type",
+        "parent_station",
+        "platform_code",
+    ];
+}
+
+impl GtfsTable for GtfsRoute {
+    const FILE: &'static str = "routes.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "route_id",
+        "agency_id",
+        "route_short_name",
+        "route_long_name",
+        "route_type",
+        "route_color",
+        "route_text_color",
+    ];
+}
+
+impl GtfsTable for GtfsTrip {
+    const FILE: &'static str = "trips.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "route_id",
+        "service_id",
+        "trip_id",
+        "trip_headsign",
+        "direction_id",
+        "shape_id",
+    ];
+}
+
+impl GtfsTable for GtfsShapePoint {
+    const FILE: &'static str = "shapes.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "shape_id",
+        "shape_pt_lat",
+        "shape_pt_lon",
+        "shape_pt_sequence",
+    ];
+}
+
+impl GtfsTable for GtfsStopTime {
+    const FILE: &'static str = "stop_times.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "trip_id",
+        "arrival_time",
+        "departure_time",
+        "stop_id",
+        "stop_sequence",
+    ];
+}
+
+impl GtfsTable for GtfsCalendar {
+    const FILE: &'static str = "calendar.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "service_id",
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+        "start_date",
+        "end_date",
+    ];
+}
+
+impl GtfsTable for GtfsFrequency {
+    const FILE: &'static str = "frequencies.txt";
+    const COLUMNS: &'static [&'static str] = &[
+        "trip_id",
+        "start_time",
+        "end_time",
+        "headway_secs",
+        "exact_times",
+    ];
+}
+
+/// Write the rows of a table as CSV, with the header even if there are none
+pub fn write_table<T: GtfsTable>(rows: &[T]) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    writer
+        .write_record(T::COLUMNS)
+        .expect("writing to memory can't fail");
+    for row in rows {
+        writer
+            .serialize(row)
+            .expect("GTFS records always serialize");
+    }
+    let bytes = writer.into_inner().expect("writing to memory can't fail");
+    String::from_utf8(bytes).expect("the records are all UTF-8")
+}
+
+/// The tables of a GTFS feed that make up its network
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GtfsFeed {
+    pub stops: Vec<GtfsStop>,
+    pub routes: Vec<GtfsRoute>,
+    pub trips: Vec<GtfsTrip>,
+    pub shapes: Vec<GtfsShapePoint>,
+    pub stop_times: Vec<GtfsStopTime>,
+    pub calendar: Vec<GtfsCalendar>,
+    pub frequencies: Vec<GtfsFrequency>,
+}
+
+impl GtfsFeed {
+    /// Read the feed's tables from a source
+    ///
+    /// `shapes.txt`, `calendar.txt` and `frequencies.txt` are optional, as
+    /// they are in GTFS; every other table must be there.
+    pub async fn load(source: &impl DataSource) -> Result<Self, DataError> {
+        Ok(Self {
+            stops: load_table(source, "stops.txt").await?,
+            routes: load_table(source, "routes.txt").await?,
+            trips: load_table(source, "trips.txt").await?,
+            shapes: load_optional_table(source, "shapes.txt").await?,
+            stop_times: load_table(source, "stop_times.txt").await?,
+            calendar: load_optional_table(source, "calendar.txt").await?,
+            frequencies: load_optional_table(source, "frequencies.txt").await?,
+        })
+    }
+}
+
+async fn load_table<T: DeserializeOwned>(
+    source: &impl DataSource,
+    file: &str,
+) -> Result<Vec<T>, DataError> {
+    let text = source.read_path(file, &|_, _| {}).await?;
+    parse_table(file, &text)
+}
+
+/// Read a table the feed may leave out, as no rows if it does
+async fn load_optional_table<T: DeserializeOwned>(
+    source: &impl DataSource,
+    file: &str,
+) -> Result<Vec<T>, DataError> {
+    match source.read_path(file, &|_, _| {}).await {
+        Ok(text) => parse_table(file, &text),
+        Err(DataError::Fetch { .. } | DataError::HttpStatus { .. }) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parse a GTFS table, a CSV file with a header row
+///
+/// Columns the record doesn't have are ignored, as feeds often add their own.
+pub fn parse_table<T: DeserializeOwned>(file: &str, text: &str) -> Result<Vec<T>, DataError> {
+    // Feeds exported from spreadsheets often start with a byte order mark
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| csv_error(file, None, e))?
+        .clone();
+
+    reader
+        .deserialize()
+        .map(|record| record.map_err(|e| csv_error(file, Some(&headers), e)))
+        .collect()
+}
+
+fn csv_error(file: &str, headers: Option<&csv::StringRecord>, error: csv::Error) -> DataError {
+    let (position, field) = match error.kind() {
+        csv::ErrorKind::Deserialize { pos, err } => (pos.clone(), err.field()),
+        _ => (error.position().cloned(), None),
+    };
+    let column_name = field
+        .and_then(|field| headers?.get(field as usize))
+        .unwrap_or_default();
+
+    DataError::Parse {
+        file: file.to_string(),
+        path: column_name.to_string(),
+        line: position.map_or(0, |position| position.line() as usize),
+        column: field.map_or(0, |field| field as usize + 1),
+        message: match error.into_kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err.kind().to_string(),
+            kind => format!("{:?}", kind),
+        },
+    }
+}
+
+/// A line string in the API's `[[[lon,lat],...]]` form
+fn line_string(points: &[[f64; 2]]) -> String {
+    let points: Vec<String> = points
+        .iter()
+        .map(|[lon, lat]| format!("[{},{}]", lon, lat))
+        .collect();
+    format!("[[{}]]", points.join(","))
+}
+
+/// A station for a GTFS stop, with what the TfL data has but GTFS doesn't
+/// left empty
+fn station(stop: &GtfsStop, lat: f64, lon: f64) -> Station {
+    Station {
+        station_unique_id: stop.stop_id.clone(),
+        station_name: stop.stop_name.clone(),
+        fare_zones: stop.zone_id.clone().unwrap_or_default(),
+        hub_naptan_code: None,
+        wifi: false,
+        outside_station_unique_id: String::new(),
+        lat,
+        lon,
+        component_stations: Vec::new(),
+    }
+}
+
+/// Build a repository from a feed, see the [module docs](self)
+pub fn to_repository(feed: &GtfsFeed) -> TflDataRepository {
+    let stops_by_id: HashMap<&str, &GtfsStop> = feed
+        .stops
+        .iter()
+        .map(|stop| (stop.stop_id.as_str(), stop))
+        .collect();
+    let routes_by_id: HashMap<&str, &GtfsRoute> = feed
+        .routes
+        .iter()
+        .map(|route| (route.route_id.as_str(), route))
+        .collect();
+    let trips_by_id: HashMap<&str, &GtfsTrip> = feed
+        .trips
+        .iter()
+        .map(|trip| (trip.trip_id.as_str(), trip))
+        .collect();
+
+    // Stations, and the stops that aren't part of one
+    let station_of = |stop: &GtfsStop| -> String {
+        match &stop.parent_station {
+            Some(parent) if stops_by_id.contains_key(parent.as_str()) => parent.clone(),
+            _ => stop.stop_id.clone(),
+        }
+    };
+    let stations: Vec<Station> = feed
+        .stops
+        .iter()
+        .filter(|stop| {
+            stop.is_station() || (stop.is_boarding_point() && station_of(stop) == stop.stop_id)
+        })
+        .filter_map(|stop| Some(station(stop, stop.stop_lat?, stop.stop_lon?)))
+        .collect();
+
+    // Each trip's stops, in order
+    let mut trip_stops: HashMap<&str, Vec<(u32, &str)>> = HashMap::new();
+    for stop_time in &feed.stop_times {
+        trip_stops
+            .entry(stop_time.trip_id.as_str())
+            .or_default()
+            .push((stop_time.stop_sequence, stop_time.stop_id.as_str()));
+    }
+    for stops in trip_stops.values_mut() {
+        stops.sort_unstable();
+    }
+
+    // A platform for each line calling at each stop
+    let mut calls: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    for (trip_id, stops) in &trip_stops {
+        let Some(trip) = trips_by_id.get(trip_id) else {
+            continue;
+        };
+        for (_, stop_id) in stops {
+            calls
+                .entry(stop_id)
+                .or_default()
+                .insert(trip.route_id.as_str());
+        }
+    }
+    let mut platforms = Vec::new();
+    for (stop_id, route_ids) in &calls {
+        let Some(stop) = stops_by_id.get(stop_id) else {
+            continue;
+        };
+        let station_id = station_of(stop);
+        let station_name = stops_by_id
+            .get(station_id.as_str())
+            .map_or(&stop.stop_name, |station| &station.stop_name);
+        let mut route_ids: Vec<&&str> = route_ids.iter().collect();
+        route_ids.sort();
+        for route_id in route_ids {
+            platforms.push(Platform {
+                platform_unique_id: stop.stop_id.clone(),
+                station_unique_id: station_id.clone(),
+                platform_number: stop.platform_code.clone(),
+                cardinal_direction: None,
+                platform_naptan_code: stop.stop_code.clone(),
+                platform_friendly_name: stop.stop_name.clone(),
+                is_customer_facing: true,
+                has_service_interchange: false,
+                station_name: station_name.clone(),
+                fare_zones: stop.zone_id.clone().unwrap_or_default(),
+                hub_naptan_code: None,
+                wifi: false,
+                outside_station_unique_id: String::new(),
+                stop_area_naptan_code: station_id.clone(),
+                line: route_id.to_string(),
+                direction_towards: None,
+                platform_service_group_name: None,
+            });
+        }
+    }
+
+    // Shape points, in order
+    let mut shapes: HashMap<&str, Vec<(u32, [f64; 2])>> = HashMap::new();
+    for point in &feed.shapes {
+        shapes.entry(point.shape_id.as_str()).or_default().push((
+            point.shape_pt_sequence,
+            [point.shape_pt_lon, point.shape_pt_lat],
+        ));
+    }
+    for points in shapes.values_mut() {
+        points.sort_unstable_by_key(|(sequence, _)| *sequence);
+    }
+
+    // The distinct geometries of each route and direction: its shapes, or the
+    // stops of its trips where there are no shapes
+    let mut geometries: BTreeMap<(&str, &str), Vec<Vec<[f64; 2]>>> = BTreeMap::new();
+    let mut seen: HashSet<(&str, &str, Vec<&str>)> = HashSet::new();
+    for trip in &feed.trips {
+        let key = (trip.route_id.as_str(), trip.direction());
+        let shape = trip
+            .shape_id
+            .as_deref()
+            .and_then(|shape_id| Some((shape_id, shapes.get(shape_id)?)));
+        let (pattern, points): (Vec<&str>, Vec<[f64; 2]>) = match shape {
+            Some((shape_id, points)) => (
+                vec![shape_id],
+                points.iter().map(|(_, point)| *point).collect(),
+            ),
+            None => {
+                let stops = trip_stops.get(trip.trip_id.as_str());
+                let stop_ids: Vec<&str> = stops
+                    .into_iter()
+                    .flatten()
+                    .map(|(_, stop_id)| *stop_id)
+                    .collect();
+                let points = stop_ids
+                    .iter()
+                    .filter_map(|stop_id| stops_by_id.get(stop_id))
+                    .filter_map(|stop| Some([stop.stop_lon?, stop.stop_lat?]))
+                    .collect();
+                (stop_ids, points)
+            }
+        };
+        if points.len() < 2 || !seen.insert((key.0, key.1, pattern)) {
+            continue;
+        }
+        geometries.entry(key).or_default().push(points);
+    }
+
+    let mut routes: HashMap<String, HashMap<String, Vec<RouteSequence>>> = HashMap::new();
+    for ((route_id, direction), lines) in geometries {
+        let Some(route) = routes_by_id.get(route_id) else {
+            continue;
+        };
+        routes.entry(route_id.to_string()).or_default().insert(
+            direction.to_string(),
+            vec![RouteSequence {
+                line_id: route_id.to_string(),
+                direction: direction.to_string(),
+                mode: route.mode().to_string(),
+                line_strings: lines.iter().map(|points| line_string(points)).collect(),
+                stop_point_sequences: Vec::new(),
+            }],
+        );
+    }
+
+    let mut repository = TflDataRepository::from_datasets(stations, platforms, routes);
+    repository.services = Some(ServicePatterns::from_gtfs(feed));
+    repository.line_details = feed
+        .routes
+        .iter()
+        .map(|route| {
+            let details = LineDetails {
+                name: route.name().to_string(),
+                color: route.color(),
+                mode: route.mode().to_string(),
+            };
+            (route.route_id.clone(), details)
+        })
+        .collect();
+    repository
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::source::EmbeddedSource;
+
+    #[tokio::test]
+    async fn maps_a_feed_onto_the_repository() {
+        let source = EmbeddedSource::new()
+            .with_path(
+                "stops.txt",
+                "\u{feff}stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                 S1,Alpha,51.5,-0.1,1,\n\
+                 S1-1,Alpha Platform 1,51.5,-0.1,0,S1\n\
+                 S2,Beta,51.6,-0.1,,\n\
+                 E1,Alpha Entrance,,,2,S1\n"
+                    .as_bytes(),
+            )
+            .with_path(
+                "routes.txt",
+                b"route_id,route_short_name,route_long_name,route_type,route_color\n\
+                  R1,,Red Line,1,e32017\n\
+                  R2,42,,3,\n",
+            )
+            .with_path(
+                "trips.txt",
+                b"route_id,service_id,trip_id,direction_id,shape_id\n\
+                  R1,weekday,T1,0,SH1\n\
+                  R1,weekday,T2,0,SH1\n\
+                  R2,weekday,T3,1,\n",
+            )
+            .with_path(
+                "shapes.txt",
+                b"shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+                  SH1,51.6,-0.1,2\n\
+                  SH1,51.5,-0.1,1\n",
+            )
+            .with_path(
+                "stop_times.txt",
+                b"trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                  T1,08:00:00,08:00:00,S1-1,1\n\
+                  T1,08:05:00,08:05:00,S2,2\n\
+                  T3,25:10:00,25:10:00,S2,2\n\
+                  T3,25:00:00,25:00:00,S1-1,1\n",
+            );
+
+        let feed = GtfsFeed::load(&source).await.unwrap();
+        let repository = to_repository(&feed);
+
+        // The station, and the stop that isn't part of one, but not the
+        // platform or entrance
+        let mut station_ids: Vec<&str> = repository
+            .stations
+            .iter()
+            .map(|s| s.station_unique_id.as_str())
+            .collect();
+        station_ids.sort();
+        assert_eq!(station_ids, ["S1", "S2"]);
+        let lines: Vec<&str> = repository.platforms_by_station["S1"]
+            .iter()
+            .map(|p| p.line.as_str())
+            .collect();
+        assert_eq!(lines, ["R1", "R2"]);
+
+        // One geometry for the two trips sharing a shape, put in order
+        assert_eq!(
+            repository.route_geometries["R1"],
+            vec![vec![[-0.1, 51.5], [-0.1, 51.6]]]
+        );
+        // Without a shape, the stops in order
+        assert_eq!(repository.routes["R2"]["inbound"][0].mode, "bus");
+        assert_eq!(
+            repository.route_geometries["R2"],
+            vec![vec![[-0.1, 51.5], [-0.1, 51.6]]]
+        );
+
+        assert_eq!(repository.line_color("R1"), "#E32017");
+        assert_eq!(repository.line_details["R2"].name, "42");
+        // Neither the feed nor the registry has a colour for it
+        assert_eq!(repository.line_color("R2"), "#777777");
+    }
+
+    #[test]
+    fn reports_where_a_table_is_invalid() {
+        let err = parse_table::<GtfsShapePoint>(
+            "shapes.txt",
+            "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\nSH1,north,-0.1,1\n",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            DataError::Parse { file, path, line: 2, column: 2, .. }
+                if file == "shapes.txt" && path == "shape_pt_lat"
+        ));
+    }
+}
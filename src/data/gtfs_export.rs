@@ -0,0 +1,459 @@
+//! Writing a repository as a GTFS static feed.
+//!
+//! Stations become stops with `location_type` 1 and their platforms stops
+//! within them, at the station's position as platforms have none of their
+//! own. Each line is a route in its registry colour, and each distinct route
+//! line string a shape.
+//!
+//! Trips are optional. With [`Headways`], each shape gets one trip calling at
+//! the stations the line serves along it, timed from a typical speed for the
+//! mode, and a `frequencies.txt` entry repeating it through the day. Without,
+//! `trips.txt` and `stop_times.txt` are written with just their headers.
+
+use super::TflDataRepository;
+use super::gtfs::{
+    GtfsAgency, GtfsCalendar, GtfsFrequency, GtfsRoute, GtfsShapePoint, GtfsStop, GtfsStopTime,
+    GtfsTable, GtfsTrip, format_time, parse_time, write_table,
+};
+use super::line_definitions::line_info_as_of;
+use super::loader::parse_line_string;
+use crate::utils::geo::project_onto_line;
+use std::collections::{BTreeMap, HashSet};
+
+/// The agency every route is run by
+pub const AGENCY_ID: &str = "tfl";
+
+/// The service every trip runs on: every day of the week
+pub const SERVICE_ID: &str = "daily";
+
+/// When headway-based trips start, by default
+pub const DEFAULT_FIRST_DEPARTURE: &str = "05:30:00";
+
+/// When they end, by default: after midnight, as the service day runs on
+pub const DEFAULT_LAST_DEPARTURE: &str = "24:30:00";
+
+/// How far a station can be from a shape, in metres, and still be called at
+pub const MAX_STOP_OFFSET: f64 = 250.0;
+
+/// Trips repeated at a fixed interval through part of the day
+#[derive(Debug, Clone, PartialEq)]
+pub struct Headways {
+    pub headway_secs: u32,
+    /// `HH:MM:SS` of the first departure
+    pub start_time: String,
+    /// `HH:MM:SS` of the last, which may be past 24:00:00
+    pub end_time: String,
+}
+
+/// What to export besides the network itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// Add headway-based trips, or none
+    pub headways: Option<Headways>,
+    /// `YYYYMMDD` the service starts
+    pub start_date: String,
+    /// `YYYYMMDD` the service ends
+    pub end_date: String,
+}
+
+/// The tables of an exported feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GtfsExport {
+    pub agency: Vec<GtfsAgency>,
+    pub stops: Vec<GtfsStop>,
+    pub routes: Vec<GtfsRoute>,
+    pub trips: Vec<GtfsTrip>,
+    pub stop_times: Vec<GtfsStopTime>,
+    pub calendar: Vec<GtfsCalendar>,
+    pub shapes: Vec<GtfsShapePoint>,
+    pub frequencies: Vec<GtfsFrequency>,
+}
+
+impl GtfsExport {
+    /// Each file of the feed, by name, as CSV
+    ///
+    /// `frequencies.txt` is only included if there are any.
+    pub fn files(&self) -> Vec<(&'static str, String)> {
+        let mut files = vec![
+            (GtfsAgency::FILE, write_table(&self.agency)),
+            (GtfsStop::FILE, write_table(&self.stops)),
+            (GtfsRoute::FILE, write_table(&self.routes)),
+            (GtfsTrip::FILE, write_table(&self.trips)),
+            (GtfsStopTime::FILE, write_table(&self.stop_times)),
+            (GtfsCalendar::FILE, write_table(&self.calendar)),
+            (GtfsShapePoint::FILE, write_table(&self.shapes)),
+        ];
+        if !self.frequencies.is_empty() {
+            files.push((GtfsFrequency::FILE, write_table(&self.frequencies)));
+        }
+        files
+    }
+}
+
+/// The GTFS `route_type` closest to a TfL mode
+fn route_type(mode: &str) -> u16 {
+    match mode {
+        "tram" => 0,
+        "tube" | "dlr" => 1,
+        "bus" => 3,
+        "river-bus" => 4,
+        "cable-car" => 6,
+        "funicular" => 7,
+        _ => 2,
+    }
+}
+
+/// Average speed in metres per second between stops, for timing trips
+fn typical_speed(mode: &str) -> f64 {
+    match mode {
+        "bus" => 4.5,
+        "tram" | "cable-car" => 6.0,
+        "tube" | "dlr" => 9.0,
+        _ => 12.0,
+    }
+}
+
+/// Seconds a vehicle waits at each stop along the way
+fn dwell_secs(mode: &str) -> u32 {
+    match mode {
+        "bus" => 20,
+        _ => 30,
+    }
+}
+
+/// Export a repository as a GTFS feed, see the [module docs](self)
+pub fn export(repository: &TflDataRepository, options: &ExportOptions) -> GtfsExport {
+    let mut feed = GtfsExport {
+        agency: vec![GtfsAgency {
+            agency_id: AGENCY_ID.to_string(),
+            agency_name: "Transport for London".to_string(),
+            agency_url: "https://tfl.gov.uk".to_string(),
+            agency_timezone: "Europe/London".to_string(),
+        }],
+        calendar: vec![GtfsCalendar {
+            service_id: SERVICE_ID.to_string(),
+            monday: 1,
+            tuesday: 1,
+            wednesday: 1,
+            thursday: 1,
+            friday: 1,
+            saturday: 1,
+            sunday: 1,
+            start_date: options.start_date.clone(),
+            end_date: options.end_date.clone(),
+        }],
+        ..GtfsExport::default()
+    };
+
+    // Stations, then their platforms, in ID order
+    let mut stations: Vec<_> = repository.station_by_id.values().collect();
+    stations.sort_by(|a, b| a.station_unique_id.cmp(&b.station_unique_id));
+    for station in &stations {
+        feed.stops.push(GtfsStop {
+            stop_id: station.station_unique_id.clone(),
+            stop_code: station.hub_naptan_code.clone(),
+            stop_name: station.station_name.clone(),
+            stop_lat: Some(station.lat),
+            stop_lon: Some(station.lon),
+            zone_id: Some(station.fare_zones.clone()).filter(|zones| !zones.is_empty()),
+            location_type: Some(1),
+            parent_station: None,
+            platform_code: None,
+        });
+    }
+    let mut platform_ids = HashSet::new();
+    for station in &stations {
+        let platforms = repository
+            .platforms_by_station
+            .get(&station.station_unique_id)
+            .into_iter()
+            .flatten();
+        // A platform shared by several lines is listed once for each
+        for platform in platforms {
+            if !platform_ids.insert(platform.platform_unique_id.as_str()) {
+                continue;
+            }
+            feed.stops.push(GtfsStop {
+                stop_id: platform.platform_unique_id.clone(),
+                stop_code: platform.platform_naptan_code.clone(),
+                stop_name: platform.platform_friendly_name.clone(),
+                stop_lat: Some(station.lat),
+                stop_lon: Some(station.lon),
+                zone_id: None,
+                location_type: Some(0),
+                parent_station: Some(station.station_unique_id.clone()),
+                platform_code: platform.platform_number.clone(),
+            });
+        }
+    }
+
+    let mut line_ids: Vec<&String> = repository.routes.keys().collect();
+    line_ids.sort();
+    for line_id in line_ids {
+        let directions: BTreeMap<_, _> = repository.routes[line_id].iter().collect();
+        let mode = directions
+            .values()
+            .flat_map(|sequences| sequences.iter())
+            .map(|sequence| sequence.mode.to_lowercase())
+            .next()
+            .unwrap_or_else(|| "train".to_string());
+
+        let name = match repository.line_details.get(line_id) {
+            Some(details) => details.name.clone(),
+            None => line_info_as_of(line_id, repository.as_of.as_deref())
+                .map_or_else(|| line_id.clone(), |info| info.name.to_string()),
+        };
+        let (short_name, long_name) = if mode == "bus" {
+            (name, String::new())
+        } else {
+            (String::new(), name)
+        };
+        feed.routes.push(GtfsRoute {
+            route_id: line_id.clone(),
+            agency_id: Some(AGENCY_ID.to_string()),
+            route_short_name: short_name,
+            route_long_name: long_name,
+            route_type: route_type(&mode),
+            route_color: Some(
+                repository
+                    .line_color(line_id)
+                    .trim_start_matches('#')
+                    .to_string(),
+            ),
+            route_text_color: Some("FFFFFF".to_string()),
+        });
+
+        // The line's platform at each station it serves
+        let mut line_stops: Vec<(&str, &str, [f64; 2])> = Vec::new();
+        for station in &stations {
+            let platform = repository
+                .platforms_by_station
+                .get(&station.station_unique_id)
+                .into_iter()
+                .flatten()
+                .find(|platform| &platform.line == line_id);
+            if let Some(platform) = platform {
+                line_stops.push((
+                    platform.platform_unique_id.as_str(),
+                    station.station_name.as_str(),
+                    [station.lon, station.lat],
+                ));
+            }
+        }
+
+        for (direction, sequences) in directions {
+            let mut seen = HashSet::new();
+            let line_strings = sequences
+                .iter()
+                .flat_map(|sequence| &sequence.line_strings)
+                .filter(|line_string| seen.insert(line_string.as_str()));
+            for (index, line_string) in line_strings.enumerate() {
+                let Ok(points) = parse_line_string(line_string) else {
+                    continue;
+                };
+                let shape_id = format!("{}-{}-{}", line_id, direction, index);
+                feed.shapes
+                    .extend(points.iter().enumerate().map(|(sequence, [lon, lat])| {
+                        GtfsShapePoint {
+                            shape_id: shape_id.clone(),
+                            shape_pt_lat: *lat,
+                            shape_pt_lon: *lon,
+                            shape_pt_sequence: sequence as u32,
+                        }
+                    }));
+
+                if let Some(headways) = &options.headways {
+                    add_trip(
+                        &mut feed,
+                        headways,
+                        (line_id, direction, &mode),
+                        &shape_id,
+                        &points,
+                        &line_stops,
+                    );
+                }
+            }
+        }
+    }
+
+    feed
+}
+
+/// Add a trip along a shape, repeated at the headway, if it calls at two or
+/// more of the line's stops
+fn add_trip(
+    feed: &mut GtfsExport,
+    headways: &Headways,
+    (line_id, direction, mode): (&str, &str, &str),
+    shape_id: &str,
+    points: &[[f64; 2]],
+    line_stops: &[(&str, &str, [f64; 2])],
+) {
+    let mut calls: Vec<(f64, &str, &str)> = line_stops
+        .iter()
+        .filter_map(|(stop_id, name, point)| {
+            let projection = project_onto_line(*point, points)?;
+            (projection.offset <= MAX_STOP_OFFSET).then_some((
+                projection.distance_along,
+                *stop_id,
+                *name,
+            ))
+        })
+        .collect();
+    if calls.len() < 2 {
+        return;
+    }
+    calls.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let trip_id = shape_id.to_string();
+    let start = parse_time(&headways.start_time).unwrap_or(0);
+    let (speed, dwell) = (typical_speed(mode), dwell_secs(mode));
+    let mut time = start;
+    let last = calls.len() - 1;
+    for (index, (distance, stop_id, _)) in calls.iter().enumerate() {
+        if index > 0 {
+            let travelled = distance - calls[index - 1].0;
+            time += (travelled / speed).round() as u32;
+        }
+        let arrival = time;
+        if index > 0 && index < last {
+            time += dwell;
+        }
+        feed.stop_times.push(GtfsStopTime {
+            trip_id: trip_id.clone(),
+            arrival_time: Some(format_time(arrival)),
+            departure_time: Some(format_time(time)),
+            stop_id: stop_id.to_string(),
+            stop_sequence: index as u32,
+        });
+    }
+
+    feed.trips.push(GtfsTrip {
+        route_id: line_id.to_string(),
+        service_id: SERVICE_ID.to_string(),
+        trip_id: trip_id.clone(),
+        trip_headsign: Some(calls[last].2.to_string()),
+        direction_id: Some(if direction == "inbound" { 1 } else { 0 }),
+        shape_id: Some(shape_id.to_string()),
+    });
+    feed.frequencies.push(GtfsFrequency {
+        trip_id,
+        start_time: headways.start_time.clone(),
+        end_time: headways.end_time.clone(),
+        headway_secs: headways.headway_secs,
+        exact_times: 0,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::gtfs::{GtfsFeed, to_repository};
+    use crate::data::model::{Platform, RouteSequence, Station};
+    use crate::data::source::EmbeddedSource;
+    use std::collections::HashMap;
+
+    fn station(id: &str, lat: f64) -> Station {
+        Station {
+            station_unique_id: id.to_string(),
+            station_name: format!("{} Station", id),
+            fare_zones: "1".to_string(),
+            hub_naptan_code: None,
+            wifi: false,
+            outside_station_unique_id: String::new(),
+            lat,
+            lon: -0.1,
+            component_stations: Vec::new(),
+        }
+    }
+
+    fn platform(id: &str, station_id: &str) -> Platform {
+        Platform {
+            platform_unique_id: id.to_string(),
+            station_unique_id: station_id.to_string(),
+            platform_number: Some("1".to_string()),
+            cardinal_direction: None,
+            platform_naptan_code: None,
+            platform_friendly_name: format!("{} Platform", station_id),
+            is_customer_facing: true,
+            has_service_interchange: false,
+            station_name: format!("{} Station", station_id),
+            fare_zones: "1".to_string(),
+            hub_naptan_code: None,
+            wifi: false,
+            outside_station_unique_id: String::new(),
+            stop_area_naptan_code: station_id.to_string(),
+            line: "bakerloo".to_string(),
+            direction_towards: None,
+            platform_service_group_name: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_a_feed_that_reads_back() {
+        let sequence = RouteSequence {
+            line_id: "bakerloo".to_string(),
+            direction: "outbound".to_string(),
+            mode: "tube".to_string(),
+            line_strings: vec!["[[[-0.1,51.5],[-0.1,51.51],[-0.1,51.52]]]".to_string()],
+            stop_point_sequences: Vec::new(),
+        };
+        let routes = HashMap::from([(
+            "bakerloo".to_string(),
+            HashMap::from([("outbound".to_string(), vec![sequence])]),
+        )]);
+        let repository = TflDataRepository::from_datasets(
+            vec![station("A", 51.5), station("B", 51.51), station("C", 51.52)],
+            vec![platform("A-1", "A"), platform("C-1", "C")],
+            routes,
+        );
+        let options = ExportOptions {
+            headways: Some(Headways {
+                headway_secs: 300,
+                start_time: "06:00:00".to_string(),
+                end_time: "24:00:00".to_string(),
+            }),
+            start_date: "20250404".to_string(),
+            end_date: "20260404".to_string(),
+        };
+
+        let feed = export(&repository, &options);
+        assert_eq!(feed.routes[0].route_color.as_deref(), Some("B36305"));
+        assert_eq!(feed.shapes.len(), 3);
+        // B has no platform on the line, so isn't called at
+        let times: Vec<_> = feed
+            .stop_times
+            .iter()
+            .map(|stop_time| {
+                (
+                    stop_time.stop_id.as_str(),
+                    stop_time.arrival_time.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            times,
+            [("A-1", Some("06:00:00")), ("C-1", Some("06:04:07"))]
+        );
+        assert_eq!(feed.trips[0].trip_headsign.as_deref(), Some("C Station"));
+
+        let files = feed.files();
+        assert!(files.iter().any(|(name, _)| *name == "frequencies.txt"));
+        let source = files
+            .into_iter()
+            .fold(EmbeddedSource::new(), |source, (name, text)| {
+                source.with_path(name, text.into_bytes())
+            });
+        let read_back = to_repository(&GtfsFeed::load(&source).await.unwrap());
+        assert_eq!(read_back.stations.len(), 3);
+        assert_eq!(
+            read_back.platforms_by_station["A"][0].platform_unique_id,
+            "A-1"
+        );
+        assert_eq!(read_back.line_color("bakerloo"), "#B36305");
+        assert_eq!(
+            read_back.route_geometries["bakerloo"],
+            repository.route_geometries["bakerloo"]
+        );
+    }
+}
@@ -84,12 +84,6 @@ pub const LINE_INFOS: &[LineInfo] = &[
         color: "#95CDBA",
         line_type: LineType::Underground,
     },
-    // LineInfo {
-    //     id: "london-overground",
-    //     name: "Overground",
-    //     color: "#EE7C0E",
-    //     line_type: LineType::Overground,
-    // },
     LineInfo {
         id: "dlr",
         name: "DLR",
@@ -159,11 +153,235 @@ pub const LINE_INFOS: &[LineInfo] = &[
     },
 ];
 
-// Helper functions
-pub fn get_line_color(line_id: &str) -> String {
-    LINE_INFOS
+/// How a line aliased by [`LINE_ALIASES`] is shown
+#[derive(Debug, Clone)]
+pub enum ShownAs {
+    /// As a line in [`LINE_INFOS`], under its ID
+    Line(&'static str),
+    /// As a line that's no longer in [`LINE_INFOS`]
+    Historical(LineInfo),
+}
+
+/// A line ID that's shown differently, for all time or between two dates
+///
+/// Dates are `YYYY-MM-DD`: `from` is the first day the alias applies and
+/// `until` the first day it no longer does.
+#[derive(Debug, Clone)]
+pub struct LineAlias {
+    pub id: &'static str,
+    pub from: Option<&'static str>,
+    pub until: Option<&'static str>,
+    pub shown_as: ShownAs,
+}
+
+impl LineAlias {
+    /// Whether the alias applies on a date, or to the current network if
+    /// there's no date
+    fn applies(&self, as_of: Option<&str>) -> bool {
+        match as_of {
+            Some(date) => {
+                self.from.is_none_or(|from| from <= date)
+                    && self.until.is_none_or(|until| date < until)
+            }
+            None => self.until.is_none(),
+        }
+    }
+}
+
+/// The day the London Overground was split into six named lines
+const OVERGROUND_SPLIT: &str = "2024-11-21";
+
+/// The London Overground as a single line, before [`OVERGROUND_SPLIT`]
+const LONDON_OVERGROUND: LineInfo = LineInfo {
+    id: "london-overground",
+    name: "London Overground",
+    color: "#EE7C0E",
+    line_type: LineType::Overground,
+};
+
+pub const LINE_ALIASES: &[LineAlias] = &[
+    // The API's ID for the cable car
+    LineAlias {
+        id: "london-cable-car",
+        from: None,
+        until: None,
+        shown_as: ShownAs::Line("cable-car"),
+    },
+    // A single line until it was split into the six named lines below
+    LineAlias {
+        id: "london-overground",
+        from: None,
+        until: None,
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "liberty",
+        from: None,
+        until: Some(OVERGROUND_SPLIT),
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "liberty",
+        from: Some(OVERGROUND_SPLIT),
+        until: None,
+        shown_as: ShownAs::Line("liberty"),
+    },
+    LineAlias {
+        id: "lioness",
+        from: None,
+        until: Some(OVERGROUND_SPLIT),
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "lioness",
+        from: Some(OVERGROUND_SPLIT),
+        until: None,
+        shown_as: ShownAs::Line("lioness"),
+    },
+    LineAlias {
+        id: "mildmay",
+        from: None,
+        until: Some(OVERGROUND_SPLIT),
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "mildmay",
+        from: Some(OVERGROUND_SPLIT),
+        until: None,
+        shown_as: ShownAs::Line("mildmay"),
+    },
+    LineAlias {
+        id: "suffragette",
+        from: None,
+        until: Some(OVERGROUND_SPLIT),
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "suffragette",
+        from: Some(OVERGROUND_SPLIT),
+        until: None,
+        shown_as: ShownAs::Line("suffragette"),
+    },
+    LineAlias {
+        id: "weaver",
+        from: None,
+        until: Some(OVERGROUND_SPLIT),
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "weaver",
+        from: Some(OVERGROUND_SPLIT),
+        until: None,
+        shown_as: ShownAs::Line("weaver"),
+    },
+    LineAlias {
+        id: "windrush",
+        from: None,
+        until: Some(OVERGROUND_SPLIT),
+        shown_as: ShownAs::Historical(LONDON_OVERGROUND),
+    },
+    LineAlias {
+        id: "windrush",
+        from: Some(OVERGROUND_SPLIT),
+        until: None,
+        shown_as: ShownAs::Line("windrush"),
+    },
+    // Ran as TfL Rail until the central section opened
+    LineAlias {
+        id: "elizabeth",
+        from: None,
+        until: Some("2022-05-24"),
+        shown_as: ShownAs::Historical(LineInfo {
+            id: "elizabeth",
+            name: "TfL Rail",
+            color: "#0019A8",
+            line_type: LineType::ElizabethLine,
+        }),
+    },
+];
+
+/// How a line is shown on a date (`YYYY-MM-DD`), or on the current network if
+/// there's no date
+pub fn line_info_as_of(line_id: &str, as_of: Option<&str>) -> Option<&'static LineInfo> {
+    let alias = LINE_ALIASES
+        .iter()
+        .find(|alias| alias.id == line_id && alias.applies(as_of));
+    let line_id = match alias.map(|alias| &alias.shown_as) {
+        Some(ShownAs::Historical(info)) => return Some(info),
+        Some(ShownAs::Line(id)) => id,
+        None => line_id,
+    };
+    LINE_INFOS.iter().find(|info| info.id == line_id)
+}
+
+/// The dates [`LINE_ALIASES`] start or stop applying on, when lines were
+/// renamed or recoloured, oldest first
+pub fn alias_dates() -> Vec<&'static str> {
+    let mut dates: Vec<&'static str> = LINE_ALIASES
+        .iter()
+        .flat_map(|alias| [alias.from, alias.until])
+        .flatten()
+        .collect();
+    dates.sort_unstable();
+    dates.dedup();
+    dates
+}
+
+/// Whether a line ID is in [`LINE_INFOS`] or [`LINE_ALIASES`]
+pub fn is_known_line(line_id: &str) -> bool {
+    LINE_INFOS.iter().any(|info| info.id == line_id)
+        || LINE_ALIASES.iter().any(|alias| alias.id == line_id)
+}
+
+/// The lines in [`LINE_INFOS`] that an ID is shown as, through its aliases at
+/// any time as well as directly
+pub fn registry_ids_for(line_id: &str) -> impl Iterator<Item = &'static str> {
+    let direct = LINE_INFOS
         .iter()
         .find(|info| info.id == line_id)
+        .map(|info| info.id);
+    let aliased = LINE_ALIASES
+        .iter()
+        .filter(move |alias| alias.id == line_id)
+        .filter_map(|alias| match alias.shown_as {
+            ShownAs::Line(id) => Some(id),
+            ShownAs::Historical(_) => None,
+        });
+    direct.into_iter().chain(aliased)
+}
+
+/// The lines for a set of line IDs as shown on a date, in registry order and
+/// without repeats, e.g. for a key
+pub fn lines_as_of<'a>(
+    line_ids: impl IntoIterator<Item = &'a str>,
+    as_of: Option<&str>,
+) -> Vec<&'static LineInfo> {
+    let mut lines: Vec<&'static LineInfo> = Vec::new();
+    for info in line_ids
+        .into_iter()
+        .filter_map(|id| line_info_as_of(id, as_of))
+    {
+        if !lines.iter().any(|line| line.id == info.id) {
+            lines.push(info);
+        }
+    }
+    // Lines that are only historical aren't in the registry, so they go last
+    lines.sort_by_key(|line| {
+        LINE_INFOS
+            .iter()
+            .position(|info| info.id == line.id)
+            .unwrap_or(usize::MAX)
+    });
+    lines
+}
+
+// Helper functions
+pub fn get_line_color(line_id: &str) -> String {
+    line_color_as_of(line_id, None)
+}
+
+pub fn line_color_as_of(line_id: &str, as_of: Option<&str>) -> String {
+    line_info_as_of(line_id, as_of)
         .map(|info| info.color.to_string())
         .unwrap_or_else(|| "#777777".to_string())
 }
@@ -192,24 +410,84 @@ pub fn get_other_rail_lines() -> Vec<&'static LineInfo> {
         .collect()
 }
 
-/// Generate CSS for the lines
-pub fn generate_line_css() -> String {
+/// Generate CSS for the lines, with their colours on a date if there is one
+pub fn generate_line_css(as_of: Option<&str>) -> String {
+    // Every line and alias, so that any ID in the data has a colour
+    let ids: Vec<&str> = LINE_INFOS
+        .iter()
+        .map(|info| info.id)
+        .chain(LINE_ALIASES.iter().map(|alias| alias.id))
+        .collect();
+    let mut lines: Vec<(&str, &str)> = Vec::new();
+    for id in ids {
+        if lines.iter().any(|(line_id, _)| *line_id == id) {
+            continue;
+        }
+        if let Some(info) = line_info_as_of(id, as_of) {
+            lines.push((id, info.color));
+        }
+    }
+
     let mut css = String::new();
 
     // Root variables
     css.push_str(":root {\n");
-    for line in LINE_INFOS {
-        css.push_str(&format!("  --{}: {};\n", line.id, line.color));
+    for (id, color) in &lines {
+        css.push_str(&format!("  --{}: {};\n", id, color));
     }
     css.push_str("}\n\n");
 
     // Line classes
-    for line in LINE_INFOS {
+    for (id, _) in &lines {
         css.push_str(&format!(
             ".color-line.{} {{ background-color: var(--{}); }}\n",
-            line.id, line.id
+            id, id
         ));
     }
 
     css
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_resolve_by_date() {
+        let name = |id, as_of| line_info_as_of(id, as_of).map(|info| info.name);
+
+        assert_eq!(name("elizabeth", None), Some("Elizabeth Line"));
+        assert_eq!(name("elizabeth", Some("2022-05-23")), Some("TfL Rail"));
+        assert_eq!(
+            name("elizabeth", Some("2022-05-24")),
+            Some("Elizabeth Line")
+        );
+        assert_eq!(name("london-cable-car", None), Some("Cable Car"));
+        assert_eq!(name("london-overground", None), Some("London Overground"));
+        assert_eq!(name("emirates-air-line", None), None);
+
+        // The named Overground lines were one line before the split
+        assert_eq!(name("mildmay", None), Some("Mildmay Line"));
+        assert_eq!(
+            name("mildmay", Some("2024-11-20")),
+            Some("London Overground")
+        );
+        assert_eq!(name("mildmay", Some("2024-11-21")), Some("Mildmay Line"));
+        let before_split = lines_as_of(["liberty", "weaver", "victoria"], Some("2024-11-20"));
+        let ids: Vec<&str> = before_split.iter().map(|line| line.id).collect();
+        assert_eq!(ids, ["victoria", "london-overground"]);
+        assert_eq!(alias_dates(), ["2022-05-24", "2024-11-21"]);
+
+        let lines = lines_as_of(
+            [
+                "london-overground",
+                "cable-car",
+                "london-cable-car",
+                "bakerloo",
+            ],
+            None,
+        );
+        let ids: Vec<&str> = lines.iter().map(|line| line.id).collect();
+        assert_eq!(ids, ["bakerloo", "cable-car", "london-overground"]);
+    }
+}
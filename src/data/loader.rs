@@ -1,137 +1,126 @@
-use super::model::{Platform, PlatformsResponse, Station, StationsResponse};
+use super::bus;
+use super::compact;
+use super::error::{DataError, parse_json};
+use super::model::{
+    BusManifest, BusStopsFile, Platform, PlatformsResponse, Station, StationsResponse,
+};
+use super::services::{SERVICES_PATH, ServicePatterns};
+use super::source::{DataFile, DataSource, OnProgress};
 use crate::data::model::{RouteSequence, RoutesFile};
 use crate::utils::log::{self, LogCategory};
-use dioxus::prelude::*;
 use std::collections::HashMap;
-use wasm_bindgen::JsCast;
-use web_sys::Response;
-
-// Define asset paths for our data files
-const STATIONS_JSON_PATH: Asset = asset!("/assets/data/stations.json");
-const PLATFORMS_JSON_PATH: Asset = asset!("/assets/data/platforms.json");
-const RAIL_ROUTES_JSON_PATH: Asset = asset!("/assets/data/rail_routes.json");
-const BUS_ROUTES_JSON_PATH: Asset = asset!("/assets/data/bus_routes.json");
-
-/// Load stations from the JSON data file using fetch
-pub async fn load_stations() -> Result<Vec<Station>, String> {
-    log::info_with_category(LogCategory::App, "Loading stations from JSON data file");
-
-    // Create a future to fetch the stations data
-    let window = web_sys::window().ok_or("No window object available")?;
-    let promise = window.fetch_with_str(
-        STATIONS_JSON_PATH
-            .resolve()
-            .to_str()
-            .expect("Failed to load stations JSON"),
-    );
-
-    // Convert the Promise<Response> to a Future<Result<Response, JsValue>>
-    let response_future = wasm_bindgen_futures::JsFuture::from(promise);
-
-    // Await the response
-    let response_value = match response_future.await {
-        Ok(val) => val,
-        Err(e) => return Err(format!("Failed to fetch stations: {:?}", e)),
-    };
-
-    let response: Response = response_value
-        .dyn_into()
-        .map_err(|_| "Failed to convert response")?;
-
-    if !response.ok() {
-        return Err(format!("HTTP error: {}", response.status()));
-    }
-
-    // Get the response text
-    let text_promise = response
-        .text()
-        .map_err(|e| format!("Failed to get response text: {:?}", e))?;
-    let text_future = wasm_bindgen_futures::JsFuture::from(text_promise);
 
-    let text = match text_future.await {
-        Ok(val) => val.as_string().ok_or("Response is not a string")?,
-        Err(e) => return Err(format!("Failed to get response text: {:?}", e)),
+/// Read a dataset from its compact binary file, if it has one
+///
+/// Returns `None` if the file is missing or can't be decoded, so the caller
+/// can read the JSON instead.
+async fn load_compact<T>(
+    source: &impl DataSource,
+    file: DataFile,
+    on_progress: OnProgress<'_>,
+    decode: fn(&str, &[u8]) -> Result<T, DataError>,
+) -> Option<T> {
+    let path = file.compact_path()?;
+
+    let bytes = match source.read_bytes(path, on_progress).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::debug_with_category(
+                LogCategory::App,
+                &format!("No {}, reading {} instead: {}", path, file.file_name(), e),
+            );
+            return None;
+        }
     };
 
-    // Parse the JSON
-    match serde_json::from_str::<StationsResponse>(&text) {
-        Ok(response) => {
-            if !response.success {
-                return Err("Stations response was unsuccessful".to_string());
-            }
+    match decode(path, &bytes) {
+        Ok(value) => {
             log::info_with_category(
                 LogCategory::App,
-                &format!("Successfully loaded {} stations", response.results.len()),
+                &format!("Decoded {} ({} bytes)", path, bytes.len()),
             );
-            Ok(response.results)
+            Some(value)
         }
         Err(e) => {
-            let error_msg = format!("Failed to parse stations JSON: {}", e);
-            log::error_with_category(LogCategory::App, &error_msg);
-            Err(error_msg)
+            log::warn_with_category(
+                LogCategory::App,
+                &format!("{}; reading {} instead", e, file.file_name()),
+            );
+            None
         }
     }
 }
 
-/// Load platforms from the JSON data file using fetch
-pub async fn load_platforms() -> Result<Vec<Platform>, String> {
-    log::info_with_category(LogCategory::App, "Loading platforms from JSON data file");
-
-    // Create a future to fetch the platforms data
-    let window = web_sys::window().ok_or("No window object available")?;
-    let promise = window.fetch_with_str(
-        PLATFORMS_JSON_PATH
-            .resolve()
-            .to_str()
-            .expect("Failed to load stations JSON"),
+/// Load stations from the stations data file, with the response's context
+pub async fn load_stations(
+    source: &impl DataSource,
+    on_progress: OnProgress<'_>,
+) -> Result<StationsResponse, DataError> {
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Loading stations from {}", source.describe()),
     );
 
-    // Convert the Promise<Response> to a Future<Result<Response, JsValue>>
-    let response_future = wasm_bindgen_futures::JsFuture::from(promise);
+    let file = DataFile::Stations;
+    let response = match load_compact(source, file, on_progress, compact::decode_stations).await {
+        Some(response) => response,
+        None => {
+            let text = source.read_text(file, on_progress).await?;
 
-    // Await the response
-    let response_value = match response_future.await {
-        Ok(val) => val,
-        Err(e) => return Err(format!("Failed to fetch platforms: {:?}", e)),
+            // Parse the JSON
+            parse_json::<StationsResponse>(file.file_name(), &text).inspect_err(|e| {
+                log::error_with_category(LogCategory::App, &e.to_string());
+            })?
+        }
     };
 
-    let response: Response = response_value
-        .dyn_into()
-        .map_err(|_| "Failed to convert response")?;
-
-    if !response.ok() {
-        return Err(format!("HTTP error: {}", response.status()));
+    if !response.success {
+        return Err(DataError::Unsuccessful {
+            file: file.file_name().to_string(),
+            context: None,
+        });
     }
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Successfully loaded {} stations", response.results.len()),
+    );
+    Ok(response)
+}
 
-    // Get the response text
-    let text_promise = response
-        .text()
-        .map_err(|e| format!("Failed to get response text: {:?}", e))?;
-    let text_future = wasm_bindgen_futures::JsFuture::from(text_promise);
+/// Load platforms from the platforms data file, with the response's context
+pub async fn load_platforms(
+    source: &impl DataSource,
+    on_progress: OnProgress<'_>,
+) -> Result<PlatformsResponse, DataError> {
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Loading platforms from {}", source.describe()),
+    );
 
-    let text = match text_future.await {
-        Ok(val) => val.as_string().ok_or("Response is not a string")?,
-        Err(e) => return Err(format!("Failed to get response text: {:?}", e)),
-    };
+    let file = DataFile::Platforms;
+    let response = match load_compact(source, file, on_progress, compact::decode_platforms).await {
+        Some(response) => response,
+        None => {
+            let text = source.read_text(file, on_progress).await?;
 
-    // Parse the JSON
-    match serde_json::from_str::<PlatformsResponse>(&text) {
-        Ok(response) => {
-            if !response.success {
-                return Err("Platforms response was unsuccessful".to_string());
-            }
-            log::info_with_category(
-                LogCategory::App,
-                &format!("Successfully loaded {} platforms", response.results.len()),
-            );
-            Ok(response.results)
-        }
-        Err(e) => {
-            let error_msg = format!("Failed to parse platforms JSON: {}", e);
-            log::error_with_category(LogCategory::App, &error_msg);
-            Err(error_msg)
+            // Parse the JSON
+            parse_json::<PlatformsResponse>(file.file_name(), &text).inspect_err(|e| {
+                log::error_with_category(LogCategory::App, &e.to_string());
+            })?
         }
+    };
+
+    if !response.success {
+        return Err(DataError::Unsuccessful {
+            file: file.file_name().to_string(),
+            context: None,
+        });
     }
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Successfully loaded {} platforms", response.results.len()),
+    );
+    Ok(response)
 }
 
 /// Filter stations to only include those with valid coordinates
@@ -162,134 +151,110 @@ pub fn group_platforms_by_station(
     map
 }
 
-/// Load routes from the JSON data files using fetch
-pub async fn load_routes(
-    load_buses: bool,
-) -> Result<HashMap<String, HashMap<String, Vec<RouteSequence>>>, String> {
-    log::info_with_category(LogCategory::App, "Loading routes from JSON data files");
-
-    // Create a function to fetch a routes file
-    async fn fetch_routes_file(file_path: &str) -> Result<RoutesFile, String> {
-        log::debug_with_category(
-            LogCategory::App,
-            &format!("Fetching routes from {}", file_path),
-        );
-
-        let window = web_sys::window().ok_or("No window object available")?;
-        let promise = window.fetch_with_str(file_path);
+/// Load the combined rail routes data file
+pub async fn load_routes_file(
+    source: &impl DataSource,
+    file: DataFile,
+    on_progress: OnProgress<'_>,
+) -> Result<RoutesFile, DataError> {
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Loading {} from {}", file.file_name(), source.describe()),
+    );
 
-        // Convert the Promise<Response> to a Future<Result<Response, JsValue>>
-        let response_future = wasm_bindgen_futures::JsFuture::from(promise);
+    if let Some(routes_file) = load_compact(source, file, on_progress, compact::decode_routes).await
+    {
+        return Ok(routes_file);
+    }
+    let text = source.read_text(file, on_progress).await?;
 
-        // Await the response
-        let response_value = match response_future.await {
-            Ok(val) => val,
-            Err(e) => {
-                return Err(format!(
-                    "Failed to fetch routes from {}: {:?}",
-                    file_path, e
-                ));
-            }
-        };
+    // Parse the JSON
+    match parse_json::<RoutesFile>(file.file_name(), &text) {
+        Ok(routes_file) => Ok(routes_file),
+        Err(e) => {
+            log::error_with_category(LogCategory::App, &e.to_string());
+            Err(e)
+        }
+    }
+}
 
-        let response: Response = response_value
-            .dyn_into()
-            .map_err(|_| "Failed to convert response")?;
+/// Load the manifest listing the bus routes and their extents
+pub async fn load_bus_manifest(
+    source: &impl DataSource,
+    on_progress: OnProgress<'_>,
+) -> Result<BusManifest, DataError> {
+    let file = DataFile::BusManifest;
+    let text = source.read_text(file, on_progress).await?;
 
-        if !response.ok() {
-            return Err(format!("HTTP error: {}", response.status()));
-        }
+    let manifest = parse_json::<BusManifest>(file.file_name(), &text)?;
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Bus manifest lists {} routes", manifest.routes.len()),
+    );
+    Ok(manifest)
+}
 
-        // Get the response text
-        let text_promise = response
-            .text()
-            .map_err(|e| format!("Failed to get response text: {:?}", e))?;
-        let text_future = wasm_bindgen_futures::JsFuture::from(text_promise);
-
-        let text = match text_future.await {
-            Ok(val) => val.as_string().ok_or("Response is not a string")?,
-            Err(e) => return Err(format!("Failed to get response text: {:?}", e)),
-        };
-
-        // Parse the JSON
-        match serde_json::from_str::<RoutesFile>(&text) {
-            Ok(routes_file) => Ok(routes_file),
-            Err(e) => {
-                let error_msg = format!("Failed to parse routes JSON from {}: {}", file_path, e);
-                log::error_with_category(LogCategory::App, &error_msg);
-                Err(error_msg)
-            }
-        }
-    }
+/// Load a single bus route's file
+pub async fn load_bus_route(source: &impl DataSource, id: &str) -> Result<RoutesFile, DataError> {
+    let path = bus::route_path(id);
+    let text = source.read_path(&path, &|_, _| {}).await?;
+    parse_json::<RoutesFile>(&path, &text)
+}
 
-    // Get paths as strings directly
-    let rail_routes_path = RAIL_ROUTES_JSON_PATH
-        .resolve()
-        .to_str()
-        .expect("Failed to load rail routes JSON")
-        .to_string(); // Convert to owned String to avoid temporary
+/// Load the bus stops, see [`super::naptan`]
+pub async fn load_bus_stops(source: &impl DataSource) -> Result<BusStopsFile, DataError> {
+    let path = bus::BUS_STOPS_PATH;
+    let text = source.read_path(path, &|_, _| {}).await?;
+    let stops_file = parse_json::<BusStopsFile>(path, &text)?;
+    log::info_with_category(
+        LogCategory::App,
+        &format!("Loaded {} bus stops", stops_file.stops.len()),
+    );
+    Ok(stops_file)
+}
 
-    // Fetch rail routes
-    let rail_routes_file = fetch_routes_file(&rail_routes_path).await?;
+/// Load how often each line runs, see [`super::services`]
+pub async fn load_service_patterns(source: &impl DataSource) -> Result<ServicePatterns, DataError> {
+    let text = source.read_path(SERVICES_PATH, &|_, _| {}).await?;
+    let patterns = ServicePatterns::parse(SERVICES_PATH, &text)?;
+    log::info_with_category(
+        LogCategory::App,
+        &format!(
+            "Loaded service patterns for {} lines and {} modes",
+            patterns.lines.len(),
+            patterns.modes.len()
+        ),
+    );
+    Ok(patterns)
+}
 
-    // Merge the route files
+/// Merge routes files into routes by line ID and direction
+///
+/// Each file is given with its path, for reporting unsuccessful responses.
+/// Files are merged in order, so pass the rail routes first: a line in a later
+/// file replaces the directions it has for the same line in an earlier one.
+pub fn merge_routes<'a>(
+    routes_files: impl IntoIterator<Item = (&'a str, &'a RoutesFile)>,
+) -> HashMap<String, HashMap<String, Vec<RouteSequence>>> {
     let mut routes_map: HashMap<String, HashMap<String, Vec<RouteSequence>>> = HashMap::new();
 
-    // Helper function to process routes from a file
-    let process_routes =
-        |routes_file: RoutesFile,
-         routes_map: &mut HashMap<String, HashMap<String, Vec<RouteSequence>>>| {
-            for (line_id, directions) in routes_file.routes {
-                let mut direction_map: HashMap<String, Vec<RouteSequence>> =
-                    routes_map.get(&line_id).cloned().unwrap_or_default();
-
-                // Process each direction
-                for (direction, response) in directions {
-                    if response.success {
-                        direction_map.insert(direction, response.results);
-                    } else {
-                        log::warn_with_category(
-                            LogCategory::App,
-                            &format!(
-                                "Unsuccessful response for line {}, direction {}",
-                                line_id, direction
-                            ),
-                        );
-                    }
+    for (file, routes_file) in routes_files {
+        for (line_id, directions) in &routes_file.routes {
+            let direction_map = routes_map.entry(line_id.clone()).or_default();
+
+            // Process each direction
+            for (direction, response) in directions {
+                if response.success {
+                    direction_map.insert(direction.clone(), response.results.clone());
+                } else {
+                    let error = DataError::Unsuccessful {
+                        file: file.to_string(),
+                        context: Some(format!("line {}, direction {}", line_id, direction)),
+                    };
+                    log::warn_with_category(LogCategory::App, &error.to_string());
                 }
-
-                routes_map.insert(line_id, direction_map);
-            }
-        };
-
-    // Process rail routes first (higher priority)
-    process_routes(rail_routes_file, &mut routes_map);
-
-    // Only load bus routes if requested
-    if load_buses {
-        log::info_with_category(LogCategory::App, "Loading bus routes");
-        let bus_routes_path = BUS_ROUTES_JSON_PATH
-            .resolve()
-            .to_str()
-            .expect("Failed to load bus routes JSON")
-            .to_string();
-
-        // Fetch bus routes
-        match fetch_routes_file(&bus_routes_path).await {
-            Ok(bus_routes_file) => {
-                // Then process bus routes
-                process_routes(bus_routes_file, &mut routes_map);
-                log::info_with_category(LogCategory::App, "Bus routes loaded successfully");
-            }
-            Err(e) => {
-                log::warn_with_category(
-                    LogCategory::App,
-                    &format!("Failed to load bus routes, continuing without them: {}", e),
-                );
             }
         }
-    } else {
-        log::info_with_category(LogCategory::App, "Skipping bus routes as requested");
     }
 
     log::info_with_category(
@@ -297,7 +262,7 @@ pub async fn load_routes(
         &format!("Successfully loaded routes for {} lines", routes_map.len()),
     );
 
-    Ok(routes_map)
+    routes_map
 }
 
 /// Process route data to create a mapping of line ID to route geometry
@@ -331,7 +296,7 @@ pub fn process_route_geometries(
 }
 
 /// Parse a LineString from a GeoJSON-like format
-fn parse_line_string(line_string: &str) -> Result<Vec<[f64; 2]>, String> {
+pub fn parse_line_string(line_string: &str) -> Result<Vec<[f64; 2]>, String> {
     // The LineString format is like: "[[[-0.335217,51.592268],[-0.31691,51.581756],[-0.308433,51.570232]]]"
     // We need to parse this and extract the coordinates
 
@@ -352,10 +317,10 @@ fn parse_line_string(line_string: &str) -> Result<Vec<[f64; 2]>, String> {
         let clean_part = part.trim_start_matches('[').trim_end_matches(']');
         let coords: Vec<&str> = clean_part.split(',').collect();
 
-        if coords.len() == 2 {
-            if let (Ok(lon), Ok(lat)) = (coords[0].parse::<f64>(), coords[1].parse::<f64>()) {
-                coordinates.push([lon, lat]);
-            }
+        if coords.len() == 2
+            && let (Ok(lon), Ok(lat)) = (coords[0].parse::<f64>(), coords[1].parse::<f64>())
+        {
+            coordinates.push([lon, lat]);
         }
     }
 
@@ -365,3 +330,62 @@ fn parse_line_string(line_string: &str) -> Result<Vec<[f64; 2]>, String> {
         Ok(coordinates)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::source::EmbeddedSource;
+
+    const STATION: &str = r#"{"StationUniqueId":"940GZZLUNWH","StationName":"Northwood Hills","FareZones":"6","OutsideStationUniqueId":"940GZZLUNWH-Outside","Lat":51.6,"Lon":-0.4}"#;
+
+    fn stations_source(json: String) -> EmbeddedSource {
+        EmbeddedSource::new().with_file(DataFile::Stations, json.into_bytes())
+    }
+
+    #[tokio::test]
+    async fn loads_stations() {
+        let source = stations_source(format!(r#"{{"success":true,"results":[{}]}}"#, STATION));
+        let stations = load_stations(&source, &|_, _| {}).await.unwrap().results;
+        assert_eq!(stations[0].station_name, "Northwood Hills");
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_a_fetch_error() {
+        let err = load_stations(&EmbeddedSource::new(), &|_, _| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DataError::Fetch { file, .. } if file == "stations.json"));
+    }
+
+    #[tokio::test]
+    async fn unsuccessful_response() {
+        let source = stations_source(r#"{"success":false,"results":[]}"#.to_string());
+        let err = load_stations(&source, &|_, _| {}).await.unwrap_err();
+        assert_eq!(
+            err,
+            DataError::Unsuccessful {
+                file: "stations.json".to_string(),
+                context: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn parse_error_has_path_and_position() {
+        let bad_station = STATION.replace("51.6", r#""north""#);
+        let source = stations_source(format!(
+            "{{\"success\":true,\n\"results\":[{},{}]}}",
+            STATION, bad_station
+        ));
+        match load_stations(&source, &|_, _| {}).await.unwrap_err() {
+            DataError::Parse {
+                file, path, line, ..
+            } => {
+                assert_eq!(file, "stations.json");
+                assert_eq!(path, "results[1].Lat");
+                assert_eq!(line, 2);
+            }
+            err => panic!("expected a parse error, got {:?}", err),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::model::{Platform, Station};
+use super::model::{BusStop, Platform, Station};
 use crate::data::TflDataRepository;
 use crate::utils::geojson::{
     new_geojson_source, new_linestring_feature, new_point_feature, to_js_value,
@@ -43,6 +43,30 @@ pub fn stations_to_geojson(stations: &[Station]) -> Result<JsValue, JsError> {
     to_js_value(&geojson_source)
 }
 
+/// Convert bus stops into a GeoJSON source, with the routes calling at each
+/// as a `routes` string, as MapLibre doesn't keep arrays in properties
+pub fn bus_stops_to_geojson(stops: &[BusStop]) -> Result<JsValue, JsError> {
+    let features = stops
+        .iter()
+        .map(|stop| {
+            let properties = serde_json::json!({
+                "id": stop.id,
+                "name": stop.name,
+                "letter": stop.letter.as_deref().unwrap_or_default(),
+                "towards": stop.towards.as_deref().unwrap_or_default(),
+                "routes": stop.routes.join(", "),
+            });
+            new_point_feature(stop.lon, stop.lat, properties)
+        })
+        .collect();
+
+    log::debug_with_category(
+        LogCategory::Map,
+        &format!("Created GeoJSON with {} bus stops", stops.len()),
+    );
+    to_js_value(&new_geojson_source(features))
+}
+
 /// Create a mapping of line names to their corresponding stations
 pub fn create_line_stations_map(platforms: &[Platform]) -> HashMap<String, Vec<String>> {
     let mut map = HashMap::new();
@@ -206,7 +230,7 @@ pub fn generate_all_line_data(
 /// Convert route geometries for a specific line to GeoJSON
 pub fn route_geometries_to_geojson(
     line_id: &str,
-    geometries: &Vec<Vec<[f64; 2]>>,
+    geometries: &[Vec<[f64; 2]>],
 ) -> Result<JsValue, JsError> {
     // Create features for each non-empty geometry
     let features: Vec<_> = geometries
@@ -228,15 +252,19 @@ pub fn route_geometries_to_geojson(
     to_js_value(&geojson_source)
 }
 
-/// Generate all route geometries as GeoJSON for multiple lines
-pub fn generate_all_route_geometries(
+/// Generate route geometries as GeoJSON for the given lines
+pub fn generate_route_geometries<'a>(
     tfl_data: &TflDataRepository,
+    line_ids: impl IntoIterator<Item = &'a String>,
 ) -> Result<Vec<(String, JsValue)>, JsError> {
     let mut result = Vec::new();
 
     // Process each line
-    for (line_id, geometries) in &tfl_data.route_geometries {
+    for line_id in line_ids {
         // Skip lines with no geometries
+        let Some(geometries) = tfl_data.route_geometries.get(line_id) else {
+            continue;
+        };
         if geometries.is_empty() {
             continue;
         }
@@ -1,14 +1,44 @@
+pub mod bus;
+pub mod compact;
+pub mod diff;
+pub mod error;
+pub mod gtfs;
+pub mod gtfs_export;
 pub mod line_definitions;
 pub mod loader;
+#[cfg(target_arch = "wasm32")]
 pub mod map_helpers;
 pub mod model;
+pub mod naptan;
+pub mod prepare;
+pub mod provenance;
+pub mod services;
+pub mod snapshots;
+pub mod source;
+pub mod stages;
+pub mod validate;
 
 // Re-export commonly used items
-pub use map_helpers::{generate_all_route_geometries, stations_to_geojson};
+pub use error::DataError;
+#[cfg(target_arch = "wasm32")]
+pub use map_helpers::{bus_stops_to_geojson, generate_route_geometries, stations_to_geojson};
+#[cfg(target_arch = "wasm32")]
+pub use source::UrlSource;
+pub use source::{AnySource, DataSource};
 
 use crate::utils::log::{self, LogCategory};
 use std::collections::HashMap;
 
+/// What the data itself says about a line, e.g. a GTFS route's name and
+/// colour, for lines the registry may not know
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineDetails {
+    pub name: String,
+    /// `#RRGGBB`, which takes precedence over the registry's colour
+    pub color: Option<String>,
+    pub mode: String,
+}
+
 /// A consolidated data repository for TfL data
 #[derive(Clone, Default)]
 pub struct TflDataRepository {
@@ -22,17 +52,33 @@ pub struct TflDataRepository {
     pub routes: HashMap<String, HashMap<String, Vec<model::RouteSequence>>>,
     /// Route geometries by line ID for efficient rendering
     pub route_geometries: HashMap<String, Vec<Vec<[f64; 2]>>>,
+    /// The bus routes available and loaded, if buses are switched on
+    pub buses: Option<bus::BusRoutes>,
+    /// Where each dataset came from and when, see [`provenance`]
+    pub provenance: Vec<provenance::Provenance>,
+    /// Names and colours given by the data, by line ID; empty for the TfL
+    /// datasets, which rely on the line registry
+    pub line_details: HashMap<String, LineDetails>,
+    /// How often each line runs through the day, see [`services`]; `None`
+    /// until read
+    pub services: Option<services::ServicePatterns>,
+    /// The dated snapshot the data is from, or `None` for the current data
+    pub snapshot: Option<String>,
+    /// The date the network is shown as of, which decides the names and
+    /// colours of aliased lines, see [`line_definitions::LINE_ALIASES`]
+    pub as_of: Option<String>,
     /// Indicates if the repository has been loaded
     pub is_loaded: bool,
 }
 
 impl TflDataRepository {
-    /// Initialize the data repository by loading all data
-    pub async fn initialize(load_buses: bool) -> Result<Self, String> {
-        log::info_with_category(LogCategory::App, "Initializing TFL data repository");
-
-        // Load and process stations
-        let stations = loader::load_stations().await?;
+    /// Build the repository from the raw datasets
+    pub fn from_datasets(
+        stations: Vec<model::Station>,
+        platforms: Vec<model::Platform>,
+        routes: HashMap<String, HashMap<String, Vec<model::RouteSequence>>>,
+    ) -> Self {
+        // Process stations
         let valid_stations = loader::filter_valid_stations(stations);
 
         // Create lookup map for stations
@@ -41,12 +87,10 @@ impl TflDataRepository {
             .map(|s| (s.station_unique_id.clone(), s.clone()))
             .collect();
 
-        // Load and process platforms
-        let platforms = loader::load_platforms().await?;
+        // Process platforms
         let platforms_by_station = loader::group_platforms_by_station(platforms);
 
-        // Load and process routes
-        let routes = loader::load_routes(load_buses).await?;
+        // Process routes
         let route_geometries = loader::process_route_geometries(&routes);
 
         log::info_with_category(
@@ -58,14 +102,148 @@ impl TflDataRepository {
             ),
         );
 
-        Ok(Self {
+        Self {
             stations: valid_stations,
             platforms_by_station,
             station_by_id,
             routes,
             route_geometries,
+            buses: None,
+            provenance: Vec::new(),
+            line_details: HashMap::new(),
+            services: None,
+            snapshot: None,
+            as_of: None,
             is_loaded: true,
-        })
+        }
+    }
+
+    /// The colour to draw a line in: its own if the data gives one, otherwise
+    /// the registry's as of the repository's date
+    pub fn line_color(&self, line_id: &str) -> String {
+        self.line_details
+            .get(line_id)
+            .and_then(|details| details.color.clone())
+            .unwrap_or_else(|| line_definitions::line_color_as_of(line_id, self.as_of.as_deref()))
+    }
+
+    /// Whether buses are switched on
+    pub fn has_buses(&self) -> bool {
+        self.buses.is_some()
+    }
+
+    /// Switch buses on, given the manifest of bus routes
+    ///
+    /// No routes are loaded yet: add them as they are wanted with
+    /// [`Self::add_bus_route`].
+    pub fn load_buses(&mut self, manifest: &model::BusManifest) {
+        if self.buses.is_none() {
+            self.buses = Some(bus::BusRoutes::new(manifest.clone()));
+        }
+    }
+
+    /// Switch buses off, removing every bus route that was added
+    pub fn unload_buses(&mut self) {
+        let Some(mut buses) = self.buses.take() else {
+            return;
+        };
+
+        log::info_with_category(
+            LogCategory::App,
+            &format!("Removing {} bus routes", buses.loaded_count()),
+        );
+
+        for line_id in buses.loaded.drain() {
+            self.remove_line(&line_id);
+        }
+    }
+
+    /// Bus routes that pass through an extent but aren't loaded yet
+    pub fn bus_routes_to_load(&self, bounds: [f64; 4]) -> Vec<String> {
+        let Some(buses) = self.buses.as_ref() else {
+            return Vec::new();
+        };
+
+        buses
+            .in_bounds(bounds)
+            .into_iter()
+            .filter(|id| !buses.is_loaded(id))
+            .collect()
+    }
+
+    /// Mark the loaded bus routes that pass through an extent as recently
+    /// used, so they are the last to be dropped when more are added
+    pub fn keep_bus_routes_in(&mut self, bounds: [f64; 4]) {
+        if let Some(buses) = self.buses.as_mut() {
+            for id in buses.in_bounds(bounds) {
+                buses.loaded.touch(&id);
+            }
+        }
+    }
+
+    /// Whether the bus stops haven't been asked for since buses were
+    /// switched on, see [`Self::request_bus_stops`]
+    pub fn wants_bus_stops(&self) -> bool {
+        self.buses
+            .as_ref()
+            .is_some_and(|buses| !buses.stops_requested)
+    }
+
+    /// Mark the bus stops as asked for, so a missing stops file isn't read
+    /// over and over
+    pub fn request_bus_stops(&mut self) {
+        if let Some(buses) = self.buses.as_mut() {
+            buses.stops_requested = true;
+        }
+    }
+
+    /// Add the bus stops read from their file, if buses are still switched on
+    pub fn set_bus_stops(&mut self, stops_file: model::BusStopsFile) {
+        if let Some(buses) = self.buses.as_mut() {
+            buses.stops = stops_file.stops;
+        }
+    }
+
+    /// The bus stops to show: none unless buses are switched on
+    pub fn bus_stops(&self) -> &[model::BusStop] {
+        self.buses.as_ref().map_or(&[], |buses| &buses.stops)
+    }
+
+    /// Add a bus route read from its own file
+    ///
+    /// Lines already loaded from the rail routes are left alone. If the cache
+    /// of bus routes is full, the least recently used route is removed.
+    pub fn add_bus_route(&mut self, id: &str, routes_file: &model::RoutesFile) {
+        let Some(buses) = self.buses.as_mut() else {
+            return;
+        };
+        if buses.is_loaded(id) || self.routes.contains_key(id) {
+            return;
+        }
+
+        let path = bus::route_path(id);
+        let bus_routes = loader::merge_routes([(path.as_str(), routes_file)]);
+        let mut bus_geometries = loader::process_route_geometries(&bus_routes);
+
+        for (line_id, directions) in bus_routes {
+            if let Some(geometries) = bus_geometries.remove(&line_id) {
+                self.route_geometries.insert(line_id.clone(), geometries);
+            }
+            self.routes.insert(line_id, directions);
+        }
+
+        if let Some(evicted) = buses.loaded.insert(id.to_string()) {
+            log::debug_with_category(
+                LogCategory::App,
+                &format!("Dropping least recently used bus route {}", evicted),
+            );
+            self.remove_line(&evicted);
+        }
+    }
+
+    fn remove_line(&mut self, line_id: &str) {
+        self.routes.remove(line_id);
+        self.route_geometries.remove(line_id);
     }
 
     // /// Get a station by its unique ID
@@ -103,29 +103,34 @@ pub struct Platform {
 }
 
 /// Response structure from the stations API
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct StationsResponse {
-    // pub context: ResponseContext,
+    #[serde(default)]
+    pub context: Option<ResponseContext>,
     pub success: bool,
     pub results: Vec<Station>,
 }
 
 /// Response structure from the platforms API
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PlatformsResponse {
-    // pub context: ResponseContext,
+    #[serde(default)]
+    pub context: Option<ResponseContext>,
     pub success: bool,
     pub results: Vec<Platform>,
 }
 
-// /// Context information included in API responses
-// #[derive(Debug, Deserialize)]
-// pub struct ResponseContext {
-//     pub request_time: String,
-//     pub response_time: String,
-//     pub response_latency: f64,
-//     pub query: String,
-// }
+/// Context information included in API responses
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ResponseContext {
+    /// When the request was made, e.g. `2025-04-04T10:58:23.565156Z`
+    pub request_time: String,
+    pub response_time: String,
+    /// Seconds taken to respond
+    pub response_latency: f64,
+    /// The query that produced the results, e.g. `line=central,direction=inbound`
+    pub query: String,
+}
 
 /// Represents a TfL route sequence with line information and stations
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -151,10 +156,12 @@ pub struct RouteSequence {
     // /// Stations along this route
     // #[serde(rename = "Stations")]
     // pub stations: Vec<MatchedStop>,
-    // /// Detailed stop point sequences
-    // #[serde(rename = "StopPointSequences")]
-    // #[serde(default)]
-    // pub stop_point_sequences: Vec<StopPointSequence>,
+    /// The stations along each branch of the line, for rail lines; the
+    /// bundled routes keep only what the simulation reads of them, see
+    /// [`super::prepare::prepare_route_response`]
+    #[serde(rename = "StopPointSequences")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_point_sequences: Vec<StopPointSequence>,
     // /// Ordered line routes
     // #[serde(rename = "OrderedLineRoutes")]
     // #[serde(default)]
@@ -195,7 +202,7 @@ pub struct StopPointSequence {
 }
 
 /// Represents a station stop on a route
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct MatchedStop {
     /// Route identifier
     #[serde(rename = "RouteId")]
@@ -346,7 +353,8 @@ pub struct OrderedRoute {
 /// Response structure for a single route
 #[derive(Debug, Deserialize)]
 pub struct RouteResponse {
-    // pub context: ResponseContext,
+    #[serde(default)]
+    pub context: Option<ResponseContext>,
     pub success: bool,
     pub results: Vec<RouteSequence>,
 }
@@ -357,3 +365,42 @@ pub struct RoutesFile {
     #[serde(flatten)]
     pub routes: HashMap<String, HashMap<String, RouteResponse>>,
 }
+
+/// Index of the per-route bus files in `bus/`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct BusManifest {
+    pub routes: Vec<BusManifestEntry>,
+}
+
+/// A bus route listed in the manifest
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BusManifestEntry {
+    /// Line ID, which is also the name of the route's file
+    pub id: String,
+    /// Extent of the route as `[west, south, east, north]`
+    pub bbox: [f64; 4],
+}
+
+/// The bus stops served by the bus routes, in `bus/stops.json`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct BusStopsFile {
+    pub stops: Vec<BusStop>,
+}
+
+/// A bus stop, placed from NaPTAN and linked to the routes calling at it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BusStop {
+    /// NaPTAN ATCO code, e.g. `490012280A`
+    pub id: String,
+    pub name: String,
+    /// Letter on the stop's flag, e.g. `A`, from the route data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub letter: Option<String>,
+    /// Where buses from the stop are heading, e.g. `Camden Town`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub towards: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    /// IDs of the bus routes calling at the stop
+    pub routes: Vec<String>,
+}
@@ -0,0 +1,240 @@
+//! Bus stops from NaPTAN, linked to the bus routes that call at them.
+//!
+//! NaPTAN (the National Public Transport Access Nodes dataset) lists every
+//! stop in Great Britain in its `Stops.csv`, with a position, name and
+//! indicator. The route sequence responses list the stops on each bus route
+//! by the same ATCO code, with the letter on the stop's flag, but the
+//! bundled bus routes leave them out, see [`super::prepare::prepare_route_response`].
+//! `tfl_data stops` joins the two into [`BUS_STOPS_PATH`](super::bus::BUS_STOPS_PATH), keeping only
+//! the stops some route calls at.
+//!
+//! A stop NaPTAN doesn't have, or has no position for, is placed where the
+//! route data says instead.
+
+use super::error::{DataError, parse_json};
+use super::gtfs::parse_table;
+use super::model::{BusStop, MatchedStop, StopPointSequence};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A row of NaPTAN's `Stops.csv`, with just the columns used
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NaptanStop {
+    #[serde(rename = "ATCOCode")]
+    pub atco_code: String,
+    #[serde(rename = "CommonName")]
+    pub common_name: String,
+    /// Where the stop is relative to its street, e.g. `Stop A` or `opp`
+    #[serde(rename = "Indicator", default)]
+    pub indicator: Option<String>,
+    #[serde(rename = "Longitude", default)]
+    pub longitude: Option<f64>,
+    #[serde(rename = "Latitude", default)]
+    pub latitude: Option<f64>,
+    /// `active`, `inactive` or `del`, if given
+    #[serde(rename = "Status", default)]
+    pub status: Option<String>,
+}
+
+impl NaptanStop {
+    /// Whether the stop is in use: one that is inactive or deleted isn't
+    pub fn is_active(&self) -> bool {
+        self.status
+            .as_deref()
+            .is_none_or(|status| status.is_empty() || status.eq_ignore_ascii_case("active"))
+    }
+}
+
+/// Parse NaPTAN's `Stops.csv`
+pub fn parse_naptan_stops(file: &str, text: &str) -> Result<Vec<NaptanStop>, DataError> {
+    parse_table(file, text)
+}
+
+/// The parts of a route sequence response that say which stops it calls at
+#[derive(Deserialize)]
+struct RouteStopsResponse {
+    results: Vec<RouteStops>,
+}
+
+#[derive(Deserialize)]
+struct RouteStops {
+    #[serde(rename = "LineId")]
+    line_id: String,
+    #[serde(rename = "StopPointSequences", default)]
+    stop_point_sequences: Vec<StopPointSequence>,
+}
+
+/// The stops on each bus route, gathered from route sequence responses
+#[derive(Debug, Clone, Default)]
+pub struct RouteStopIndex {
+    /// Each stop as the route data first gives it, with the routes calling at it
+    stops: BTreeMap<String, (MatchedStop, BTreeSet<String>)>,
+}
+
+impl RouteStopIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the stops in a downloaded route sequence response
+    pub fn add_response(&mut self, file: &str, text: &str) -> Result<(), DataError> {
+        let response: RouteStopsResponse = parse_json(file, text)?;
+        for route in response.results {
+            let stops = route
+                .stop_point_sequences
+                .into_iter()
+                .flat_map(|sequence| sequence.stop_point);
+            for stop in stops {
+                let Some(id) = stop.id.clone() else {
+                    continue;
+                };
+                self.stops
+                    .entry(id)
+                    .or_insert_with(|| (stop, BTreeSet::new()))
+                    .1
+                    .insert(route.line_id.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// How many distinct stops the routes call at
+    pub fn len(&self) -> usize {
+        self.stops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// Place each stop from NaPTAN, see the [module docs](self)
+    pub fn link(&self, naptan: &[NaptanStop]) -> LinkedStops {
+        let naptan: BTreeMap<&str, &NaptanStop> = naptan
+            .iter()
+            .filter(|stop| stop.is_active())
+            .map(|stop| (stop.atco_code.as_str(), stop))
+            .collect();
+
+        let mut linked = LinkedStops::default();
+        for (id, (stop, routes)) in &self.stops {
+            let from_naptan = naptan.get(id.as_str()).and_then(|naptan_stop| {
+                Some((
+                    naptan_stop.common_name.clone(),
+                    naptan_stop.latitude?,
+                    naptan_stop.longitude?,
+                ))
+            });
+            let from_routes = || Some((stop.name.clone()?, stop.lat?, stop.lon?));
+            let Some((name, lat, lon)) = from_naptan.or_else(|| {
+                linked.not_in_naptan.push(id.clone());
+                from_routes()
+            }) else {
+                linked.unplaced.push(id.clone());
+                continue;
+            };
+
+            let mut routes: Vec<String> = routes.iter().cloned().collect();
+            routes.sort_by_key(|route| route_order(route));
+            linked.stops.push(BusStop {
+                id: id.clone(),
+                name,
+                letter: stop.stop_letter.clone().filter(|letter| !letter.is_empty()),
+                towards: stop.towards.clone().filter(|towards| !towards.is_empty()),
+                lat,
+                lon,
+                routes,
+            });
+        }
+        linked
+    }
+}
+
+/// Bus stops placed by [`RouteStopIndex::link`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkedStops {
+    pub stops: Vec<BusStop>,
+    /// Stops NaPTAN doesn't place, placed from the route data if it could be
+    pub not_in_naptan: Vec<String>,
+    /// Stops neither NaPTAN nor the route data place, left out
+    pub unplaced: Vec<String>,
+}
+
+/// Sort key putting route IDs in the order TfL lists them: `24` before
+/// `134`, and lettered routes like `C2` and `N5` after the numbered ones
+fn route_order(id: &str) -> (String, u32, String) {
+    let prefix: String = id.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let digits: String = id[prefix.len()..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    (
+        prefix.to_lowercase(),
+        digits.parse().unwrap_or(0),
+        id.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(line_id: &str, stops: &[(&str, &str)]) -> String {
+        let stops: Vec<String> = stops
+            .iter()
+            .map(|(id, letter)| {
+                format!(
+                    r#"{{"Id":"{}","Name":"Route name {}","StopLetter":"{}","Lat":51.5,"Lon":-0.1}}"#,
+                    id, id, letter
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"success":true,"results":[{{"LineId":"{0}","StopPointSequences":[
+                {{"LineId":"{0}","LineName":"{0}","Direction":"inbound","BranchId":0,
+                  "StopPoint":[{1}]}}]}}]}}"#,
+            line_id,
+            stops.join(",")
+        )
+    }
+
+    #[test]
+    fn links_route_stops_to_naptan() {
+        let mut index = RouteStopIndex::new();
+        index
+            .add_response("134.json", &response("134", &[("S1", "A"), ("S2", "")]))
+            .unwrap();
+        index
+            .add_response("24.json", &response("24", &[("S1", "A"), ("S3", "C")]))
+            .unwrap();
+        assert_eq!(index.len(), 3);
+
+        let naptan = parse_naptan_stops(
+            "Stops.csv",
+            "ATCOCode,NaptanCode,CommonName,Indicator,Longitude,Latitude,Status\n\
+             S1,123,Camden Town,Stop A,-0.14,51.54,active\n\
+             S2,124,Old Stop,,-0.15,51.55,del\n\
+             S9,125,Elsewhere,,,,active\n",
+        )
+        .unwrap();
+        let linked = index.link(&naptan);
+
+        let stop = &linked.stops[0];
+        assert_eq!(stop.name, "Camden Town");
+        assert_eq!((stop.lat, stop.lon), (51.54, -0.14));
+        assert_eq!(stop.letter.as_deref(), Some("A"));
+        assert_eq!(stop.routes, ["24", "134"]);
+
+        // A deleted stop is placed from the route data, without its empty letter
+        assert_eq!(linked.stops[1].name, "Route name S2");
+        assert_eq!(linked.stops[1].letter, None);
+        assert_eq!(linked.not_in_naptan, ["S2", "S3"]);
+        assert!(linked.unplaced.is_empty());
+    }
+
+    #[test]
+    fn orders_routes_as_tfl_does() {
+        let mut routes = vec!["N5", "134", "C2", "24", "N133"];
+        routes.sort_by_key(|route| route_order(route));
+        assert_eq!(routes, ["24", "134", "C2", "N5", "N133"]);
+    }
+}
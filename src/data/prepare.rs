@@ -0,0 +1,269 @@
+//! Preparing the bundled datasets from downloaded API responses.
+//!
+//! The route sequence endpoint returns one response per line and direction.
+//! These functions check each response, strip it down to the fields the app
+//! reads and combine them into the files in `assets/data`. They are used by
+//! the `tfl_data` tool, and do no downloading themselves.
+
+use super::error::{DataError, parse_json};
+use super::loader::parse_line_string;
+use super::model::{BusManifestEntry, RouteResponse, RoutesFile};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Fields of each route sequence that the app doesn't use, and that make up
+/// most of the size of a response
+pub const DROPPED_ROUTE_FIELDS: [&str; 4] = [
+    "Stations",
+    "LineName",
+    "IsOutboundOnly",
+    "OrderedLineRoutes",
+];
+
+/// Fields of each branch in a sequence's `StopPointSequences` that the
+/// simulation reads, to run trains along whole workings of a line
+pub const KEPT_BRANCH_FIELDS: [&str; 8] = [
+    "LineId",
+    "LineName",
+    "Direction",
+    "BranchId",
+    "NextBranchIds",
+    "PrevBranchIds",
+    "StopPoint",
+    "ServiceType",
+];
+
+/// Fields of each stop on a branch that the simulation reads
+pub const KEPT_BRANCH_STOP_FIELDS: [&str; 6] =
+    ["Id", "StationId", "TopMostParentId", "Name", "Lat", "Lon"];
+
+/// Responses by line ID, then by direction
+pub type LineResponses = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// Check a downloaded route response and strip it down for bundling
+///
+/// The response must match [`RouteResponse`]; the error says where it doesn't.
+pub fn prepare_route_response(file: &str, text: &str) -> Result<Value, DataError> {
+    parse_json::<RouteResponse>(file, text)?;
+
+    let mut response: Value = parse_json(file, text)?;
+    if let Some(results) = response.get_mut("results").and_then(Value::as_array_mut) {
+        for sequence in results.iter_mut().filter_map(Value::as_object_mut) {
+            for field in DROPPED_ROUTE_FIELDS {
+                sequence.shift_remove(field);
+            }
+
+            // Buses run one stop pattern per direction, so their branches
+            // would only add size
+            if sequence.get("Mode").and_then(Value::as_str) == Some("bus") {
+                sequence.shift_remove("StopPointSequences");
+            } else if let Some(branches) = sequence
+                .get_mut("StopPointSequences")
+                .and_then(Value::as_array_mut)
+            {
+                for branch in branches.iter_mut().filter_map(Value::as_object_mut) {
+                    branch.retain(|field, _| KEPT_BRANCH_FIELDS.contains(&field.as_str()));
+                    let stops = branch.get_mut("StopPoint").and_then(Value::as_array_mut);
+                    for stop in stops.into_iter().flatten().filter_map(Value::as_object_mut) {
+                        stop.retain(|field, _| KEPT_BRANCH_STOP_FIELDS.contains(&field.as_str()));
+                    }
+                }
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// Whether a prepared response says `success: true`
+pub fn is_successful(response: &Value) -> bool {
+    response.get("success").and_then(Value::as_bool) == Some(true)
+}
+
+/// Combine responses into the `{line: {direction: response}}` shape of
+/// `rail_routes.json` and the per-route bus files
+pub fn combine_routes<'a>(
+    lines: impl IntoIterator<Item = (&'a String, &'a BTreeMap<String, Value>)>,
+) -> Value {
+    let combined = lines
+        .into_iter()
+        .map(|(line_id, directions)| {
+            let directions = directions
+                .iter()
+                .map(|(direction, response)| (direction.clone(), response.clone()))
+                .collect();
+            (line_id.clone(), Value::Object(directions))
+        })
+        .collect();
+    Value::Object(combined)
+}
+
+/// The `[west, south, east, north]` extent of every line string in a routes file
+pub fn routes_bbox(routes_file: &RoutesFile) -> Option<[f64; 4]> {
+    let points = routes_file
+        .routes
+        .values()
+        .flat_map(|directions| directions.values())
+        .flat_map(|response| &response.results)
+        .flat_map(|sequence| &sequence.line_strings)
+        .filter_map(|line_string| parse_line_string(line_string).ok())
+        .flatten();
+
+    points.fold(None, |bbox, [lon, lat]| {
+        let [west, south, east, north] = bbox.unwrap_or([lon, lat, lon, lat]);
+        Some([west.min(lon), south.min(lat), east.max(lon), north.max(lat)])
+    })
+}
+
+/// The manifest entry for a bus route, or `None` if it has no geometry
+pub fn bus_manifest_entry(id: &str, routes_file: &RoutesFile) -> Option<BusManifestEntry> {
+    routes_bbox(routes_file).map(|bbox| BusManifestEntry {
+        id: id.to_string(),
+        bbox,
+    })
+}
+
+/// Approximate distance in metres from a point to a segment, treating the
+/// small area around them as flat
+fn distance_to_segment(point: [f64; 2], start: [f64; 2], end: [f64; 2]) -> f64 {
+    const METRES_PER_DEGREE: f64 = 111_320.0;
+    let scale_x = METRES_PER_DEGREE * point[1].to_radians().cos();
+    let project = |p: [f64; 2]| (p[0] * scale_x, p[1] * METRES_PER_DEGREE);
+
+    let (px, py) = project(point);
+    let (ax, ay) = project(start);
+    let (bx, by) = project(end);
+    let (dx, dy) = (bx - ax, by - ay);
+
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((px - ax) * dx + (py - ay) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    ((px - ax - t * dx).powi(2) + (py - ay - t * dy).powi(2)).sqrt()
+}
+
+/// Simplify a polyline with the Douglas-Peucker algorithm, dropping points
+/// that are within `tolerance` metres of the simplified line
+pub fn simplify_polyline(points: &[[f64; 2]], tolerance: f64) -> Vec<[f64; 2]> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = spans.pop() {
+        let furthest = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    distance_to_segment(points[i], points[first], points[last]),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((index, distance)) = furthest
+            && distance > tolerance
+        {
+            keep[index] = true;
+            spans.push((first, index));
+            spans.push((index, last));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Simplify every line string in a routes file, returning how many points
+/// were dropped
+///
+/// Line strings that aren't a single line of points are left as they are.
+pub fn simplify_routes(routes_file: &mut RoutesFile, tolerance: f64) -> usize {
+    let mut dropped = 0;
+
+    let sequences = routes_file
+        .routes
+        .values_mut()
+        .flat_map(|directions| directions.values_mut())
+        .flat_map(|response| &mut response.results);
+    for sequence in sequences {
+        for line_string in &mut sequence.line_strings {
+            if line_string.contains("]],[[") {
+                continue;
+            }
+            let Ok(points) = parse_line_string(line_string) else {
+                continue;
+            };
+
+            let simplified = simplify_polyline(&points, tolerance);
+            dropped += points.len() - simplified.len();
+            let coordinates: Vec<String> = simplified
+                .iter()
+                .map(|[lon, lat]| format!("[{},{}]", lon, lat))
+                .collect();
+            *line_string = format!("[[{}]]", coordinates.join(","));
+        }
+    }
+
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_unused_fields_and_reports_invalid_responses() {
+        let prepared = prepare_route_response(
+            "bakerloo.json",
+            r#"{"success":true,"results":[{"LineId":"bakerloo","LineName":"Bakerloo",
+                "Direction":"inbound","Mode":"tube","LineStrings":[],"Stations":[]}]}"#,
+        )
+        .unwrap();
+        let sequence = &prepared["results"][0];
+        assert_eq!(sequence["LineId"], "bakerloo");
+        assert!(sequence.get("LineName").is_none() && sequence.get("Stations").is_none());
+
+        // Rail lines keep their branches, slimmed down; buses lose them
+        let branches = r#"[{"LineId":"x","LineName":"X","Direction":"inbound","BranchId":0,
+            "NextBranchIds":[],"PrevBranchIds":[],"IsOutboundOnly":false,
+            "StopPoint":[{"Id":"940GZZLUOXC","Name":"Oxford Circus","Zone":"1","Lat":51.5,"Lon":-0.1}]}]"#;
+        let response = |mode| {
+            format!(
+                r#"{{"success":true,"results":[{{"LineId":"x","Direction":"inbound","Mode":"{}",
+                    "LineStrings":[],"StopPointSequences":{}}}]}}"#,
+                mode, branches
+            )
+        };
+        let prepared = prepare_route_response("x.json", &response("tube")).unwrap();
+        let branch = &prepared["results"][0]["StopPointSequences"][0];
+        assert_eq!(branch["LineName"], "X");
+        assert!(branch.get("IsOutboundOnly").is_none());
+        assert_eq!(branch["StopPoint"][0]["Name"], "Oxford Circus");
+        assert!(branch["StopPoint"][0].get("Zone").is_none());
+        let prepared = prepare_route_response("x.json", &response("bus")).unwrap();
+        assert!(prepared["results"][0].get("StopPointSequences").is_none());
+
+        let err = prepare_route_response(
+            "central.json",
+            r#"{"success":true,"results":[{"LineId":"central"}]}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, DataError::Parse { path, .. } if path == "results[0]"));
+    }
+
+    #[test]
+    fn simplifies_within_tolerance() {
+        // The middle point is about 11 m off the straight line
+        let points = [[-0.1, 51.5], [-0.099, 51.5001], [-0.098, 51.5]];
+        assert_eq!(simplify_polyline(&points, 20.0).len(), 2);
+        assert_eq!(simplify_polyline(&points, 5.0).len(), 3);
+    }
+}
@@ -0,0 +1,139 @@
+//! Where each dataset came from, and how old it is.
+//!
+//! Every API response carries a `context` with the query that produced it and
+//! when it was requested. The repository keeps a [`Provenance`] per dataset
+//! from these, so the app can say how fresh its data is.
+
+use super::model::{ResponseContext, RoutesFile};
+use super::source::DataFile;
+use crate::utils::time::parse_utc_timestamp;
+
+/// Age in days beyond which the data is reported as stale, by default
+pub const DEFAULT_MAX_AGE_DAYS: u32 = 30;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Where a dataset came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub file: DataFile,
+    /// The query that produced the data, or a summary if there were several
+    pub query: Option<String>,
+    /// When the data was requested, as given by the API; the oldest request if
+    /// there were several
+    pub fetched_at: Option<String>,
+    /// How many records there are, e.g. stations or route sequences
+    pub record_count: usize,
+}
+
+impl Provenance {
+    /// Provenance of a dataset from a single response
+    pub fn from_context(
+        file: DataFile,
+        context: Option<&ResponseContext>,
+        record_count: usize,
+    ) -> Self {
+        Self {
+            file,
+            query: context.map(|c| c.query.clone()),
+            fetched_at: context.map(|c| c.request_time.clone()),
+            record_count,
+        }
+    }
+
+    /// Provenance of a routes file, which combines a response per line and
+    /// direction
+    pub fn from_routes(file: DataFile, routes_file: &RoutesFile) -> Self {
+        let responses: Vec<_> = routes_file
+            .routes
+            .values()
+            .flat_map(|directions| directions.values())
+            .collect();
+
+        let mut queries: Vec<&str> = responses
+            .iter()
+            .filter_map(|response| response.context.as_ref())
+            .map(|context| context.query.as_str())
+            .collect();
+        queries.sort_unstable();
+        let query = match queries.as_slice() {
+            [] => None,
+            [query] => Some(query.to_string()),
+            [first, rest @ ..] => Some(format!("{} and {} more", first, rest.len())),
+        };
+
+        // Timestamps in the API's form sort in time order
+        let fetched_at = responses
+            .iter()
+            .filter_map(|response| response.context.as_ref())
+            .map(|context| context.request_time.as_str())
+            .min()
+            .map(str::to_string);
+
+        Self {
+            file,
+            query,
+            fetched_at,
+            record_count: responses.iter().map(|r| r.results.len()).sum(),
+        }
+    }
+
+    /// How many days old the data is at `now` (seconds since the epoch), if
+    /// it says when it was fetched
+    pub fn age_days(&self, now: f64) -> Option<f64> {
+        let fetched = parse_utc_timestamp(self.fetched_at.as_deref()?)?;
+        Some((now - fetched) / SECONDS_PER_DAY)
+    }
+
+    /// Whether the data is older than `max_age_days` at `now`
+    ///
+    /// Data that doesn't say when it was fetched isn't counted as stale.
+    pub fn is_stale(&self, now: f64, max_age_days: u32) -> bool {
+        self.age_days(now)
+            .is_some_and(|age| age > f64::from(max_age_days))
+    }
+}
+
+/// The day the oldest of the datasets was fetched, `YYYY-MM-DD`
+pub fn fetched_on(provenance: &[Provenance]) -> Option<&str> {
+    provenance
+        .iter()
+        .filter_map(|dataset| dataset.fetched_at.as_deref())
+        .min()
+        .and_then(|fetched_at| fetched_at.get(..10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::error::parse_json;
+    use crate::utils::time::parse_utc_timestamp;
+
+    #[test]
+    fn routes_provenance_uses_the_oldest_request() {
+        let routes: RoutesFile = parse_json(
+            "rail_routes.json",
+            r#"{"central":{
+                "inbound":{"success":true,"results":[],"context":{"request_time":"2025-04-05T09:19:23Z",
+                    "response_time":"","response_latency":0.1,"query":"line=central,direction=inbound"}},
+                "outbound":{"success":true,"results":[],"context":{"request_time":"2025-04-04T09:00:00Z",
+                    "response_time":"","response_latency":0.1,"query":"line=central,direction=outbound"}}}}"#,
+        )
+        .unwrap();
+
+        let provenance = Provenance::from_routes(DataFile::RailRoutes, &routes);
+        assert_eq!(
+            provenance.query.as_deref(),
+            Some("line=central,direction=inbound and 1 more")
+        );
+        assert_eq!(
+            provenance.fetched_at.as_deref(),
+            Some("2025-04-04T09:00:00Z")
+        );
+
+        let now = parse_utc_timestamp("2025-05-04T09:00:00Z").unwrap();
+        assert_eq!(provenance.age_days(now), Some(30.0));
+        assert!(!provenance.is_stale(now, 30));
+        assert!(provenance.is_stale(now, 29));
+    }
+}
@@ -0,0 +1,370 @@
+//! How often each line runs through the day, and how long its trips take.
+//!
+//! The simulation dispatches vehicles by these. For the TfL datasets they're
+//! read from [`SERVICES_PATH`], a config file of bands of the day each with
+//! a headway and, if known, an end-to-end running time:
+//!
+//! ```json
+//! {
+//!   "modes": { "tube": [{ "start": "05:30", "end": "24:30", "headway_mins": 5 }] },
+//!   "lines": { "victoria": [{ "start": "07:00", "end": "10:00", "headway_mins": 2, "running_mins": 32 }] }
+//! }
+//! ```
+//!
+//! For a GTFS feed they're worked out from its trips' stop times instead,
+//! with [`ServicePatterns::from_gtfs`].
+//!
+//! A line's bands are the ones given for its ID, or for the longest pattern
+//! ending in `*` that it matches (e.g. `n*` for the night buses), or else for
+//! its mode. A line with no band covering a time doesn't run then.
+
+use super::error::{DataError, parse_json};
+use super::gtfs::{GtfsFeed, GtfsFrequency, format_time, parse_time};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Path of the service patterns file, relative to the dataset root
+pub const SERVICES_PATH: &str = "services.json";
+
+/// Seconds in a day
+const DAY_SECS: u32 = 24 * 3_600;
+
+/// Service levels by line and by mode, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ServicePatterns {
+    /// Bands by line ID, or by a pattern ending in `*`
+    #[serde(default)]
+    pub lines: BTreeMap<String, Vec<ServiceBand>>,
+    /// Bands for the lines of each mode not given their own
+    #[serde(default)]
+    pub modes: BTreeMap<String, Vec<ServiceBand>>,
+}
+
+/// A part of the day with a steady service
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ServiceBand {
+    /// `HH:MM` the band starts at
+    pub start: String,
+    /// `HH:MM` the band runs up to, past `24:00` if it runs after midnight
+    pub end: String,
+    /// Minutes between departures in each direction
+    pub headway_mins: f64,
+    /// Minutes from one end of the line to the other, if known; otherwise
+    /// vehicles run at their mode's usual speed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub running_mins: Option<f64>,
+}
+
+impl ServiceBand {
+    /// Whether the band covers a time of day, in seconds since midnight
+    pub fn covers(&self, time: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let time = time % DAY_SECS;
+        (start..end).contains(&time) || (start..end).contains(&(time + DAY_SECS))
+    }
+}
+
+impl ServicePatterns {
+    /// Every line running all day at the same headway, for when there are
+    /// no patterns to go by
+    pub fn uniform(headway_mins: f64) -> Self {
+        let band = ServiceBand {
+            start: "00:00".to_string(),
+            end: "24:00".to_string(),
+            headway_mins,
+            running_mins: None,
+        };
+        Self {
+            lines: BTreeMap::from([("*".to_string(), vec![band])]),
+            modes: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a service patterns file
+    pub fn parse(file: &str, text: &str) -> Result<Self, DataError> {
+        parse_json(file, text)
+    }
+
+    /// The bands a line runs to, see the [module docs](self)
+    pub fn bands(&self, line_id: &str, mode: &str) -> &[ServiceBand] {
+        if let Some(bands) = self.lines.get(line_id) {
+            return bands;
+        }
+        let pattern = self
+            .lines
+            .iter()
+            .filter_map(|(pattern, bands)| Some((pattern.strip_suffix('*')?, bands)))
+            .filter(|(prefix, _)| line_id.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len());
+        match pattern {
+            Some((_, bands)) => bands,
+            None => self.modes.get(mode).map_or(&[], Vec::as_slice),
+        }
+    }
+
+    /// The band a line is running to at a time of day, in seconds since
+    /// midnight, if it is running then
+    pub fn band_at(&self, line_id: &str, mode: &str, time: u32) -> Option<&ServiceBand> {
+        self.bands(line_id, mode)
+            .iter()
+            .find(|band| band.covers(time))
+    }
+
+    /// Work out each route's service on a typical day from a feed's trips,
+    /// an hour at a time
+    ///
+    /// Only the trips of that day's services count, see [`day_services`].
+    /// The headway in each hour is from the number of trips starting then in
+    /// the busier direction, and the running time is their median. A trip
+    /// repeated by `frequencies.txt` counts each time it starts.
+    pub fn from_gtfs(feed: &GtfsFeed) -> Self {
+        // Each trip's first departure and last arrival
+        let mut trip_times: HashMap<&str, (u32, u32)> = HashMap::new();
+        for stop_time in &feed.stop_times {
+            let departure = stop_time.departure_time.as_deref().and_then(parse_time);
+            let arrival = stop_time.arrival_time.as_deref().and_then(parse_time);
+            let Some(time) = departure.or(arrival) else {
+                continue;
+            };
+            let times = trip_times
+                .entry(stop_time.trip_id.as_str())
+                .or_insert((time, time));
+            times.0 = times.0.min(time);
+            times.1 = times.1.max(arrival.unwrap_or(time));
+        }
+
+        let mut repeats: HashMap<&str, Vec<&GtfsFrequency>> = HashMap::new();
+        for frequency in &feed.frequencies {
+            repeats
+                .entry(frequency.trip_id.as_str())
+                .or_default()
+                .push(frequency);
+        }
+
+        // Trips starting in each hour, by route and direction
+        let services = day_services(feed);
+        let mut hours: BTreeMap<(&str, u32), HashMap<&str, Vec<u32>>> = BTreeMap::new();
+        for trip in &feed.trips {
+            if !services.contains(trip.service_id.as_str()) {
+                continue;
+            }
+            let Some(&(first, last)) = trip_times.get(trip.trip_id.as_str()) else {
+                continue;
+            };
+            let starts = match repeats.get(trip.trip_id.as_str()) {
+                Some(frequencies) => frequencies.iter().flat_map(|f| repeat_starts(f)).collect(),
+                None => vec![first],
+            };
+            for start in starts {
+                hours
+                    .entry((trip.route_id.as_str(), start / 3_600))
+                    .or_default()
+                    .entry(trip.direction())
+                    .or_default()
+                    .push(last - first);
+            }
+        }
+
+        let mut patterns = Self::default();
+        for ((route_id, hour), directions) in hours {
+            let Some(mut running) = directions.into_values().max_by_key(Vec::len) else {
+                continue;
+            };
+            running.sort_unstable();
+            patterns
+                .lines
+                .entry(route_id.to_string())
+                .or_default()
+                .push(ServiceBand {
+                    start: format_time(hour * 3_600),
+                    end: format_time((hour + 1) * 3_600),
+                    headway_mins: 60.0 / running.len() as f64,
+                    running_mins: Some(f64::from(running[running.len() / 2]) / 60.0),
+                });
+        }
+        patterns
+    }
+}
+
+/// The services a typical day runs: those running on Wednesdays in the
+/// feed's latest timetable, or without any in `calendar.txt`, the one with
+/// the most trips
+///
+/// A feed's services are often one for weekdays and one each for Saturdays
+/// and Sundays, so counting the trips of every service would add up several
+/// days' worth.
+fn day_services(feed: &GtfsFeed) -> HashSet<&str> {
+    let wednesdays: Vec<_> = feed
+        .calendar
+        .iter()
+        .filter(|calendar| calendar.wednesday == 1)
+        .collect();
+    if let Some(date) = wednesdays.iter().map(|calendar| &calendar.start_date).max() {
+        return wednesdays
+            .iter()
+            .filter(|calendar| calendar.start_date <= *date && *date <= calendar.end_date)
+            .map(|calendar| calendar.service_id.as_str())
+            .collect();
+    }
+
+    let mut trips: HashMap<&str, usize> = HashMap::new();
+    for trip in &feed.trips {
+        *trips.entry(trip.service_id.as_str()).or_default() += 1;
+    }
+    trips
+        .into_iter()
+        .max_by_key(|&(service_id, count)| (count, service_id))
+        .map(|(service_id, _)| service_id)
+        .into_iter()
+        .collect()
+}
+
+/// When a trip repeated by a `frequencies.txt` entry starts: every headway
+/// from the start of its window up to the end
+fn repeat_starts(frequency: &GtfsFrequency) -> Vec<u32> {
+    let window = parse_time(&frequency.start_time).zip(parse_time(&frequency.end_time));
+    match window {
+        Some((start, end)) if frequency.headway_secs > 0 => (start..end)
+            .step_by(frequency.headway_secs as usize)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::gtfs::{GtfsCalendar, GtfsStopTime, GtfsTrip};
+
+    fn band(start: &str, end: &str, headway_mins: f64) -> ServiceBand {
+        ServiceBand {
+            start: start.to_string(),
+            end: end.to_string(),
+            headway_mins,
+            running_mins: None,
+        }
+    }
+
+    #[test]
+    fn looks_up_bands_by_line_pattern_then_mode() {
+        let patterns = ServicePatterns {
+            lines: BTreeMap::from([
+                ("victoria".to_string(), vec![band("07:00", "10:00", 2.0)]),
+                ("n*".to_string(), vec![band("23:00", "29:00", 15.0)]),
+            ]),
+            modes: BTreeMap::from([
+                ("tube".to_string(), vec![band("05:30", "24:30", 5.0)]),
+                ("bus".to_string(), vec![band("05:00", "24:00", 10.0)]),
+            ]),
+        };
+        let at = |line, mode, time| {
+            patterns
+                .band_at(line, mode, parse_time(time).unwrap())
+                .map(|band| band.headway_mins)
+        };
+
+        assert_eq!(at("victoria", "tube", "08:00"), Some(2.0));
+        assert_eq!(at("victoria", "tube", "12:00"), None);
+        assert_eq!(at("central", "tube", "00:15"), Some(5.0));
+        assert_eq!(at("central", "tube", "03:00"), None);
+        assert_eq!(at("n29", "bus", "03:00"), Some(15.0));
+        assert_eq!(at("n29", "bus", "12:00"), None);
+        assert_eq!(at("29", "bus", "12:00"), Some(10.0));
+    }
+
+    fn trip(trip_id: &str, service_id: &str, direction_id: u8) -> GtfsTrip {
+        GtfsTrip {
+            route_id: "R".to_string(),
+            service_id: service_id.to_string(),
+            trip_id: trip_id.to_string(),
+            trip_headsign: None,
+            direction_id: Some(direction_id),
+            shape_id: None,
+        }
+    }
+
+    fn stop_time(trip_id: &str, time: &str, stop_sequence: u32) -> GtfsStopTime {
+        GtfsStopTime {
+            trip_id: trip_id.to_string(),
+            arrival_time: Some(time.to_string()),
+            departure_time: Some(time.to_string()),
+            stop_id: format!("S{}", stop_sequence),
+            stop_sequence,
+        }
+    }
+
+    #[test]
+    fn works_out_headways_from_gtfs_trips() {
+        let trip = |trip_id, direction_id| trip(trip_id, "daily", direction_id);
+        let feed = GtfsFeed {
+            trips: vec![trip("a", 0), trip("b", 0), trip("c", 0), trip("d", 1)],
+            stop_times: vec![
+                stop_time("a", "08:00:00", 0),
+                stop_time("a", "08:30:00", 1),
+                stop_time("b", "08:20:00", 0),
+                stop_time("b", "08:40:00", 1),
+                stop_time("c", "08:40:00", 0),
+                stop_time("c", "09:05:00", 1),
+                stop_time("d", "08:10:00", 0),
+                stop_time("d", "08:30:00", 1),
+            ],
+            ..Default::default()
+        };
+
+        let patterns = ServicePatterns::from_gtfs(&feed);
+        assert_eq!(
+            patterns.lines["R"],
+            [ServiceBand {
+                start: "08:00:00".to_string(),
+                end: "09:00:00".to_string(),
+                headway_mins: 20.0,
+                running_mins: Some(25.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn counts_one_days_trips_as_often_as_they_repeat() {
+        let calendar = |service_id: &str, weekdays: u8, weekends: u8| GtfsCalendar {
+            service_id: service_id.to_string(),
+            monday: weekdays,
+            tuesday: weekdays,
+            wednesday: weekdays,
+            thursday: weekdays,
+            friday: weekdays,
+            saturday: weekends,
+            sunday: weekends,
+            start_date: "20250101".to_string(),
+            end_date: "20251231".to_string(),
+        };
+        let feed = GtfsFeed {
+            trips: vec![trip("weekday", "weekday", 0), trip("weekend", "weekend", 0)],
+            stop_times: vec![
+                stop_time("weekday", "00:00:00", 0),
+                stop_time("weekday", "00:20:00", 1),
+                stop_time("weekend", "08:00:00", 0),
+                stop_time("weekend", "08:20:00", 1),
+            ],
+            calendar: vec![calendar("weekday", 1, 0), calendar("weekend", 0, 1)],
+            frequencies: vec![GtfsFrequency {
+                trip_id: "weekday".to_string(),
+                start_time: "08:00:00".to_string(),
+                end_time: "10:00:00".to_string(),
+                headway_secs: 600,
+                exact_times: 0,
+            }],
+            ..Default::default()
+        };
+
+        // Every 10 minutes on weekdays, leaving out the weekend trip at 08:00
+        let patterns = ServicePatterns::from_gtfs(&feed);
+        let bands: Vec<(&str, f64)> = patterns.lines["R"]
+            .iter()
+            .map(|band| (band.start.as_str(), band.headway_mins))
+            .collect();
+        assert_eq!(bands, [("08:00:00", 10.0), ("09:00:00", 10.0)]);
+        assert_eq!(patterns.lines["R"][0].running_mins, Some(20.0));
+    }
+}
@@ -0,0 +1,190 @@
+//! Dated copies of the datasets, to show the network as it was.
+//!
+//! Each snapshot is a directory `snapshots/<YYYY-MM-DD>/` holding the same
+//! files as the dataset root, listed in `snapshots/index.json`. Bus routes
+//! aren't kept in snapshots, so they are always read from the current data.
+
+use super::error::{DataError, parse_json};
+use super::source::{DataSource, OnProgress};
+use serde::{Deserialize, Serialize};
+
+/// Path of the snapshot index, relative to the dataset root
+pub const SNAPSHOT_INDEX: &str = "snapshots/index.json";
+
+/// A dated snapshot of the datasets
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SnapshotEntry {
+    /// When the data was fetched, `YYYY-MM-DD`, which is also its directory
+    pub date: String,
+    /// What's notable about the network at the time, e.g. a line opening
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// The snapshots available, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SnapshotIndex {
+    pub snapshots: Vec<SnapshotEntry>,
+}
+
+impl SnapshotIndex {
+    /// Add a snapshot, replacing any with the same date
+    pub fn insert(&mut self, entry: SnapshotEntry) {
+        self.snapshots
+            .retain(|existing| existing.date != entry.date);
+        self.snapshots.push(entry);
+        self.snapshots.sort_by(|a, b| a.date.cmp(&b.date));
+    }
+
+    /// The snapshot to show for the network on `date`, or `None` for the
+    /// current data
+    ///
+    /// That's the latest snapshot from on or before the date, unless the
+    /// current data (fetched on `current_date`) is newer. Dates before every
+    /// snapshot get the earliest one, as the closest there is.
+    pub fn choose(&self, date: &str, current_date: Option<&str>) -> Option<&SnapshotEntry> {
+        if current_date.is_some_and(|current| current <= date) {
+            return None;
+        }
+        let before = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|entry| entry.date.as_str() <= date);
+        let chosen = before.or(self.snapshots.first())?;
+        // Unless the current data is older than every snapshot, in which
+        // case it's the closest to an early date
+        match current_date {
+            Some(current) if before.is_none() && chosen.date.as_str() > current => None,
+            _ => Some(chosen),
+        }
+    }
+}
+
+/// Directory of a snapshot, relative to the dataset root
+pub fn snapshot_dir(date: &str) -> String {
+    format!("snapshots/{}", date)
+}
+
+/// Read the snapshot index; a missing index means there are no snapshots
+pub async fn load_snapshot_index(source: &impl DataSource) -> Result<SnapshotIndex, DataError> {
+    match source.read_path(SNAPSHOT_INDEX, &|_, _| {}).await {
+        Ok(text) => {
+            let mut index: SnapshotIndex = parse_json(SNAPSHOT_INDEX, &text)?;
+            index.snapshots.sort_by(|a, b| a.date.cmp(&b.date));
+            Ok(index)
+        }
+        Err(DataError::Fetch { .. } | DataError::HttpStatus { .. }) => Ok(SnapshotIndex::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the datasets of a snapshot from another source, or the current
+/// datasets if there's no snapshot
+#[derive(Debug, Clone)]
+pub struct SnapshotSource<S> {
+    source: S,
+    date: Option<String>,
+}
+
+impl<S: DataSource> SnapshotSource<S> {
+    pub fn new(source: S, date: Option<String>) -> Self {
+        Self { source, date }
+    }
+}
+
+impl<S: DataSource> DataSource for SnapshotSource<S> {
+    fn describe(&self) -> String {
+        match &self.date {
+            Some(date) => format!("{} snapshot of {}", date, self.source.describe()),
+            None => self.source.describe(),
+        }
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        match &self.date {
+            Some(date) if !path.starts_with("bus/") => {
+                let path = format!("{}/{}", snapshot_dir(date), path);
+                self.source.read_bytes(&path, on_progress).await
+            }
+            _ => self.source.read_bytes(path, on_progress).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::line_definitions::line_info_as_of;
+    use crate::data::source::{DataFile, EmbeddedSource};
+    use crate::data::stages::RepositoryLoader;
+
+    #[tokio::test]
+    async fn shows_a_snapshot_with_the_lines_as_they_were() {
+        let routes = |line: &str| {
+            format!(
+                r#"{{"{}":{{"inbound":{{"success":true,"results":[]}}}}}}"#,
+                line
+            )
+        };
+        let empty = br#"{"success":true,"results":[]}"#;
+        let date = "2024-06-01";
+        let snapshot_path = |file: DataFile| format!("{}/{}", snapshot_dir(date), file.file_name());
+        let source = EmbeddedSource::new()
+            .with_file(DataFile::Stations, empty)
+            .with_file(DataFile::Platforms, empty)
+            .with_file(DataFile::RailRoutes, routes("mildmay").into_bytes())
+            .with_path(&snapshot_path(DataFile::Stations), empty)
+            .with_path(&snapshot_path(DataFile::Platforms), empty)
+            .with_path(
+                &snapshot_path(DataFile::RailRoutes),
+                routes("london-overground").into_bytes(),
+            );
+
+        let mut loader = RepositoryLoader::new(false)
+            .with_snapshot(Some(date.to_string()), Some(date.to_string()));
+        let snapshot = SnapshotSource::new(source, Some(date.to_string()));
+        loader.run(&snapshot, &|_| {}).await.unwrap();
+        let repository = loader.build().unwrap();
+
+        // The routes are the snapshot's, not the current data's
+        assert!(repository.routes.contains_key("london-overground"));
+        assert!(!repository.routes.contains_key("mildmay"));
+        assert_eq!(repository.snapshot.as_deref(), Some(date));
+        assert_eq!(repository.line_color("london-overground"), "#EE7C0E");
+
+        // Lines named since are shown as they were then
+        let as_of = repository.as_of.as_deref();
+        let mildmay = line_info_as_of("mildmay", as_of).unwrap();
+        assert_eq!(
+            (mildmay.name, mildmay.color),
+            ("London Overground", "#EE7C0E")
+        );
+        let mildmay = line_info_as_of("mildmay", None).unwrap();
+        assert_eq!((mildmay.name, mildmay.color), ("Mildmay Line", "#088ECC"));
+    }
+
+    #[test]
+    fn chooses_the_closest_snapshot_before_a_date() {
+        let mut index = SnapshotIndex::default();
+        for date in ["2023-01-10", "2021-06-01"] {
+            index.insert(SnapshotEntry {
+                date: date.to_string(),
+                label: None,
+            });
+        }
+        let current = Some("2025-04-04");
+        let chosen = |date| index.choose(date, current).map(|entry| entry.date.as_str());
+
+        assert_eq!(chosen("2022-05-23"), Some("2021-06-01"));
+        assert_eq!(chosen("2023-01-10"), Some("2023-01-10"));
+        assert_eq!(chosen("2024-12-31"), Some("2023-01-10"));
+        assert_eq!(chosen("2025-04-04"), None);
+        assert_eq!(chosen("1999-01-01"), Some("2021-06-01"));
+        assert_eq!(SnapshotIndex::default().choose("2022-01-01", current), None);
+    }
+}
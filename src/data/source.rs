@@ -0,0 +1,497 @@
+//! Where the raw TfL datasets are read from.
+//!
+//! Fetching over HTTP only works in the browser, so the [`AssetSource`] and
+//! [`UrlSource`] are only built for `wasm32`; native builds read the same
+//! files from disk with a [`FileSystemSource`], or from a [`ZipSource`].
+
+use super::error::DataError;
+use std::borrow::Cow;
+use std::collections::HashMap;
+#[cfg(target_arch = "wasm32")]
+use {
+    crate::utils::log::{self, LogCategory},
+    dioxus::prelude::*,
+    js_sys::{Reflect, Uint8Array},
+    wasm_bindgen::{JsCast, JsValue},
+    web_sys::{ReadableStreamDefaultReader, Response},
+};
+
+// Define asset paths for our data files
+#[cfg(target_arch = "wasm32")]
+const STATIONS_JSON_PATH: Asset = asset!("/assets/data/stations.json");
+#[cfg(target_arch = "wasm32")]
+const PLATFORMS_JSON_PATH: Asset = asset!("/assets/data/platforms.json");
+#[cfg(target_arch = "wasm32")]
+const RAIL_ROUTES_JSON_PATH: Asset = asset!("/assets/data/rail_routes.json");
+#[cfg(target_arch = "wasm32")]
+const SERVICES_JSON_PATH: Asset = asset!("/assets/data/services.json");
+// The per-route bus files are fetched on demand, so the folder is bundled whole
+#[cfg(target_arch = "wasm32")]
+const BUS_ROUTES_DIR: Asset = asset!("/assets/data/bus", FolderAssetOptions::new());
+// The compact files are optional, so bundle the folder rather than each file
+#[cfg(target_arch = "wasm32")]
+const COMPACT_DIR: Asset = asset!("/assets/data/compact", FolderAssetOptions::new());
+// Dated snapshots are only read if one is picked
+#[cfg(target_arch = "wasm32")]
+const SNAPSHOTS_DIR: Asset = asset!("/assets/data/snapshots", FolderAssetOptions::new());
+
+/// The dataset files that make up the TfL data repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataFile {
+    Stations,
+    Platforms,
+    RailRoutes,
+    /// Index of the per-route bus files, which are read one at a time
+    BusManifest,
+}
+
+impl DataFile {
+    /// Every dataset file, in the order they are loaded
+    pub const ALL: [DataFile; 4] = [
+        DataFile::Stations,
+        DataFile::Platforms,
+        DataFile::RailRoutes,
+        DataFile::BusManifest,
+    ];
+
+    /// Path of the dataset, relative to `assets/data`
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            DataFile::Stations => "stations.json",
+            DataFile::Platforms => "platforms.json",
+            DataFile::RailRoutes => "rail_routes.json",
+            DataFile::BusManifest => "bus/manifest.json",
+        }
+    }
+
+    /// Path of the dataset in the compact binary format, if it has one
+    ///
+    /// See [`super::compact`]; the JSON file is read if this one is missing.
+    pub fn compact_path(&self) -> Option<&'static str> {
+        match self {
+            DataFile::Stations => Some("compact/stations.bin"),
+            DataFile::Platforms => Some("compact/platforms.bin"),
+            DataFile::RailRoutes => Some("compact/rail_routes.bin"),
+            DataFile::BusManifest => None,
+        }
+    }
+}
+
+/// Called as a file is read, with the bytes read so far and the total size if known
+pub type OnProgress<'a> = &'a dyn Fn(u64, Option<u64>);
+
+/// Somewhere the raw dataset files can be read from
+///
+/// The loaders in [`super::loader`] only deal with parsing, so the same
+/// repository can be built from the bundled assets, a remote API, bytes
+/// compiled into the binary or a directory on disk.
+// Sources are awaited where they were created (futures in the browser aren't
+// `Send` anyway), so callers don't need `Send` bounds on the futures
+#[allow(async_fn_in_trait)]
+pub trait DataSource {
+    /// Short description of where the data comes from, for log messages
+    fn describe(&self) -> String;
+
+    /// Read the whole of a file, reporting progress as it goes
+    ///
+    /// The path is relative to the dataset root, e.g. `bus/24.json`.
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError>;
+
+    /// Read the whole of a file as text, reporting progress as it goes
+    async fn read_path(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<String, DataError> {
+        let bytes = self.read_bytes(path, on_progress).await?;
+        String::from_utf8(bytes).map_err(|e| DataError::Fetch {
+            file: path.to_string(),
+            message: format!("file is not valid UTF-8: {}", e),
+        })
+    }
+
+    /// Read the whole of a dataset file as text, reporting progress as it goes
+    async fn read_text(
+        &self,
+        file: DataFile,
+        on_progress: OnProgress<'_>,
+    ) -> Result<String, DataError> {
+        self.read_path(file.file_name(), on_progress).await
+    }
+}
+
+/// The JSON files bundled with the app via `asset!`, fetched at runtime
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AssetSource;
+
+#[cfg(target_arch = "wasm32")]
+impl AssetSource {
+    /// URL of a bundled file, if there is one at that path
+    fn url_for(path: &str) -> Option<String> {
+        let asset = match path {
+            "stations.json" => STATIONS_JSON_PATH,
+            "platforms.json" => PLATFORMS_JSON_PATH,
+            "rail_routes.json" => RAIL_ROUTES_JSON_PATH,
+            super::services::SERVICES_PATH => SERVICES_JSON_PATH,
+            _ => {
+                let (dir, name) = match path.split_once('/')? {
+                    ("bus", name) => (BUS_ROUTES_DIR, name),
+                    ("compact", name) => (COMPACT_DIR, name),
+                    ("snapshots", name) => (SNAPSHOTS_DIR, name),
+                    _ => return None,
+                };
+                let dir = dir.resolve();
+                return Some(format!("{}/{}", dir.to_str()?.trim_end_matches('/'), name));
+            }
+        };
+        asset.resolve().to_str().map(str::to_string)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl DataSource for AssetSource {
+    fn describe(&self) -> String {
+        "bundled assets".to_string()
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        let url = Self::url_for(path).ok_or_else(|| DataError::Fetch {
+            file: path.to_string(),
+            message: "no bundled asset at this path".to_string(),
+        })?;
+        fetch_bytes(path, &url, on_progress).await
+    }
+}
+
+/// Dataset files served under a base URL, e.g. an alternate dataset or a
+/// local stand-in for the API
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone)]
+pub struct UrlSource {
+    base_url: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl UrlSource {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+
+    fn url_for(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl DataSource for UrlSource {
+    fn describe(&self) -> String {
+        format!("URL {}", self.base_url)
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        fetch_bytes(path, &self.url_for(path), on_progress).await
+    }
+}
+
+/// Dataset files held in memory, e.g. compiled in with `include_bytes!`
+///
+/// For tests only: [`AnySource`] has no variant for it, so the app never
+/// reads from one.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedSource {
+    files: HashMap<String, Cow<'static, [u8]>>,
+}
+
+impl EmbeddedSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the contents of a dataset file
+    pub fn with_file(self, file: DataFile, bytes: impl Into<Cow<'static, [u8]>>) -> Self {
+        self.with_path(file.file_name(), bytes)
+    }
+
+    /// Add the contents of any file, by its path relative to the dataset root
+    pub fn with_path(mut self, path: &str, bytes: impl Into<Cow<'static, [u8]>>) -> Self {
+        self.files.insert(path.to_string(), bytes.into());
+        self
+    }
+}
+
+impl DataSource for EmbeddedSource {
+    fn describe(&self) -> String {
+        format!("{} embedded files", self.files.len())
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        let bytes = self.files.get(path).ok_or_else(|| DataError::Fetch {
+            file: path.to_string(),
+            message: "no embedded data for this file".to_string(),
+        })?;
+        on_progress(bytes.len() as u64, Some(bytes.len() as u64));
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Dataset files in a directory on the local filesystem (native builds,
+/// such as the `desktop` feature)
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileSystemSource {
+    root: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileSystemSource {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The `assets/data` directory of this crate, unless overridden by the
+    /// `TFL_DATA_DIR` environment variable
+    pub fn bundled() -> Self {
+        match std::env::var_os("TFL_DATA_DIR") {
+            Some(dir) => Self::new(dir),
+            None => Self::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/data")),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DataSource for FileSystemSource {
+    fn describe(&self) -> String {
+        format!("directory {}", self.root.display())
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        let full_path = self.root.join(path);
+        let bytes = std::fs::read(&full_path).map_err(|e| DataError::Fetch {
+            file: path.to_string(),
+            message: format!("failed to read {}: {}", full_path.display(), e),
+        })?;
+        on_progress(bytes.len() as u64, Some(bytes.len() as u64));
+        Ok(bytes)
+    }
+}
+
+/// Files in a zip archive on the local filesystem, e.g. a GTFS feed as
+/// published
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct ZipSource {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ZipSource {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DataSource for ZipSource {
+    fn describe(&self) -> String {
+        format!("zip {}", self.path.display())
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        use std::io::Read;
+
+        let fetch_error = |message: String| DataError::Fetch {
+            file: path.to_string(),
+            message,
+        };
+        let file = std::fs::File::open(&self.path)
+            .map_err(|e| fetch_error(format!("failed to open {}: {}", self.path.display(), e)))?;
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| {
+            fetch_error(format!("{} is not a valid zip: {}", self.path.display(), e))
+        })?;
+
+        // Some archives put everything in a folder, so look for the file anywhere
+        let name = match archive.index_for_name(path) {
+            Some(index) => index,
+            None => archive
+                .file_names()
+                .position(|name| name.rsplit_once('/').is_some_and(|(_, file)| file == path))
+                .ok_or_else(|| fetch_error(format!("not in {}", self.path.display())))?,
+        };
+        let mut entry = archive
+            .by_index(name)
+            .map_err(|e| fetch_error(format!("failed to read from the zip: {}", e)))?;
+
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| fetch_error(format!("failed to read from the zip: {}", e)))?;
+        on_progress(bytes.len() as u64, Some(bytes.len() as u64));
+        Ok(bytes)
+    }
+}
+
+/// Any of the above, for when the source is chosen at runtime
+#[derive(Debug, Clone)]
+pub enum AnySource {
+    #[cfg(target_arch = "wasm32")]
+    Asset(AssetSource),
+    #[cfg(target_arch = "wasm32")]
+    Url(UrlSource),
+    #[cfg(not(target_arch = "wasm32"))]
+    FileSystem(FileSystemSource),
+    #[cfg(not(target_arch = "wasm32"))]
+    Zip(ZipSource),
+}
+
+impl Default for AnySource {
+    /// The bundled dataset: fetched assets in the browser, files on disk natively
+    fn default() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        return AnySource::Asset(AssetSource);
+        #[cfg(not(target_arch = "wasm32"))]
+        return AnySource::FileSystem(FileSystemSource::bundled());
+    }
+}
+
+impl DataSource for AnySource {
+    fn describe(&self) -> String {
+        match self {
+            #[cfg(target_arch = "wasm32")]
+            AnySource::Asset(source) => source.describe(),
+            #[cfg(target_arch = "wasm32")]
+            AnySource::Url(source) => source.describe(),
+            #[cfg(not(target_arch = "wasm32"))]
+            AnySource::FileSystem(source) => source.describe(),
+            #[cfg(not(target_arch = "wasm32"))]
+            AnySource::Zip(source) => source.describe(),
+        }
+    }
+
+    async fn read_bytes(
+        &self,
+        path: &str,
+        on_progress: OnProgress<'_>,
+    ) -> Result<Vec<u8>, DataError> {
+        match self {
+            #[cfg(target_arch = "wasm32")]
+            AnySource::Asset(source) => source.read_bytes(path, on_progress).await,
+            #[cfg(target_arch = "wasm32")]
+            AnySource::Url(source) => source.read_bytes(path, on_progress).await,
+            #[cfg(not(target_arch = "wasm32"))]
+            AnySource::FileSystem(source) => source.read_bytes(path, on_progress).await,
+            #[cfg(not(target_arch = "wasm32"))]
+            AnySource::Zip(source) => source.read_bytes(path, on_progress).await,
+        }
+    }
+}
+
+/// Fetch a URL and return the response body
+///
+/// The body is streamed so progress can be reported chunk by chunk.
+#[cfg(target_arch = "wasm32")]
+async fn fetch_bytes(
+    file: &str,
+    url: &str,
+    on_progress: OnProgress<'_>,
+) -> Result<Vec<u8>, DataError> {
+    log::debug_with_category(LogCategory::App, &format!("Fetching {}", url));
+
+    let fetch_error = |message: String| DataError::Fetch {
+        file: file.to_string(),
+        message,
+    };
+
+    let window =
+        web_sys::window().ok_or_else(|| fetch_error("no window object available".to_string()))?;
+    let promise = window.fetch_with_str(url);
+
+    // Convert the Promise<Response> to a Future<Result<Response, JsValue>>
+    let response_future = wasm_bindgen_futures::JsFuture::from(promise);
+
+    // Await the response
+    let response_value = match response_future.await {
+        Ok(val) => val,
+        Err(e) => return Err(fetch_error(format!("failed to fetch {}: {:?}", url, e))),
+    };
+
+    let response: Response = response_value
+        .dyn_into()
+        .map_err(|_| fetch_error("failed to convert response".to_string()))?;
+
+    if !response.ok() {
+        return Err(DataError::HttpStatus {
+            file: file.to_string(),
+            status: response.status(),
+        });
+    }
+
+    // Content-Length is the compressed size if the server gzips the file, so
+    // it is only a hint and may be smaller than the bytes we end up reading
+    let total = response
+        .headers()
+        .get("Content-Length")
+        .ok()
+        .flatten()
+        .and_then(|length| length.parse::<u64>().ok());
+
+    let Some(body) = response.body() else {
+        return Ok(Vec::new());
+    };
+    let reader: ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+
+    // Read the body chunk by chunk
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = wasm_bindgen_futures::JsFuture::from(reader.read())
+            .await
+            .map_err(|e| fetch_error(format!("failed to read response body: {:?}", e)))?;
+
+        // A chunk that doesn't say whether the stream is done would otherwise
+        // end the body early, to fail later as bad JSON
+        let done = Reflect::get(&chunk, &JsValue::from_str("done"))
+            .ok()
+            .and_then(|done| done.as_bool())
+            .ok_or_else(|| fetch_error("response body chunk has no done flag".to_string()))?;
+        if done {
+            break;
+        }
+
+        let value = Reflect::get(&chunk, &JsValue::from_str("value"))
+            .map_err(|e| fetch_error(format!("failed to read response body: {:?}", e)))?;
+        let array = Uint8Array::new(&value);
+        let start = bytes.len();
+        bytes.resize(start + array.length() as usize, 0);
+        array.copy_to(&mut bytes[start..]);
+
+        on_progress(bytes.len() as u64, total);
+    }
+
+    Ok(bytes)
+}
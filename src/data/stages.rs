@@ -0,0 +1,463 @@
+//! Loading the repository one dataset at a time.
+//!
+//! Each dataset file is a stage. Stages report their progress as they go, and
+//! the results of finished stages are kept, so after a failure only the stage
+//! that failed needs to be run again.
+
+use super::TflDataRepository;
+use super::error::DataError;
+use super::loader;
+use super::model::{BusManifest, PlatformsResponse, RoutesFile, StationsResponse};
+use super::provenance::Provenance;
+use super::source::{DataFile, DataSource};
+use super::validate::{self, ValidationReport};
+use crate::utils::log::{self, LogCategory};
+
+/// Something that happened while loading a stage
+#[derive(Debug, Clone)]
+pub enum StageEvent {
+    Started(DataFile),
+    Progress {
+        file: DataFile,
+        bytes: u64,
+        total: Option<u64>,
+    },
+    Finished(DataFile),
+    Failed(DataFile, DataError),
+    Skipped(DataFile),
+}
+
+/// Where a stage has got to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum StageStatus {
+    #[default]
+    Pending,
+    Loading {
+        bytes: u64,
+        total: Option<u64>,
+    },
+    Done {
+        bytes: u64,
+    },
+    Failed(DataError),
+    /// Not wanted this time, e.g. bus routes when buses are switched off
+    Skipped,
+}
+
+/// Status of every stage, for showing to the user
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadProgress {
+    pub stations: StageStatus,
+    pub platforms: StageStatus,
+    pub rail_routes: StageStatus,
+    pub bus_manifest: StageStatus,
+}
+
+impl LoadProgress {
+    pub fn status(&self, file: DataFile) -> &StageStatus {
+        match file {
+            DataFile::Stations => &self.stations,
+            DataFile::Platforms => &self.platforms,
+            DataFile::RailRoutes => &self.rail_routes,
+            DataFile::BusManifest => &self.bus_manifest,
+        }
+    }
+
+    fn status_mut(&mut self, file: DataFile) -> &mut StageStatus {
+        match file {
+            DataFile::Stations => &mut self.stations,
+            DataFile::Platforms => &mut self.platforms,
+            DataFile::RailRoutes => &mut self.rail_routes,
+            DataFile::BusManifest => &mut self.bus_manifest,
+        }
+    }
+
+    /// Update the status of a stage from an event
+    pub fn apply(&mut self, event: StageEvent) {
+        match event {
+            StageEvent::Started(file) => {
+                *self.status_mut(file) = StageStatus::Loading {
+                    bytes: 0,
+                    total: None,
+                }
+            }
+            StageEvent::Progress { file, bytes, total } => {
+                *self.status_mut(file) = StageStatus::Loading { bytes, total }
+            }
+            StageEvent::Finished(file) => {
+                let bytes = match self.status(file) {
+                    StageStatus::Loading { bytes, .. } => *bytes,
+                    _ => 0,
+                };
+                *self.status_mut(file) = StageStatus::Done { bytes };
+            }
+            StageEvent::Failed(file, error) => *self.status_mut(file) = StageStatus::Failed(error),
+            StageEvent::Skipped(file) => *self.status_mut(file) = StageStatus::Skipped,
+        }
+    }
+
+    /// Whether any stage is still being loaded
+    pub fn is_loading(&self) -> bool {
+        DataFile::ALL
+            .iter()
+            .any(|file| matches!(self.status(*file), StageStatus::Loading { .. }))
+    }
+
+    /// The first stage that failed, with its error
+    pub fn failure(&self) -> Option<(DataFile, &DataError)> {
+        DataFile::ALL
+            .iter()
+            .find_map(|file| match self.status(*file) {
+                StageStatus::Failed(error) => Some((*file, error)),
+                _ => None,
+            })
+    }
+}
+
+/// The datasets loaded so far
+#[derive(Default)]
+pub struct RepositoryLoader {
+    stations: Option<StationsResponse>,
+    platforms: Option<PlatformsResponse>,
+    rail_routes: Option<RoutesFile>,
+    bus_manifest: Option<BusManifest>,
+    load_buses: bool,
+    /// Stages that failed but that the repository can be built without
+    skipped: Vec<DataFile>,
+    /// The dated snapshot being loaded, see [`super::snapshots`]
+    snapshot: Option<String>,
+    /// The date the lines are shown as of, see
+    /// [`line_info_as_of`](super::line_definitions::line_info_as_of)
+    as_of: Option<String>,
+    /// Whether this stands in for a loader taken away to run, see
+    /// [`Self::start_run`]
+    running: bool,
+}
+
+impl RepositoryLoader {
+    pub fn new(load_buses: bool) -> Self {
+        Self {
+            load_buses,
+            ..Self::default()
+        }
+    }
+
+    /// Load a dated snapshot rather than the current datasets, showing the
+    /// lines as they were on `as_of`
+    pub fn with_snapshot(mut self, snapshot: Option<String>, as_of: Option<String>) -> Self {
+        self.snapshot = snapshot;
+        self.as_of = as_of;
+        self
+    }
+
+    /// The dated snapshot being loaded, if any
+    pub fn snapshot(&self) -> Option<&str> {
+        self.snapshot.as_deref()
+    }
+
+    /// Show the lines as they were on another date, without reloading
+    pub fn set_as_of(&mut self, as_of: Option<String>) {
+        self.as_of = as_of;
+    }
+
+    fn is_loaded(&self, file: DataFile) -> bool {
+        match file {
+            DataFile::Stations => self.stations.is_some(),
+            DataFile::Platforms => self.platforms.is_some(),
+            DataFile::RailRoutes => self.rail_routes.is_some(),
+            DataFile::BusManifest => self.bus_manifest.is_some(),
+        }
+    }
+
+    /// The stages still to be loaded, in order
+    pub fn remaining_stages(&self) -> Vec<DataFile> {
+        DataFile::ALL
+            .into_iter()
+            .filter(|file| *file != DataFile::BusManifest || self.load_buses)
+            .filter(|file| !self.is_loaded(*file) && !self.skipped.contains(file))
+            .collect()
+    }
+
+    /// Whether the bus routes are wanted
+    pub fn load_buses(&self) -> bool {
+        self.load_buses
+    }
+
+    /// Choose whether the bus routes are wanted
+    ///
+    /// Once loaded, the bus manifest is kept even when not wanted, so switching
+    /// buses back on doesn't read it again. The routes themselves are read
+    /// one at a time, see [`super::bus`].
+    pub fn set_load_buses(&mut self, load_buses: bool) {
+        self.load_buses = load_buses;
+    }
+
+    /// Take the loader away to run across awaits, leaving a stand-in in its
+    /// place until [`Self::finish_run`], or `None` if a run is already under
+    /// way
+    ///
+    /// Whether buses are wanted can still be chosen on the stand-in, and
+    /// applies once the run finishes.
+    pub fn start_run(&mut self) -> Option<Self> {
+        if self.running {
+            return None;
+        }
+        let stand_in = Self {
+            load_buses: self.load_buses,
+            snapshot: self.snapshot.clone(),
+            as_of: self.as_of.clone(),
+            running: true,
+            ..Self::default()
+        };
+        Some(std::mem::replace(self, stand_in))
+    }
+
+    /// Put back a loader taken by [`Self::start_run`] after its run, with
+    /// whether buses are wanted as chosen meanwhile
+    ///
+    /// Returns whether that choice changed during the run, so another run is
+    /// needed to apply it.
+    pub fn finish_run(&mut self, mut loader: Self) -> bool {
+        let changed = loader.load_buses != self.load_buses;
+        loader.load_buses = self.load_buses;
+        loader.as_of = self.as_of.take();
+        *self = loader;
+        changed
+    }
+
+    /// Forget which optional stages failed, so the next run tries them again
+    pub fn retry_skipped(&mut self) {
+        self.skipped.clear();
+    }
+
+    /// Load a single stage, keeping the result
+    async fn load_stage(
+        &mut self,
+        source: &impl DataSource,
+        file: DataFile,
+        on_event: &dyn Fn(StageEvent),
+    ) -> Result<(), DataError> {
+        let on_progress = |bytes, total| on_event(StageEvent::Progress { file, bytes, total });
+
+        match file {
+            DataFile::Stations => {
+                self.stations = Some(loader::load_stations(source, &on_progress).await?)
+            }
+            DataFile::Platforms => {
+                self.platforms = Some(loader::load_platforms(source, &on_progress).await?)
+            }
+            DataFile::RailRoutes => {
+                self.rail_routes = Some(loader::load_routes_file(source, file, &on_progress).await?)
+            }
+            DataFile::BusManifest => {
+                self.bus_manifest = Some(loader::load_bus_manifest(source, &on_progress).await?)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load every remaining stage, stopping at the first one that fails
+    ///
+    /// Bus routes are optional: if they fail, the failure is reported and the
+    /// remaining stages carry on without them.
+    pub async fn run(
+        &mut self,
+        source: &impl DataSource,
+        on_event: &dyn Fn(StageEvent),
+    ) -> Result<(), DataError> {
+        if !self.load_buses {
+            on_event(StageEvent::Skipped(DataFile::BusManifest));
+        }
+
+        for file in self.remaining_stages() {
+            on_event(StageEvent::Started(file));
+
+            match self.load_stage(source, file, on_event).await {
+                Ok(()) => on_event(StageEvent::Finished(file)),
+                Err(e) if file == DataFile::BusManifest => {
+                    log::warn_with_category(
+                        LogCategory::App,
+                        &format!("Failed to load bus routes, continuing without them: {}", e),
+                    );
+                    self.skipped.push(file);
+                    on_event(StageEvent::Failed(file, e));
+                }
+                Err(e) => {
+                    on_event(StageEvent::Failed(file, e.clone()));
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the repository from the stages loaded so far
+    ///
+    /// Returns `None` until all of the required stages have loaded.
+    pub fn build(&self) -> Option<TflDataRepository> {
+        let stations = self.stations.as_ref()?;
+        let platforms = self.platforms.as_ref()?;
+        let rail_routes = self.rail_routes.as_ref()?;
+
+        let mut repository = TflDataRepository::from_datasets(
+            stations.results.clone(),
+            platforms.results.clone(),
+            loader::merge_routes([(DataFile::RailRoutes.file_name(), rail_routes)]),
+        );
+        repository.provenance = vec![
+            Provenance::from_context(
+                DataFile::Stations,
+                stations.context.as_ref(),
+                stations.results.len(),
+            ),
+            Provenance::from_context(
+                DataFile::Platforms,
+                platforms.context.as_ref(),
+                platforms.results.len(),
+            ),
+            Provenance::from_routes(DataFile::RailRoutes, rail_routes),
+        ];
+        repository.snapshot = self.snapshot.clone();
+        repository.as_of = self.as_of.clone();
+        self.sync(&mut repository);
+        Some(repository)
+    }
+
+    /// Check the datasets loaded so far, see [`validate::validate`]
+    ///
+    /// Returns `None` until all of the required stages have loaded.
+    pub fn validate(&self, max_station_distance: f64) -> Option<ValidationReport> {
+        Some(validate::validate(
+            &self.stations.as_ref()?.results,
+            &self.platforms.as_ref()?.results,
+            self.rail_routes.as_ref()?,
+            max_station_distance,
+        ))
+    }
+
+    /// Bring a repository in line with the datasets loaded and wanted
+    ///
+    /// Only the difference is applied: buses are switched on or off in an
+    /// already loaded repository, which is only built from scratch if it
+    /// hasn't been loaded yet.
+    pub fn sync(&self, repository: &mut TflDataRepository) {
+        if !repository.is_loaded {
+            if let Some(built) = self.build() {
+                *repository = built;
+            }
+            return;
+        }
+
+        match (&self.bus_manifest, self.load_buses, repository.has_buses()) {
+            (Some(manifest), true, false) => repository.load_buses(manifest),
+            (_, false, true) => repository.unload_buses(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::source::EmbeddedSource;
+
+    #[test]
+    fn runs_one_at_a_time_keeping_the_choices_made_meanwhile() {
+        let mut loader = RepositoryLoader::new(false);
+        let run = loader.start_run().expect("nothing is running yet");
+        assert!(loader.start_run().is_none());
+
+        // Buses switched on during the run are wanted once it's back, with
+        // another run to load them
+        loader.set_load_buses(true);
+        assert!(loader.finish_run(run));
+        assert!(loader.load_buses());
+        assert_eq!(loader.remaining_stages().len(), 4);
+
+        let run = loader.start_run().expect("the last run finished");
+        assert!(!loader.finish_run(run));
+    }
+
+    #[tokio::test]
+    async fn retry_resumes_at_the_failed_stage() {
+        let stations = EmbeddedSource::new()
+            .with_file(DataFile::Stations, br#"{"success":true,"results":[]}"#);
+        let mut loader = RepositoryLoader::new(false);
+
+        let err = loader.run(&stations, &|_| {}).await.unwrap_err();
+        assert!(matches!(err, DataError::Fetch { file, .. } if file == "platforms.json"));
+        assert_eq!(
+            loader.remaining_stages(),
+            vec![DataFile::Platforms, DataFile::RailRoutes]
+        );
+        assert!(loader.build().is_none());
+
+        // Stations are not read again, so the retry doesn't need them
+        let rest = EmbeddedSource::new()
+            .with_file(DataFile::Platforms, br#"{"success":true,"results":[]}"#)
+            .with_file(DataFile::RailRoutes, b"{}");
+        loader.run(&rest, &|_| {}).await.unwrap();
+        assert!(loader.remaining_stages().is_empty());
+        assert!(loader.build().unwrap().is_loaded);
+    }
+
+    #[tokio::test]
+    async fn toggling_buses_only_reads_them_once() {
+        let routes = |line: &str| {
+            format!(
+                r#"{{"{}":{{"inbound":{{"success":true,"results":[]}}}}}}"#,
+                line
+            )
+        };
+        let source = EmbeddedSource::new()
+            .with_file(DataFile::Stations, br#"{"success":true,"results":[]}"#)
+            .with_file(DataFile::Platforms, br#"{"success":true,"results":[]}"#)
+            .with_file(DataFile::RailRoutes, routes("victoria").into_bytes())
+            .with_file(
+                DataFile::BusManifest,
+                br#"{"routes":[{"id":"24","bbox":[-0.15,51.49,-0.13,51.56]}]}"#,
+            )
+            .with_path("bus/24.json", routes("24").into_bytes());
+
+        let mut loader = RepositoryLoader::new(false);
+        loader.run(&source, &|_| {}).await.unwrap();
+        let mut repository = loader.build().unwrap();
+        assert!(!repository.has_buses());
+
+        loader.set_load_buses(true);
+        assert_eq!(loader.remaining_stages(), vec![DataFile::BusManifest]);
+        loader.run(&source, &|_| {}).await.unwrap();
+        loader.sync(&mut repository);
+        assert!(repository.has_buses());
+
+        // Routes are only read once they're in view
+        assert!(
+            repository
+                .bus_routes_to_load([0.0, 51.0, 0.1, 51.1])
+                .is_empty()
+        );
+        let to_load = repository.bus_routes_to_load([-0.2, 51.5, -0.1, 51.52]);
+        assert_eq!(to_load, vec!["24".to_string()]);
+        let route = loader::load_bus_route(&source, "24").await.unwrap();
+        repository.add_bus_route("24", &route);
+        assert!(repository.routes.contains_key("24"));
+        assert!(
+            repository
+                .bus_routes_to_load([-0.2, 51.5, -0.1, 51.52])
+                .is_empty()
+        );
+
+        loader.set_load_buses(false);
+        loader.sync(&mut repository);
+        assert!(!repository.routes.contains_key("24"));
+        assert!(repository.routes.contains_key("victoria"));
+
+        // The manifest is kept, so switching back on has nothing to read
+        loader.set_load_buses(true);
+        assert!(loader.remaining_stages().is_empty());
+        loader.sync(&mut repository);
+        assert!(repository.has_buses());
+    }
+}
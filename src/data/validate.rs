@@ -0,0 +1,446 @@
+//! Integrity checks over the datasets.
+//!
+//! Each data refresh can break things that only show up on the map, such as a
+//! line renamed in the API so it loses its colour, or platforms pointing at a
+//! station that no longer exists. [`validate`] reports these, and is run by
+//! `tfl_data validate` and on the datasets the app loads.
+
+use super::line_definitions::{LINE_INFOS, is_known_line, registry_ids_for};
+use super::loader::{filter_valid_stations, parse_line_string};
+use super::model::{Platform, RoutesFile, Station};
+use crate::utils::geo::haversine_distance;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Default distance in metres beyond which a route vertex is reported as far
+/// from any station
+///
+/// Generous, as lines run several kilometres between stations out of town.
+pub const DEFAULT_MAX_STATION_DISTANCE: f64 = 5_000.0;
+
+/// How much an issue matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth a look, but the app copes
+    Warning,
+    /// Data the app will show wrongly or not at all
+    Error,
+}
+
+/// Something wrong with the datasets
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// A line with routes but no entry in [`LINE_INFOS`] or
+    /// [`LINE_ALIASES`](super::line_definitions::LINE_ALIASES), so it has no name
+    /// or colour
+    UnknownLine { line_id: String },
+    /// A line in [`LINE_INFOS`] without any routes, under its ID or an alias
+    LineWithoutRoutes { line_id: String },
+    /// A platform whose station isn't in the stations
+    OrphanPlatform {
+        platform_id: String,
+        station_id: String,
+    },
+    /// A station that no platform belongs to
+    StationWithoutPlatforms { station_id: String, name: String },
+    /// A station left out of the map for having no usable coordinates
+    DroppedStation {
+        station_id: String,
+        name: String,
+        lat: f64,
+        lon: f64,
+    },
+    /// Route vertices further than the limit from every station
+    FarFromStations {
+        line_id: String,
+        vertices: usize,
+        furthest: [f64; 2],
+        distance: f64,
+    },
+    /// An ID used by more than one record of a dataset
+    DuplicateId {
+        dataset: &'static str,
+        id: String,
+        count: usize,
+    },
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::UnknownLine { .. }
+            | Issue::OrphanPlatform { .. }
+            | Issue::DuplicateId { .. } => Severity::Error,
+            Issue::LineWithoutRoutes { .. }
+            | Issue::StationWithoutPlatforms { .. }
+            | Issue::DroppedStation { .. }
+            | Issue::FarFromStations { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::UnknownLine { line_id } => {
+                write!(f, "line {} has routes but isn't in LINE_INFOS", line_id)
+            }
+            Issue::LineWithoutRoutes { line_id } => {
+                write!(f, "line {} is in LINE_INFOS but has no routes", line_id)
+            }
+            Issue::OrphanPlatform {
+                platform_id,
+                station_id,
+            } => write!(
+                f,
+                "platform {} belongs to unknown station {}",
+                platform_id, station_id
+            ),
+            Issue::StationWithoutPlatforms { station_id, name } => {
+                write!(f, "station {} ({}) has no platforms", station_id, name)
+            }
+            Issue::DroppedStation {
+                station_id,
+                name,
+                lat,
+                lon,
+            } => write!(
+                f,
+                "station {} ({}) is left off the map for its coordinates ({}, {})",
+                station_id, name, lat, lon
+            ),
+            Issue::FarFromStations {
+                line_id,
+                vertices,
+                furthest: [lon, lat],
+                distance,
+            } => write!(
+                f,
+                "line {} has {} route points far from any station, the furthest {:.0} m away at ({}, {})",
+                line_id, vertices, distance, lon, lat
+            ),
+            Issue::DuplicateId { dataset, id, count } => {
+                write!(f, "{} ID {} is used {} times", dataset, id, count)
+            }
+        }
+    }
+}
+
+/// The issues found by [`validate`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// How many issues there are of a severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    /// A one-line summary, e.g. "2 errors, 5 warnings"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} errors, {} warnings",
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        )
+    }
+}
+
+/// Stations bucketed into square cells of a grid, for finding the nearest
+/// station to a point without checking them all
+struct StationGrid {
+    /// Cell size in degrees, the same on both axes
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<[f64; 2]>>,
+}
+
+impl StationGrid {
+    fn new(stations: &[Station], max_distance: f64) -> Self {
+        // A degree of latitude is about 111 km; a degree of longitude is less
+        let cell = (max_distance / 111_000.0).max(1e-4);
+        let mut cells: HashMap<_, Vec<_>> = HashMap::new();
+        for station in stations {
+            let point = [station.lon, station.lat];
+            cells.entry(Self::key(cell, point)).or_default().push(point);
+        }
+        Self { cell, cells }
+    }
+
+    fn key(cell: f64, [lon, lat]: [f64; 2]) -> (i64, i64) {
+        ((lon / cell).floor() as i64, (lat / cell).floor() as i64)
+    }
+
+    /// Whether any station is within `max_distance` metres of a point
+    fn has_station_within(&self, point: [f64; 2], max_distance: f64) -> bool {
+        let (x, y) = Self::key(self.cell, point);
+        // Cells are narrower than they are tall away from the equator, so
+        // look further along the longitude axis
+        let reach = (1.0 / point[1].to_radians().cos().max(0.1)).ceil() as i64;
+
+        (x - reach..=x + reach)
+            .flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y)))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .any(|station| haversine_distance(point, *station) <= max_distance)
+    }
+
+    /// Distance in metres to the nearest station, checking every cell
+    fn nearest_distance(&self, point: [f64; 2]) -> f64 {
+        self.cells
+            .values()
+            .flatten()
+            .map(|station| haversine_distance(point, *station))
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+fn duplicate_ids(
+    dataset: &'static str,
+    ids: impl Iterator<Item = String>,
+) -> impl Iterator<Item = Issue> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for id in ids {
+        *counts.entry(id).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(move |(id, count)| Issue::DuplicateId { dataset, id, count })
+}
+
+/// Check the datasets against each other and against [`LINE_INFOS`]
+///
+/// Takes the datasets as loaded, before the repository drops invalid stations
+/// and merges duplicates, so those can be reported. Route vertices further
+/// than `max_station_distance` metres from every station are reported per line.
+pub fn validate(
+    stations: &[Station],
+    platforms: &[Platform],
+    routes: &RoutesFile,
+    max_station_distance: f64,
+) -> ValidationReport {
+    let mut issues = Vec::new();
+
+    // Lines
+    let mut route_lines: Vec<&String> = routes.routes.keys().collect();
+    route_lines.sort();
+    issues.extend(
+        route_lines
+            .iter()
+            .filter(|line_id| !is_known_line(line_id))
+            .map(|line_id| Issue::UnknownLine {
+                line_id: line_id.to_string(),
+            }),
+    );
+    let lines_with_routes: HashSet<&str> = routes
+        .routes
+        .keys()
+        .flat_map(|line_id| registry_ids_for(line_id))
+        .collect();
+    issues.extend(
+        LINE_INFOS
+            .iter()
+            .filter(|info| !lines_with_routes.contains(info.id))
+            .map(|info| Issue::LineWithoutRoutes {
+                line_id: info.id.to_string(),
+            }),
+    );
+
+    // Duplicates
+    issues.extend(duplicate_ids(
+        "station",
+        stations.iter().map(|s| s.station_unique_id.clone()),
+    ));
+    // A platform shared by several lines is listed once for each, under the
+    // same ID, so it's only a duplicate if the line is the same too
+    issues.extend(duplicate_ids(
+        "platform",
+        platforms
+            .iter()
+            .map(|p| format!("{} on {}", p.platform_unique_id, p.line)),
+    ));
+
+    // Stations and platforms
+    let valid_stations = filter_valid_stations(stations.to_vec());
+    let valid_ids: HashSet<&str> = valid_stations
+        .iter()
+        .map(|s| s.station_unique_id.as_str())
+        .collect();
+    issues.extend(
+        stations
+            .iter()
+            .filter(|s| !valid_ids.contains(s.station_unique_id.as_str()))
+            .map(|s| Issue::DroppedStation {
+                station_id: s.station_unique_id.clone(),
+                name: s.station_name.clone(),
+                lat: s.lat,
+                lon: s.lon,
+            }),
+    );
+
+    let station_ids: HashSet<&str> = stations
+        .iter()
+        .map(|s| s.station_unique_id.as_str())
+        .collect();
+    let platform_station_ids: HashSet<&str> = platforms
+        .iter()
+        .map(|p| p.station_unique_id.as_str())
+        .collect();
+    issues.extend(
+        platforms
+            .iter()
+            .filter(|p| !station_ids.contains(p.station_unique_id.as_str()))
+            .map(|p| Issue::OrphanPlatform {
+                platform_id: p.platform_unique_id.clone(),
+                station_id: p.station_unique_id.clone(),
+            }),
+    );
+    issues.extend(
+        stations
+            .iter()
+            .filter(|s| !platform_station_ids.contains(s.station_unique_id.as_str()))
+            .map(|s| Issue::StationWithoutPlatforms {
+                station_id: s.station_unique_id.clone(),
+                name: s.station_name.clone(),
+            }),
+    );
+
+    // Route geometry
+    if !valid_stations.is_empty() {
+        let grid = StationGrid::new(&valid_stations, max_station_distance);
+        for line_id in route_lines {
+            let far: Vec<[f64; 2]> = routes.routes[line_id]
+                .values()
+                .flat_map(|response| &response.results)
+                .flat_map(|sequence| &sequence.line_strings)
+                .filter_map(|line_string| parse_line_string(line_string).ok())
+                .flatten()
+                .filter(|point| !grid.has_station_within(*point, max_station_distance))
+                .collect();
+
+            let furthest = far
+                .iter()
+                .map(|point| (*point, grid.nearest_distance(*point)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((furthest, distance)) = furthest {
+                issues.push(Issue::FarFromStations {
+                    line_id: line_id.clone(),
+                    vertices: far.len(),
+                    furthest,
+                    distance,
+                });
+            }
+        }
+    }
+
+    ValidationReport { issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::error::parse_json;
+
+    fn station(id: &str, lat: f64, lon: f64) -> Station {
+        parse_json(
+            "stations.json",
+            &format!(
+                r#"{{"StationUniqueId":"{id}","StationName":"{id}","FareZones":"1",
+                    "OutsideStationUniqueId":"{id}-Outside","Lat":{lat},"Lon":{lon}}}"#
+            ),
+        )
+        .unwrap()
+    }
+
+    fn platform(id: &str, station_id: &str, line: &str) -> Platform {
+        parse_json(
+            "platforms.json",
+            &format!(
+                r#"{{"PlatformUniqueId":"{id}","StationUniqueId":"{station_id}",
+                    "PlatformFriendlyName":"","IsCustomerFacing":true,
+                    "HasServiceInterchange":false,"StationName":"","FareZones":"1",
+                    "OutsideStationUniqueId":"","StopAreaNaptanCode":"","Line":"{line}"}}"#
+            ),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reports_each_kind_of_issue() {
+        let stations = [
+            station("A", 51.5, -0.1),
+            station("B", 51.51, -0.1),
+            station("B", 51.51, -0.1),
+            station("C", 0.0, 0.0),
+        ];
+        let platforms = [
+            platform("A-1", "A", "bakerloo"),
+            platform("A-1", "A", "bakerloo"),
+            platform("A-2", "A", "district"),
+            platform("A-2", "A", "piccadilly"),
+            platform("Z-1", "Z", "bakerloo"),
+        ];
+        let routes: RoutesFile = parse_json(
+            "rail_routes.json",
+            r#"{"bakerloo":{"inbound":{"success":true,"results":[{"LineId":"bakerloo",
+                "Direction":"inbound","Mode":"tube",
+                "LineStrings":["[[[-0.1,51.5],[-0.1,51.51],[-0.1,51.6]]]"]}]}},
+               "london-cable-car":{},"emirates-air-line":{}}"#,
+        )
+        .unwrap();
+
+        let report = validate(&stations, &platforms, &routes, 1_000.0);
+        let has = |matches: &dyn Fn(&Issue) -> bool| report.issues.iter().any(matches);
+
+        assert!(has(
+            &|i| matches!(i, Issue::UnknownLine { line_id } if line_id == "emirates-air-line")
+        ));
+        assert!(has(
+            &|i| matches!(i, Issue::LineWithoutRoutes { line_id } if line_id == "tram")
+        ));
+        // Known through an alias, which counts as routes for the line it's shown as
+        assert!(!has(
+            &|i| matches!(i, Issue::UnknownLine { line_id } if line_id == "london-cable-car")
+        ));
+        assert!(!has(
+            &|i| matches!(i, Issue::LineWithoutRoutes { line_id } if line_id == "cable-car")
+        ));
+        assert!(has(
+            &|i| matches!(i, Issue::DuplicateId { dataset: "station", id, count: 2 } if id == "B")
+        ));
+        assert!(has(
+            &|i| matches!(i, Issue::DuplicateId { dataset: "platform", id, count: 2 } if id == "A-1 on bakerloo")
+        ));
+        assert!(has(
+            &|i| matches!(i, Issue::DroppedStation { station_id, .. } if station_id == "C")
+        ));
+        assert!(has(
+            &|i| matches!(i, Issue::OrphanPlatform { station_id, .. } if station_id == "Z")
+        ));
+        assert!(has(
+            &|i| matches!(i, Issue::StationWithoutPlatforms { station_id, .. } if station_id == "B")
+        ));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::FarFromStations { line_id, vertices: 1, distance, .. }
+                if line_id == "bakerloo" && (*distance - 10_000.0).abs() < 100.0
+        )));
+        assert!(!has(
+            &|i| matches!(i, Issue::DuplicateId { id, .. } if id.starts_with("A-2"))
+        ));
+        assert!(report.has_errors());
+    }
+}
@@ -0,0 +1,8 @@
+//! # TfL data, vehicle simulation and utilities
+//!
+//! The parts of the app that don't depend on the UI, shared by the app itself
+//! and the offline tools in `src/bin`.
+
+pub mod data;
+pub mod simulation;
+pub mod utils;
@@ -1,19 +1,24 @@
 use dioxus::prelude::*;
 
 // For logging and better errors in WASM
+#[cfg(target_arch = "wasm32")]
 use log::Level;
 
 mod app;
-mod data;
+#[cfg(target_arch = "wasm32")]
 mod maplibre; // Add the new MapLibre module
-mod utils; // Add the new utils module
 
+// The data layer lives in the library crate, so the offline tools can use it too
+use my_map::{data, utils};
 use utils::{LogLevel, set_log_level};
 
 /// Entry point for Dioxus
 fn main() {
-    console_error_panic_hook::set_once();
-    console_log::init_with_level(Level::Info).expect("error initializing logger");
+    #[cfg(target_arch = "wasm32")]
+    {
+        console_error_panic_hook::set_once();
+        console_log::init_with_level(Level::Info).expect("error initializing logger");
+    }
 
     // Initialize our custom logger
     #[cfg(debug_assertions)]
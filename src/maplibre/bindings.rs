@@ -36,15 +36,76 @@ extern "C" {
     #[wasm_bindgen(method, js_name = addLayer)]
     pub fn add_layer(this: &Map, layer: &JsValue) -> Map;
 
+    /// Add a layer beneath another, rather than on top
+    #[wasm_bindgen(method, js_name = addLayer)]
+    pub fn add_layer_before(this: &Map, layer: &JsValue, before_id: &str) -> Map;
+
+    #[wasm_bindgen(method, js_name = removeLayer)]
+    pub fn remove_layer(this: &Map, id: &str) -> Map;
+
+    #[wasm_bindgen(method, js_name = removeSource)]
+    pub fn remove_source(this: &Map, id: &str) -> Map;
+
+    #[wasm_bindgen(method, js_name = getStyle)]
+    pub fn get_style(this: &Map) -> JsValue;
+
     #[wasm_bindgen(method)]
     pub fn on(this: &Map, event: &str, handler: &Closure<dyn FnMut()>) -> Map;
 
+    /// Listen for an event on the features of one layer, e.g. clicks
+    #[wasm_bindgen(method, js_name = on)]
+    pub fn on_layer(
+        this: &Map,
+        event: &str,
+        layer_id: &str,
+        handler: &Closure<dyn FnMut(JsValue)>,
+    ) -> Map;
+
     #[wasm_bindgen(method)]
     pub fn off(this: &Map, event: &str, handler: &Closure<dyn FnMut()>) -> Map;
 
     #[wasm_bindgen(method, js_name = isStyleLoaded)]
     pub fn is_style_loaded(this: &Map) -> bool;
 
+    #[wasm_bindgen(method, js_name = getZoom)]
+    pub fn get_zoom(this: &Map) -> f64;
+
+    #[wasm_bindgen(method, js_name = getBounds)]
+    pub fn get_bounds(this: &Map) -> LngLatBounds;
+
+    #[wasm_bindgen(method, js_name = fitBounds)]
+    pub fn fit_bounds(this: &Map, bounds: &JsValue, options: &JsValue) -> Map;
+
+    #[wasm_bindgen(js_namespace = maplibregl, js_name = LngLatBounds)]
+    pub type LngLatBounds;
+
+    #[wasm_bindgen(method, js_name = getWest)]
+    pub fn get_west(this: &LngLatBounds) -> f64;
+
+    #[wasm_bindgen(method, js_name = getSouth)]
+    pub fn get_south(this: &LngLatBounds) -> f64;
+
+    #[wasm_bindgen(method, js_name = getEast)]
+    pub fn get_east(this: &LngLatBounds) -> f64;
+
+    #[wasm_bindgen(method, js_name = getNorth)]
+    pub fn get_north(this: &LngLatBounds) -> f64;
+
+    #[wasm_bindgen(js_namespace = maplibregl, js_name = Popup)]
+    pub type Popup;
+
+    #[wasm_bindgen(constructor, js_namespace = maplibregl, js_name = Popup)]
+    pub fn new(options: &JsValue) -> Popup;
+
+    #[wasm_bindgen(method, js_name = setLngLat)]
+    pub fn set_lng_lat(this: &Popup, lng_lat: &JsValue) -> Popup;
+
+    #[wasm_bindgen(method, js_name = setHTML)]
+    pub fn set_html(this: &Popup, html: &str) -> Popup;
+
+    #[wasm_bindgen(method, js_name = addTo)]
+    pub fn add_to(this: &Popup, map: &Map) -> Popup;
+
     // MapLibre Controls - these are correctly named
     #[wasm_bindgen(js_namespace = maplibregl, js_name = NavigationControl)]
     pub type NavigationControl;
@@ -109,6 +170,44 @@ impl Map {
             Some(raw)
         }
     }
+
+    /// The visible extent of the map as `[west, south, east, north]`
+    pub fn bbox(&self) -> [f64; 4] {
+        let bounds = self.get_bounds();
+        [
+            bounds.get_west(),
+            bounds.get_south(),
+            bounds.get_east(),
+            bounds.get_north(),
+        ]
+    }
+
+    /// Pan and zoom the map to show a `[west, south, east, north]` extent
+    pub fn fit_bbox(&self, bbox: [f64; 4]) {
+        let [west, south, east, north] = bbox;
+        let bounds = js_sys::Array::of2(
+            &js_sys::Array::of2(&west.into(), &south.into()),
+            &js_sys::Array::of2(&east.into(), &north.into()),
+        );
+        let options = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&options, &JsValue::from_str("padding"), &40.into());
+        self.fit_bounds(&bounds, &options);
+    }
+
+    /// IDs of all the layers in the current style
+    pub fn layer_ids(&self) -> Vec<String> {
+        let layers = js_sys::Reflect::get(&self.get_style(), &JsValue::from_str("layers"))
+            .unwrap_or(JsValue::UNDEFINED);
+        if !js_sys::Array::is_array(&layers) {
+            return Vec::new();
+        }
+
+        js_sys::Array::from(&layers)
+            .iter()
+            .filter_map(|layer| js_sys::Reflect::get(&layer, &JsValue::from_str("id")).ok())
+            .filter_map(|id| id.as_string())
+            .collect()
+    }
 }
 
 // Helper to access the global MapLibre instance
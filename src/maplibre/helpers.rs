@@ -1,11 +1,7 @@
 use crate::maplibre::bindings::*;
-use crate::utils::geojson::{
-    new_geojson_source, new_linestring_feature, new_point_feature, to_js_value,
-};
 use crate::utils::log::{LogCategory, with_context};
 use js_sys::{Array, Object, Reflect};
 use serde::Serialize;
-use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 use web_sys::window;
 
@@ -73,7 +69,7 @@ pub fn create_layer_groups() -> Result<JsValue, JsValue> {
         // Background group
         {
             // Label layer IDs - `window.mapInstance.getStyle().layers.slice(110, 119).map(layer => layer.id);`
-            let label_ids = vec![
+            let label_ids = [
                 "label_other",
                 "label_village",
                 "label_town",
@@ -105,7 +101,7 @@ pub fn create_layer_groups() -> Result<JsValue, JsValue> {
         // Label group
         {
             // Label layer IDs - `window.mapInstance.getStyle().layers.slice(110, 119).map(layer => layer.id);`
-            let highway_ids = vec![
+            let highway_ids = [
                 "highway-name-path",
                 "highway-name-minor",
                 "highway-name-major",
@@ -275,6 +271,7 @@ pub fn create_layer_groups() -> Result<JsValue, JsValue> {
                 "tfl-station-labels",
                 true,
             ));
+            infrastructure_layers.push(&Layer::new("bus-stops", "Bus Stops", "bus-stops", true));
 
             let infrastructure_group = LayerGroup::new("Infrastructure", &infrastructure_layers);
             layer_groups.push(&infrastructure_group);
@@ -460,6 +457,53 @@ pub fn create_circle_layer(id: &str, source: &str) -> Result<JsValue, JsValue> {
     })
 }
 
+/// Serialize a layer written as JSON
+fn layer_to_js_value(layer_config: &serde_json::Value) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    layer_config
+        .serialize(&serializer)
+        .map_err(|err| JsValue::from_str(&format!("Serialization error: {:?}", err)))
+}
+
+/// Helper to create the circles of the bus stop layer, hidden below `min_zoom`
+pub fn create_bus_stop_layer(id: &str, source: &str, min_zoom: f64) -> Result<JsValue, JsValue> {
+    layer_to_js_value(&serde_json::json!({
+        "id": id,
+        "type": "circle",
+        "source": source,
+        "minzoom": min_zoom,
+        "paint": {
+            "circle-radius": 7.0,
+            "circle-color": "#DC241F",
+            "circle-stroke-color": "#ffffff",
+            "circle-stroke-width": 1.5
+        }
+    }))
+}
+
+/// Helper to write each bus stop's letter on its circle, hidden below `min_zoom`
+pub fn create_bus_stop_letter_layer(
+    id: &str,
+    source: &str,
+    min_zoom: f64,
+) -> Result<JsValue, JsValue> {
+    layer_to_js_value(&serde_json::json!({
+        "id": id,
+        "type": "symbol",
+        "source": source,
+        "minzoom": min_zoom,
+        "layout": {
+            "text-field": ["get", "letter"],
+            "text-font": ["Noto Sans Bold"],
+            "text-size": 9.0,
+            "text-allow-overlap": true
+        },
+        "paint": {
+            "text-color": "#ffffff"
+        }
+    }))
+}
+
 /// Helper to create a text label layer
 pub fn create_label_layer(id: &str, source: &str) -> Result<JsValue, JsValue> {
     with_context("create_label_layer", LogCategory::Map, |logger| {
@@ -9,13 +9,8 @@ use wasm_bindgen::prelude::*;
 
 /// Manager for map controls
 pub struct ControlManager {
-    registered_controls: HashMap<String, ControlInfo>,
-}
-
-/// Information about a registered control
-struct ControlInfo {
-    id: String,
-    position: String,
+    /// Position of each control added, by ID
+    registered_controls: HashMap<String, String>,
 }
 
 impl ControlManager {
@@ -44,13 +39,8 @@ impl ControlManager {
             map.addControl(control, position);
 
             // Register the control
-            self.registered_controls.insert(
-                id.to_string(),
-                ControlInfo {
-                    id: id.to_string(),
-                    position: position.unwrap_or("default").to_string(),
-                },
-            );
+            self.registered_controls
+                .insert(id.to_string(), position.unwrap_or("default").to_string());
 
             logger.debug(&format!("Control '{}' added and registered", id));
 
@@ -17,36 +17,6 @@ impl EventManager {
         }
     }
 
-    /// Add an event listener to the map
-    pub fn add_listener(
-        &mut self,
-        map: &Map,
-        event: &str,
-        callback: impl FnMut() + 'static,
-    ) -> Result<(), JsValue> {
-        with_context("EventManager::add_listener", LogCategory::Map, |logger| {
-            logger.debug(&format!("Adding listener for '{}' event", event));
-
-            // Create a closure from the callback
-            let closure = Closure::wrap(Box::new(callback) as Box<dyn FnMut()>);
-
-            // Register the listener with the map
-            map.on(event, &closure);
-
-            // Store the closure so it isn't dropped
-            let event_listeners = self.registered_events.entry(event.to_string()).or_default();
-            event_listeners.push(closure);
-
-            logger.debug(&format!(
-                "Added '{}' listener (now {} total)",
-                event,
-                event_listeners.len()
-            ));
-
-            Ok(())
-        })
-    }
-
     /// Add a load event handler that will be called when the map is loaded
     pub fn add_load_handler<F>(&mut self, map: &Map, callback: F) -> Result<(), JsValue>
     where
@@ -135,35 +105,6 @@ impl EventManager {
         )
     }
 
-    /// Remove a specific listener for an event
-    pub fn remove_listener(&mut self, map: &Map, event: &str, index: usize) -> Result<(), JsValue> {
-        with_context(
-            "EventManager::remove_listener",
-            LogCategory::Map,
-            |logger| {
-                if let Some(listeners) = self.registered_events.get_mut(event) {
-                    if index < listeners.len() {
-                        // Remove from map
-                        map.off(event, &listeners[index]);
-                        // Remove from our storage
-                        listeners.remove(index);
-                        logger.debug(&format!("Removed listener {} for event '{}'", index, event));
-                        Ok(())
-                    } else {
-                        logger.error(&format!(
-                            "Listener index {} out of bounds for event '{}'",
-                            index, event
-                        ));
-                        Err(JsValue::from_str("Listener index out of bounds"))
-                    }
-                } else {
-                    logger.error(&format!("No listeners registered for event '{}'", event));
-                    Err(JsValue::from_str("No listeners for this event"))
-                }
-            },
-        )
-    }
-
     /// Clear all listeners
     pub fn clear_listeners(&mut self, map: &Map) {
         with_context(
@@ -6,7 +6,6 @@ mod lifecycle;
 
 pub use controls::*;
 pub use events::*;
-pub use layers::*;
 
 use crate::data::TflDataRepository;
 use crate::maplibre::bindings::Map;
@@ -17,7 +16,6 @@ use wasm_bindgen::prelude::*;
 pub struct MapLibreManager {
     pub map: Option<Map>,
     event_manager: EventManager,
-    layer_manager: LayerManager,
     control_manager: ControlManager,
 }
 
@@ -28,7 +26,6 @@ impl MapLibreManager {
         Self {
             map: None,
             event_manager: EventManager::new(),
-            layer_manager: LayerManager::new(),
             control_manager: ControlManager::new(),
         }
     }
@@ -39,11 +36,6 @@ impl MapLibreManager {
         lifecycle::create_map(self, container_id)
     }
 
-    /// Check if maplibregl is available (static debug function)
-    pub fn debug_check_maplibregl() -> Result<(), JsValue> {
-        lifecycle::debug_check_maplibregl()
-    }
-
     /// Add map controls (navigation, scale, etc.)
     pub fn add_map_controls(&mut self) -> Result<(), JsValue> {
         if let Some(map) = &self.map {
@@ -70,15 +62,6 @@ impl MapLibreManager {
             Err(JsValue::from_str("Map not initialized"))
         }
     }
-
-    /// Update layer visibility based on TflLayers struct
-    pub fn update_layer_visibility(&self, layers: &crate::app::TflLayers) -> Result<(), JsValue> {
-        if let Some(map) = &self.map {
-            self.layer_manager.update_visibility(map, layers)
-        } else {
-            Err(JsValue::from_str("Map not initialized"))
-        }
-    }
 }
 
 /// Implement Drop to clean up resources
@@ -0,0 +1,73 @@
+//! The simulation's clock: the time of day simulated, and how fast it runs.
+
+use crate::data::gtfs::parse_time;
+
+/// Seconds in a day
+const DAY_SECS: f64 = 24.0 * 3_600.0;
+
+/// Simulated time of day the simulation starts at, by default
+pub const DEFAULT_START_TIME: f64 = 8.0 * 3_600.0;
+
+/// Speeds the clock can run at, as multiples of real time
+pub const SPEEDS: [f64; 3] = [1.0, 10.0, 60.0];
+
+/// Speed the clock runs at, by default
+pub const DEFAULT_SPEED: f64 = 10.0;
+
+/// How often a running simulation advances by one tick, in real time
+pub const TICK_INTERVAL_MS: u32 = 1000 / 30;
+
+/// Simulated seconds a single step moves on by, while paused
+pub const STEP_SECS: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clock {
+    /// Simulated seconds since midnight of the first day, so past 24 hours
+    /// once the simulation runs overnight
+    pub time: f64,
+    /// Multiple of real time the clock runs at
+    pub speed: f64,
+    /// Time of day the simulation starts at, kept when it is reset
+    pub start_time: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            time: DEFAULT_START_TIME,
+            speed: DEFAULT_SPEED,
+            start_time: DEFAULT_START_TIME,
+        }
+    }
+}
+
+impl Clock {
+    /// Simulated seconds that pass in one tick of the animation
+    pub fn tick_secs(&self) -> f64 {
+        f64::from(TICK_INTERVAL_MS) / 1000.0 * self.speed
+    }
+}
+
+/// Seconds since midnight, from simulated seconds that may run back into
+/// the day before, as during the warm-up for an early start
+pub fn time_of_day(time: f64) -> u32 {
+    time.rem_euclid(DAY_SECS) as u32
+}
+
+/// `HH:MM:SS` on a 24-hour clock, from simulated seconds
+pub fn format_clock(time: f64) -> String {
+    let seconds = time_of_day(time);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Seconds since midnight from `HH:MM` or `HH:MM:SS`
+pub fn parse_clock(text: &str) -> Option<f64> {
+    parse_time(text.trim())
+        .map(f64::from)
+        .filter(|&time| time < DAY_SECS)
+}
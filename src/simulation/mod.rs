@@ -0,0 +1,662 @@
+//! # Vehicle simulation engine
+//!
+//! Runs vehicles along the routes to the lines' service patterns, in plain
+//! Rust with no UI or browser, so it steps the same in the app, in the
+//! headless runner (`src/bin/simulate.rs`) and in tests. The app keeps one
+//! [`Simulation`] for the map and advances it on a timer; the runner
+//! advances one as fast as it can and reports its [`Statistics`].
+
+pub mod clock;
+pub mod model;
+pub mod scenario;
+pub mod service;
+pub mod signalling;
+pub mod stats;
+
+use crate::data::TflDataRepository;
+use crate::data::services::ServicePatterns;
+use crate::utils::rng::Rng;
+use clock::Clock;
+use model::{ModeProfile, ModeProfiles, Route, Vehicle, build_routes_from_tfl_data};
+use scenario::Scenario;
+use service::{Service, build_services};
+use signalling::Signalling;
+pub use stats::{LineStatistics, Statistics};
+use std::collections::HashMap;
+
+/// How long the service runs before the start time, in coarser steps, so
+/// vehicles are running the length of every line from the start
+pub const WARM_UP_SECS: f64 = 90.0 * 60.0;
+const WARM_UP_STEP_SECS: f64 = 5.0;
+
+/// Headway for every line when there are no service patterns to go by
+pub const DEFAULT_HEADWAY_MINS: f64 = 10.0;
+
+/// Fraction a vehicle's top speed can fall short of the service's
+const SPEED_VARIATION: f64 = 0.1;
+
+/// Fraction a vehicle's dwell time can differ from its mode's
+const DWELL_VARIATION: f64 = 0.2;
+
+/// How close in metres a vehicle has to get to a stop to have reached it
+const ARRIVAL_TOLERANCE: f64 = 0.5;
+
+/// The vehicles running on the routes, and everything that moves them
+#[derive(Debug, Default)]
+pub struct Simulation {
+    pub vehicles: Vec<Vehicle>,
+    pub routes: Vec<Route>,
+    /// How each mode's vehicles move
+    pub profiles: ModeProfiles,
+    /// How often each line runs through the day
+    pub patterns: ServicePatterns,
+    /// Each line's service in each direction, dispatching its vehicles
+    pub services: Vec<Service>,
+    /// The blocks the trains run through, one train in each at a time
+    pub signalling: Signalling,
+    /// The disruptions the routes and services run with
+    pub scenario: Scenario,
+    /// Simulated time of day and how fast it passes
+    pub clock: Clock,
+    /// Seed the run started from, which replays it exactly
+    pub seed: u64,
+    /// Draws the variation between vehicles, from the seed
+    rng: Rng,
+    /// ID for the next vehicle dispatched
+    next_vehicle_id: usize,
+    /// What has happened since the start time
+    pub stats: Statistics,
+}
+
+/// How far a vehicle got in a step
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Movement {
+    /// Still on its way, or waiting at a stop
+    Running,
+    /// Arrived at a stop, where it waits
+    CalledAt,
+    /// Arrived at the end of its run
+    Finished,
+    /// Stopped at a signal for the block ahead to clear
+    Held,
+}
+
+impl Simulation {
+    /// A simulation of the routes run to the service patterns, with no
+    /// vehicles yet, varying the vehicles by a seed
+    pub fn new(routes: Vec<Route>, patterns: ServicePatterns, seed: u64) -> Self {
+        Self {
+            services: build_services(&routes),
+            signalling: Signalling::new(&routes),
+            routes,
+            patterns,
+            seed,
+            rng: Rng::new(seed),
+            ..Default::default()
+        }
+    }
+
+    /// A simulation of the repository's routes and service patterns, or of
+    /// the sample routes without a repository, with every line every
+    /// [`DEFAULT_HEADWAY_MINS`] where there are no patterns
+    pub fn from_repository(repository: Option<&TflDataRepository>, seed: u64) -> Self {
+        let (routes, patterns) = match repository {
+            Some(repository) => (
+                build_routes_from_tfl_data(repository),
+                repository.services.clone(),
+            ),
+            None => (model::build_sample_routes(), None),
+        };
+        let patterns = patterns.unwrap_or_else(|| ServicePatterns::uniform(DEFAULT_HEADWAY_MINS));
+        Self::new(routes, patterns, seed)
+    }
+
+    /// Run the routes and services as a scenario disrupts them, before any
+    /// vehicles are dispatched
+    pub fn disrupt(&mut self, scenario: Scenario) {
+        self.routes = scenario.disrupt(std::mem::take(&mut self.routes));
+        self.services = build_services(&self.routes);
+        for service in &mut self.services {
+            service.service_level = scenario.service_level(&service.line_id);
+        }
+        self.signalling = Signalling::new(&self.routes);
+        self.scenario = scenario;
+    }
+
+    /// Start at the clock's start time, having run the service for
+    /// [`WARM_UP_SECS`] before it so vehicles are already spread along
+    /// their routes
+    pub fn start(&mut self) {
+        let start_time = self.clock.start_time;
+        self.clock.time = start_time - WARM_UP_SECS;
+        while self.clock.time < start_time {
+            self.advance(WARM_UP_STEP_SECS.min(start_time - self.clock.time));
+        }
+        self.stats = Statistics {
+            peak_vehicles: self.vehicles.len(),
+            ..Default::default()
+        };
+    }
+
+    /// Advance by one tick at the clock's speed
+    pub fn tick(&mut self) {
+        self.advance(self.clock.tick_secs());
+    }
+
+    /// Advance the clock by `seconds`, moving every vehicle along its route,
+    /// retiring those at the end of their run and dispatching those due
+    pub fn advance(&mut self, seconds: f64) {
+        // Vehicles on a closed loop go on round while the loop needs them
+        let mut loops = self.loop_fleets();
+
+        let routes = &self.routes;
+        let profiles = &self.profiles;
+        let signalling = &mut self.signalling;
+        let stats = &mut self.stats;
+        let time = self.clock.time + seconds;
+        self.vehicles.retain_mut(|vehicle| {
+            let route = &routes[vehicle.route_index];
+            let profile = profiles.get(&route.mode);
+            let from = vehicle.distance;
+            let movement = advance_vehicle(vehicle, route, profile, signalling, seconds);
+            stats.record(vehicle, movement, vehicle.distance - from, time);
+            if movement == Movement::Held {
+                stats.record_hold(vehicle, seconds);
+            }
+            if movement != Movement::Finished {
+                return true;
+            }
+            signalling.release(vehicle);
+            if let Some((on_loop, fleet)) = loops.get_mut(&route.id) {
+                if *on_loop <= *fleet {
+                    vehicle.start_lap(route, &profile, time);
+                    return true;
+                }
+                *on_loop -= 1;
+            }
+            false
+        });
+
+        self.clock.time = time;
+        self.stats.simulated_secs += seconds;
+        self.dispatch();
+        self.stats.peak_vehicles = self.stats.peak_vehicles.max(self.vehicles.len());
+    }
+
+    /// How many trains are stopped at signals for the block ahead to clear
+    pub fn held_vehicles(&self) -> usize {
+        self.vehicles.iter().filter(|vehicle| vehicle.held).count()
+    }
+
+    /// Advance by `seconds` in steps of at most `step_secs`
+    pub fn run_for(&mut self, seconds: f64, step_secs: f64) {
+        let end = self.clock.time + seconds;
+        while self.clock.time < end {
+            self.advance(step_secs.min(end - self.clock.time));
+        }
+    }
+
+    /// The vehicles on each closed loop route and the fleet it needs now,
+    /// by route index
+    fn loop_fleets(&self) -> HashMap<usize, (usize, usize)> {
+        let mut loops: HashMap<usize, (usize, usize)> = self
+            .routes
+            .iter()
+            .filter(|route| route.is_closed_loop())
+            .map(|route| (route.id, (0, self.loop_fleet(route))))
+            .collect();
+        for vehicle in &self.vehicles {
+            if let Some((on_loop, _)) = loops.get_mut(&vehicle.route_index) {
+                *on_loop += 1;
+            }
+        }
+        loops
+    }
+
+    /// Vehicles a closed loop needs to run its service: enough to go round
+    /// it at the headway, or none while its line isn't running
+    ///
+    /// Trains on a signalled loop need blocks left clear to move into, or
+    /// they would all be held waiting for each other, so no more run round
+    /// it than one for every other block, though always at least one.
+    fn loop_fleet(&self, route: &Route) -> usize {
+        let service_level = self.scenario.service_level(&route.line_id);
+        let band = self
+            .patterns
+            .band_at(
+                &route.line_id,
+                &route.mode,
+                clock::time_of_day(self.clock.time),
+            )
+            .filter(|band| band.headway_mins > 0.0 && service_level > 0.0);
+        let Some(band) = band else {
+            return 0;
+        };
+        let profile = self.profiles.get(&route.mode);
+        let lap_secs = band.running_mins.map_or_else(
+            || route.running_time(&profile, profile.max_speed),
+            |minutes| minutes * 60.0,
+        );
+        let fleet = (lap_secs * service_level / (band.headway_mins * 60.0)).ceil() as usize;
+        match self.signalling.sections(route.id).len() {
+            0 => fleet,
+            sections => fleet.min((sections / 2).max(1)),
+        }
+    }
+
+    /// Send off the vehicles due by the current time, each already moved on
+    /// by the time since it was due
+    ///
+    /// A closed loop only takes new vehicles until it has its fleet.
+    fn dispatch(&mut self) {
+        let time = self.clock.time;
+        let mut loops = self.loop_fleets();
+        for service in &mut self.services {
+            for departure in service.due(&self.patterns, time) {
+                if let Some((on_loop, fleet)) = loops.get_mut(&departure.route_index) {
+                    if *on_loop >= *fleet {
+                        continue;
+                    }
+                    *on_loop += 1;
+                }
+                let route = &self.routes[departure.route_index];
+                let profile = self.profiles.get(&route.mode);
+                let max_speed = departure.running_secs.map_or(profile.max_speed, |secs| {
+                    route.top_speed_for(&profile, secs * route.timetable_share)
+                });
+
+                // Each vehicle runs a little under the top speed and takes its
+                // own time at the stops
+                let max_speed = max_speed * self.rng.range(1.0 - SPEED_VARIATION, 1.0);
+                let mut vehicle = Vehicle::dispatch(self.next_vehicle_id, route, max_speed);
+                vehicle.dwell_factor = self.rng.range(1.0 - DWELL_VARIATION, 1.0 + DWELL_VARIATION);
+                vehicle.departed_at = departure.time;
+                self.next_vehicle_id += 1;
+                self.stats.record_departure(&vehicle);
+
+                let from = vehicle.distance;
+                let seconds = time - departure.time;
+                let signalling = &mut self.signalling;
+                let movement = advance_vehicle(&mut vehicle, route, profile, signalling, seconds);
+                self.stats
+                    .record(&vehicle, movement, vehicle.distance - from, time);
+                match movement {
+                    Movement::Finished => self.signalling.release(&mut vehicle),
+                    Movement::Held => {
+                        self.stats.record_hold(&vehicle, seconds);
+                        self.vehicles.push(vehicle);
+                    }
+                    _ => self.vehicles.push(vehicle),
+                }
+            }
+        }
+    }
+}
+
+/// Move a vehicle along its route for `seconds`, speeding up after each stop
+/// and slowing to a halt at the next, where it waits for its dwell time, and
+/// braking for any signal ahead held at danger
+fn advance_vehicle(
+    vehicle: &mut Vehicle,
+    route: &Route,
+    profile: ModeProfile,
+    signalling: &mut Signalling,
+    mut seconds: f64,
+) -> Movement {
+    // Wait out any dwell first, moving off for the rest of the step
+    if vehicle.dwell_remaining >= seconds {
+        vehicle.dwell_remaining -= seconds;
+        return Movement::Running;
+    }
+    seconds -= vehicle.dwell_remaining;
+    vehicle.dwell_remaining = 0.0;
+
+    // Head for the next stop, or the end of the run
+    let destination = route.destination();
+    let target = route
+        .stop_ahead(vehicle.distance)
+        .filter(|stop| stop.distance < destination);
+    let stop_distance = target.map_or(destination, |stop| stop.distance);
+
+    // But no further than the signals allow, with the blocks reserved as
+    // far as the vehicle could get this step and still stop
+    let speed = (vehicle.speed + profile.acceleration * seconds).min(vehicle.max_speed);
+    let reach = vehicle.distance
+        + speed * seconds
+        + speed.powi(2) / (2.0 * profile.deceleration)
+        + ARRIVAL_TOLERANCE;
+    let authority = signalling.authorise(vehicle, reach.min(stop_distance));
+    let target_distance = stop_distance.min(authority);
+    let remaining = (target_distance - vehicle.distance).max(0.0);
+    vehicle.held = authority < stop_distance && remaining <= ARRIVAL_TOLERANCE;
+    if vehicle.held {
+        vehicle.speed = 0.0;
+        return Movement::Held;
+    }
+
+    // Speed up to the top speed, but no faster than can stop at the target
+    let braking_limit = (2.0 * profile.deceleration * remaining).sqrt();
+    vehicle.speed = speed.min(braking_limit);
+    let step = vehicle.speed * seconds;
+
+    let movement = if step + ARRIVAL_TOLERANCE >= remaining {
+        vehicle.distance = target_distance;
+        vehicle.speed = 0.0;
+        match target {
+            _ if target_distance < stop_distance => Movement::Running,
+            None => return Movement::Finished,
+            Some(stop) => {
+                vehicle.last_station = Some(stop.station_id.clone());
+                vehicle.dwell_remaining = profile.dwell_secs * vehicle.dwell_factor;
+                Movement::CalledAt
+            }
+        }
+    } else {
+        vehicle.distance += step;
+        Movement::Running
+    };
+
+    vehicle.next_station = route
+        .stop_ahead(vehicle.distance)
+        .map(|stop| stop.station_id.clone());
+    (vehicle.lng, vehicle.lat) = route.point_at(vehicle.distance);
+    movement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::services::ServiceBand;
+
+    fn route_through(points: Vec<(f64, f64)>) -> Route {
+        Route::new(
+            0,
+            "test".to_string(),
+            "test".to_string(),
+            "outbound".to_string(),
+            "#000000".to_string(),
+            "tube".to_string(),
+            points,
+        )
+    }
+
+    fn simulation_with(route: Route) -> Simulation {
+        let profile = ModeProfile::for_mode(&route.mode);
+        let vehicle = Vehicle::dispatch(0, &route, profile.max_speed);
+        let mut simulation = Simulation::new(vec![route], ServicePatterns::default(), 0);
+        simulation.vehicles.push(vehicle);
+        simulation
+    }
+
+    #[test]
+    fn moves_by_distance_however_the_route_is_drawn() {
+        // The same stretch of about 2 km east, drawn with two points and with many
+        let sparse = vec![(0.0, 51.5), (0.0288, 51.5)];
+        let dense: Vec<(f64, f64)> = (0..=20).map(|i| (i as f64 * 0.00144, 51.5)).collect();
+        let distances: Vec<f64> = [sparse, dense]
+            .into_iter()
+            .map(|points| {
+                let mut simulation = simulation_with(route_through(points));
+                for _ in 0..60 {
+                    simulation.advance(1.0);
+                }
+                simulation.vehicles[0].distance
+            })
+            .collect();
+
+        assert!(distances[0] > 500.0);
+        assert!((distances[0] - distances[1]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stops_and_dwells_at_each_station() {
+        let mut route = route_through(vec![(0.0, 51.5), (0.0288, 51.5)]);
+        route.add_stops([
+            ("A", "Station A", [0.0, 51.5]),
+            ("B", "Station B", [0.0144, 51.5001]),
+            ("C", "Station C", [0.0288, 51.5]),
+            ("Far", "Too far away", [0.0144, 51.51]),
+        ]);
+        assert_eq!(route.stops.len(), 3);
+
+        let mut simulation = simulation_with(route);
+        simulation.profiles.set_dwell_secs("tube", 45.0);
+        assert_eq!(simulation.vehicles[0].last_station.as_deref(), Some("A"));
+        let mut arrived_at = None;
+        for second in 0..600 {
+            simulation.advance(1.0);
+            let vehicle = &simulation.vehicles[0];
+            if vehicle.last_station.as_deref() == Some("B") {
+                arrived_at = Some(second);
+                break;
+            }
+            assert_eq!(vehicle.next_station.as_deref(), Some("B"));
+        }
+        let arrived_at = arrived_at.expect("reached station B");
+        // Starting from rest, it can't average its top speed over 1 km
+        assert!(arrived_at as f64 > 1000.0 / ModeProfile::for_mode("tube").max_speed);
+
+        // It waits at B for the dwell time, then heads on for C
+        let at_b = simulation.vehicles[0].distance;
+        assert_eq!(simulation.vehicles[0].speed, 0.0);
+        assert_eq!(simulation.vehicles[0].next_station.as_deref(), Some("C"));
+        for _ in 0..44 {
+            simulation.advance(1.0);
+        }
+        assert_eq!(simulation.vehicles[0].distance, at_b);
+        simulation.advance(2.0);
+        assert!(simulation.vehicles[0].distance > at_b);
+
+        // And is retired at the end of its run
+        for _ in 0..600 {
+            simulation.advance(1.0);
+        }
+        assert!(simulation.vehicles.is_empty());
+        assert_eq!(simulation.stats.stop_calls, 1);
+        assert_eq!(simulation.stats.completed_runs, 1);
+    }
+
+    #[test]
+    fn holds_a_train_at_a_signal_behind_one_at_the_platform() {
+        let mut route = route_through(vec![(0.0, 51.5), (0.0288, 51.5)]);
+        route.add_stops([
+            ("A", "A", [0.0, 51.5]),
+            ("B", "B", [0.0144, 51.5]),
+            ("C", "C", [0.0288, 51.5]),
+        ]);
+        let mut simulation = simulation_with(route);
+        simulation.profiles.set_dwell_secs("tube", 300.0);
+        simulation.run_for(120.0, 1.0);
+        assert_eq!(simulation.vehicles[0].last_station.as_deref(), Some("B"));
+
+        // The train behind runs up to the signal protecting the platform at
+        // B, and waits there while the one ahead stands in it
+        let profile = ModeProfile::for_mode("tube");
+        let behind = Vehicle::dispatch(1, &simulation.routes[0], profile.max_speed);
+        simulation.vehicles.push(behind);
+        simulation.run_for(180.0, 1.0);
+        let signal = simulation.signalling.sections(0)[0].end;
+        assert_eq!(simulation.vehicles[1].distance, signal);
+        assert_eq!(simulation.held_vehicles(), 1);
+        assert!(simulation.stats.held_mins > 1.0);
+
+        // Then follows it on once it has gone
+        simulation.run_for(300.0, 1.0);
+        assert_eq!(simulation.held_vehicles(), 0);
+        let behind = simulation.vehicles.iter().find(|vehicle| vehicle.id == 1);
+        assert_eq!(behind.unwrap().last_station.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn dispatches_at_the_headway_to_the_running_time() {
+        let route = route_through(vec![(0.0, 51.5), (0.0288, 51.5)]);
+        let profile = ModeProfile::for_mode("tube");
+        let top_speed = route.top_speed_for(&profile, 180.0);
+        assert!((route.running_time(&profile, top_speed) - 180.0).abs() < 1.0);
+
+        let patterns = ServicePatterns {
+            lines: [(
+                "test".to_string(),
+                vec![ServiceBand {
+                    start: "07:00".to_string(),
+                    end: "08:00".to_string(),
+                    headway_mins: 5.0,
+                    running_mins: Some(3.0),
+                }],
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(vec![route], patterns, 0);
+        simulation.clock.time = 6.5 * 3_600.0;
+
+        // Nothing runs until 07:00, then a vehicle every 5 minutes, each
+        // taking 3 minutes to run, until 08:00
+        while simulation.clock.time < 7.0 * 3_600.0 - 1.0 {
+            simulation.advance(1.0);
+        }
+        assert_eq!(simulation.next_vehicle_id, 0);
+        while simulation.clock.time < 7.5 * 3_600.0 {
+            simulation.advance(1.0);
+        }
+        assert_eq!(simulation.next_vehicle_id, 7);
+        assert_eq!(simulation.vehicles.len(), 1);
+        while simulation.clock.time < 9.0 * 3_600.0 {
+            simulation.advance(1.0);
+        }
+        assert_eq!(simulation.next_vehicle_id, 12);
+        assert!(simulation.vehicles.is_empty());
+
+        let stats = &simulation.stats.lines["test"];
+        assert_eq!((stats.departures, stats.completed_runs), (12, 12));
+        // A little over the timetable, as vehicles run a little under its
+        // top speed
+        assert!((3.0..3.4).contains(&stats.mean_run_mins));
+    }
+
+    #[test]
+    fn warms_up_on_the_last_evening_service_after_midnight() {
+        let route = route_through(vec![(0.0, 51.5), (0.0288, 51.5)]);
+        let patterns = ServicePatterns {
+            lines: [(
+                "test".to_string(),
+                vec![ServiceBand {
+                    start: "22:00".to_string(),
+                    end: "24:00".to_string(),
+                    headway_mins: 5.0,
+                    running_mins: Some(3.0),
+                }],
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(vec![route], patterns, 0);
+        simulation.clock.start_time = 0.5 * 3_600.0;
+
+        // The warm-up runs from 23:00 the night before, when trains still run
+        simulation.start();
+        assert_eq!(simulation.next_vehicle_id, 12);
+    }
+
+    #[test]
+    fn runs_the_clock_at_its_speed() {
+        let tick_secs = f64::from(clock::TICK_INTERVAL_MS) / 1000.0;
+        let mut simulation = Simulation::from_repository(None, 0);
+        simulation.start();
+        assert_eq!(simulation.clock.time, clock::DEFAULT_START_TIME);
+
+        simulation.tick();
+        simulation.clock.speed = 60.0;
+        simulation.tick();
+        let elapsed = simulation.clock.time - clock::DEFAULT_START_TIME;
+        assert!((elapsed - tick_secs * (clock::DEFAULT_SPEED + 60.0)).abs() < 1e-9);
+        assert_eq!(clock::format_clock(simulation.clock.time), "08:00:02");
+
+        // The clock reads as a time of day overnight
+        assert_eq!(clock::format_clock(25.5 * 3_600.0), "01:30:00");
+        assert_eq!(clock::parse_clock("23:15"), Some(23.25 * 3_600.0));
+        assert_eq!(clock::parse_clock("25:00"), None);
+    }
+
+    #[test]
+    fn keeps_a_closed_loop_going_round_at_the_headway() {
+        let mut route = route_through(vec![
+            (0.0, 51.5),
+            (0.0, 51.51),
+            (0.016, 51.51),
+            (0.016, 51.5),
+            (0.0, 51.5),
+        ]);
+        route.add_stops([
+            ("A", "A", [0.0, 51.5]),
+            ("B", "B", [0.0, 51.51]),
+            ("C", "C", [0.016, 51.51]),
+            ("D", "D", [0.016, 51.5]),
+        ]);
+        route.detect_loop();
+        assert!(route.is_closed_loop());
+
+        let mut simulation = Simulation::new(vec![route], ServicePatterns::uniform(2.0), 0);
+        simulation.start();
+        let fleet = simulation.loop_fleet(&simulation.routes[0]);
+        assert!(fleet > 1);
+        simulation.run_for(3.0 * 3_600.0, 1.0);
+
+        // The loop's fleet went on round lap after lap, with no more sent out
+        assert_eq!(simulation.vehicles.len(), fleet);
+        assert_eq!(simulation.next_vehicle_id, fleet);
+        assert!(simulation.stats.completed_runs > 3 * fleet);
+    }
+
+    #[test]
+    fn runs_a_train_round_a_loop_of_one_block() {
+        // A loop of about 430 m, calling only at A
+        let mut route = route_through(vec![
+            (0.0, 51.5),
+            (0.0, 51.501),
+            (0.0015, 51.501),
+            (0.0015, 51.5),
+            (0.0, 51.5),
+        ]);
+        route.stops = [0.0, route.length()]
+            .into_iter()
+            .map(|distance| model::Stop {
+                station_id: "A".to_string(),
+                name: "A".to_string(),
+                distance,
+            })
+            .collect();
+        route.detect_loop();
+        assert!(route.is_closed_loop());
+
+        let mut simulation = Simulation::new(vec![route], ServicePatterns::uniform(2.0), 0);
+        assert_eq!(simulation.signalling.sections(0).len(), 1);
+        assert_eq!(simulation.loop_fleet(&simulation.routes[0]), 1);
+        simulation.start();
+        assert_eq!(simulation.vehicles.len(), 1);
+    }
+
+    #[test]
+    fn replays_the_same_trajectories_from_the_same_seed() {
+        let run = |seed| {
+            let mut simulation = Simulation::from_repository(None, seed);
+            simulation.start();
+            let mut trajectories = Vec::new();
+            for _ in 0..20 {
+                simulation.run_for(10.0, 1.0);
+                trajectories.extend(
+                    simulation
+                        .vehicles
+                        .iter()
+                        .map(|vehicle| (vehicle.id, vehicle.lng, vehicle.lat)),
+                );
+            }
+            trajectories
+        };
+
+        let first = run(7);
+        assert!(!first.is_empty());
+        assert_eq!(first, run(7));
+        assert_ne!(first, run(8));
+    }
+}
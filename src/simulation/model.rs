@@ -0,0 +1,1113 @@
+use crate::data::TflDataRepository;
+use crate::data::gtfs_export::MAX_STOP_OFFSET;
+use crate::data::line_definitions::get_line_color;
+use crate::data::loader::parse_line_string;
+use crate::data::model::{MatchedStop, StopPointSequence};
+use crate::utils::geo::{haversine_distance, project_onto_line, slice_line};
+use crate::utils::log::{LogCategory, debug_with_category, warn_with_category};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+#[derive(Clone, Debug)]
+pub enum VehicleType {
+    Bus,
+    Train,
+}
+
+impl VehicleType {
+    pub fn for_mode(mode: &str) -> Self {
+        match mode {
+            "bus" => VehicleType::Bus,
+            _ => VehicleType::Train,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Vehicle {
+    pub id: usize,
+    pub vehicle_type: VehicleType,
+    pub route_index: usize,
+    pub line_id: String,
+    pub distance: f64,  // Metres along the route from its first point
+    pub speed: f64,     // Metres per second
+    pub max_speed: f64, // Top speed, to keep to the service's running time
+    /// Seconds left waiting at the current stop
+    pub dwell_remaining: f64,
+    /// Multiple of its mode's dwell time the vehicle waits at each stop
+    pub dwell_factor: f64,
+    /// Simulated time the vehicle set off
+    pub departed_at: f64,
+    /// Indices of the route's signalling sections whose blocks the vehicle
+    /// holds, see [`super::signalling`]
+    pub sections: Range<usize>,
+    /// Whether the vehicle is stopped at a signal for the block ahead to
+    /// clear
+    pub held: bool,
+    /// ID of the station the vehicle last called at
+    pub last_station: Option<String>,
+    /// ID of the station the vehicle is heading for
+    pub next_station: Option<String>,
+    pub lng: f64, // Current longitude
+    pub lat: f64, // Current latitude
+}
+
+/// A station a route calls at
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stop {
+    pub station_id: String,
+    pub name: String,
+    /// Metres along the route from its first point
+    pub distance: f64,
+}
+
+/// Which way a vehicle runs round a loop: trains keep left, so the outer
+/// rail runs clockwise and the inner rail anticlockwise
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rail {
+    Inner,
+    Outer,
+}
+
+impl Rail {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rail::Inner => "inner",
+            Rail::Outer => "outer",
+        }
+    }
+}
+
+/// Where a route runs round a loop, back to a station it has called at:
+/// the whole of its run on a closed loop, or the end of it on a lasso, such
+/// as the Circle line's run from Hammersmith round from Edgware Road
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopSpan {
+    /// Metres along the route to where the loop leaves the station
+    pub start: f64,
+    /// Metres along the route to where it gets back there
+    pub end: f64,
+    pub rail: Rail,
+}
+
+/// How close in metres the ends of a route's line have to be for it to be
+/// drawn as a ring
+const RING_CLOSURE: f64 = 100.0;
+
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub id: usize,
+    pub name: String,
+    pub line_id: String,
+    /// `outbound` or `inbound`: vehicles run each route from its first point
+    /// to its last, so each direction is a route of its own
+    pub direction: String,
+    pub vehicle_type: VehicleType,
+    pub color: String,
+    /// Transport mode, e.g. `tube` or `bus`, which sets how the vehicles move
+    pub mode: String,
+    pub points: Vec<(f64, f64)>, // Vec of (lng, lat) coordinates
+    /// Metres along the route to each point
+    pub distances: Vec<f64>,
+    /// The stations called at, in order along the route
+    pub stops: Vec<Stop>,
+    /// Where the route runs round a loop, if it does, found by
+    /// [`Self::detect_loop`]
+    pub loop_span: Option<LoopSpan>,
+    /// ID of the undisrupted route this runs all or part of, once a
+    /// [`super::scenario::Scenario`] has disrupted the routes, so the parts
+    /// of a route turned short run on the same departures
+    pub part_of: Option<usize>,
+    /// Fraction of the line's timetabled running time a run takes, less
+    /// than 1 for a route turned short
+    pub timetable_share: f64,
+}
+
+impl Route {
+    /// Create a route through the given points, measuring the distance
+    /// along the ground to each
+    ///
+    /// Repeated points are dropped, so every segment has some length. The
+    /// route has no stops until they are added with [`Self::add_stops`].
+    pub fn new(
+        id: usize,
+        name: String,
+        line_id: String,
+        direction: String,
+        color: String,
+        mode: String,
+        mut points: Vec<(f64, f64)>,
+    ) -> Self {
+        points.dedup();
+        let mut distances = Vec::with_capacity(points.len());
+        let mut travelled = 0.0;
+        for (index, point) in points.iter().enumerate() {
+            if index > 0 {
+                let previous = points[index - 1];
+                travelled += haversine_distance([previous.0, previous.1], [point.0, point.1]);
+            }
+            distances.push(travelled);
+        }
+        Self {
+            id,
+            name,
+            line_id,
+            direction,
+            vehicle_type: VehicleType::for_mode(&mode),
+            color,
+            mode,
+            points,
+            distances,
+            stops: Vec::new(),
+            loop_span: None,
+            part_of: None,
+            timetable_share: 1.0,
+        }
+    }
+
+    /// Length of the route in metres
+    pub fn length(&self) -> f64 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+
+    /// Add the stations within [`MAX_STOP_OFFSET`] of the route as its stops,
+    /// placed where each is closest to it
+    ///
+    /// Takes `(station_id, name, [lng, lat])` for each station.
+    pub fn add_stops<'a>(
+        &mut self,
+        stations: impl IntoIterator<Item = (&'a str, &'a str, [f64; 2])>,
+    ) {
+        let line: Vec<[f64; 2]> = self.points.iter().map(|&(lng, lat)| [lng, lat]).collect();
+        for (station_id, name, point) in stations {
+            let Some(projection) = project_onto_line(point, &line) else {
+                continue;
+            };
+            if projection.offset <= MAX_STOP_OFFSET {
+                self.stops.push(Stop {
+                    station_id: station_id.to_string(),
+                    name: name.to_string(),
+                    distance: projection.distance_along,
+                });
+            }
+        }
+        self.stops.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    }
+
+    /// Find where the route runs round a loop, from the first station it
+    /// calls at twice
+    ///
+    /// A route drawn as a ring, with its ends together, is first run on round
+    /// to call at its first stop again, so its vehicles make the full circle.
+    pub fn detect_loop(&mut self) {
+        self.close_ring();
+
+        let repeat = (1..self.stops.len()).find_map(|end| {
+            self.stops[..end]
+                .iter()
+                .position(|stop| stop.station_id == self.stops[end].station_id)
+                .map(|start| (self.stops[start].distance, self.stops[end].distance))
+        });
+        self.loop_span = repeat.and_then(|(start, end)| {
+            let line: Vec<[f64; 2]> = self.points.iter().map(|&(lng, lat)| [lng, lat]).collect();
+            let ring = slice_line(&line, start, end);
+            // Twice the area enclosed, positive anticlockwise
+            let area: f64 = ring
+                .iter()
+                .zip(ring.iter().cycle().skip(1))
+                .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+                .sum();
+            let rail = match area {
+                area if area > 0.0 => Rail::Inner,
+                area if area < 0.0 => Rail::Outer,
+                _ => return None,
+            };
+            Some(LoopSpan { start, end, rail })
+        });
+    }
+
+    /// Carry a route drawn as a ring on from its end round to its first stop,
+    /// and call there again
+    fn close_ring(&mut self) {
+        let (Some(&first), Some(&last)) = (self.points.first(), self.points.last()) else {
+            return;
+        };
+        let Some(first_stop) = self.stops.first().cloned() else {
+            return;
+        };
+        let is_ring = self.points.len() > 2
+            && self.stops.len() > 1
+            && haversine_distance([first.0, first.1], [last.0, last.1]) <= RING_CLOSURE;
+        if !is_ring
+            || self.stops.last().map(|stop| &stop.station_id) == Some(&first_stop.station_id)
+        {
+            return;
+        }
+
+        let line: Vec<[f64; 2]> = self.points.iter().map(|&(lng, lat)| [lng, lat]).collect();
+        let mut travelled = self.length();
+        let mut previous = last;
+        for [lng, lat] in slice_line(&line, 0.0, first_stop.distance) {
+            travelled += haversine_distance([previous.0, previous.1], [lng, lat]);
+            if (lng, lat) != previous {
+                self.points.push((lng, lat));
+                self.distances.push(travelled);
+            }
+            previous = (lng, lat);
+        }
+        self.stops.push(Stop {
+            distance: travelled,
+            ..first_stop
+        });
+    }
+
+    /// Whether the route runs round a closed loop from its origin back to
+    /// it, so its vehicles can go on round again
+    pub fn is_closed_loop(&self) -> bool {
+        self.loop_span
+            .is_some_and(|span| span.start <= self.origin() && span.end >= self.destination())
+    }
+
+    /// The `(lng, lat)` position a distance along the route
+    pub fn point_at(&self, distance: f64) -> (f64, f64) {
+        let distance = distance.clamp(0.0, self.length());
+        let next = self
+            .distances
+            .partition_point(|&d| d < distance)
+            .clamp(1, self.points.len() - 1);
+        let (start, end) = (self.distances[next - 1], self.distances[next]);
+        let fraction = if end > start {
+            (distance - start) / (end - start)
+        } else {
+            0.0
+        };
+        let (last_lng, last_lat) = self.points[next - 1];
+        let (next_lng, next_lat) = self.points[next];
+        (
+            last_lng + (next_lng - last_lng) * fraction,
+            last_lat + (next_lat - last_lat) * fraction,
+        )
+    }
+
+    /// The first stop strictly beyond a distance along the route
+    pub fn stop_ahead(&self, distance: f64) -> Option<&Stop> {
+        self.stops.iter().find(|stop| stop.distance > distance)
+    }
+
+    /// Where vehicles start their run: the first stop, or the start of a
+    /// route with fewer than two stops
+    pub fn origin(&self) -> f64 {
+        match self.stops.as_slice() {
+            [first, _, ..] => first.distance,
+            _ => 0.0,
+        }
+    }
+
+    /// Where vehicles end their run: the last stop, or the end of a route
+    /// with fewer than two stops
+    pub fn destination(&self) -> f64 {
+        match self.stops.as_slice() {
+            [.., _, last] => last.distance,
+            _ => self.length(),
+        }
+    }
+
+    /// Seconds a run takes at a top speed, stopping at each stop on the way
+    pub fn running_time(&self, profile: &ModeProfile, top_speed: f64) -> f64 {
+        let (origin, destination) = (self.origin(), self.destination());
+        let mut calls = vec![origin];
+        calls.extend(
+            self.stops
+                .iter()
+                .map(|stop| stop.distance)
+                .filter(|&distance| distance > origin && distance < destination),
+        );
+        calls.push(destination);
+        let dwells = calls.len().saturating_sub(2) as f64 * profile.dwell_secs;
+        let moving: f64 = calls
+            .windows(2)
+            .map(|pair| {
+                let gap = pair[1] - pair[0];
+                let (a, b) = (profile.acceleration, profile.deceleration);
+                // Distance to reach the top speed and stop again
+                let ramps = top_speed * top_speed * (1.0 / (2.0 * a) + 1.0 / (2.0 * b));
+                if gap >= ramps {
+                    gap / top_speed + top_speed / (2.0 * a) + top_speed / (2.0 * b)
+                } else {
+                    let peak = (2.0 * gap * a * b / (a + b)).sqrt();
+                    peak / a + peak / b
+                }
+            })
+            .sum();
+        moving + dwells
+    }
+
+    /// The top speed that makes a run take about `running_secs`, within
+    /// reason: from a quarter to twice the mode's usual top speed
+    pub fn top_speed_for(&self, profile: &ModeProfile, running_secs: f64) -> f64 {
+        let (mut low, mut high) = (profile.max_speed / 4.0, profile.max_speed * 2.0);
+        for _ in 0..30 {
+            let middle = (low + high) / 2.0;
+            if self.running_time(profile, middle) > running_secs {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        (low + high) / 2.0
+    }
+}
+
+/// How a mode's vehicles move and call at stops
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModeProfile {
+    /// Top speed in metres per second
+    pub max_speed: f64,
+    /// Metres per second squared
+    pub acceleration: f64,
+    /// Metres per second squared
+    pub deceleration: f64,
+    /// Seconds spent at each stop
+    pub dwell_secs: f64,
+}
+
+impl ModeProfile {
+    /// Typical figures for a mode's vehicles
+    pub fn for_mode(mode: &str) -> Self {
+        let (km_per_hour, acceleration, deceleration, dwell_secs) = match mode {
+            "bus" => (30.0, 1.0, 1.2, 20.0),
+            "tram" => (50.0, 1.0, 1.2, 20.0),
+            "cable-car" => (22.0, 0.5, 0.5, 30.0),
+            "dlr" => (80.0, 1.0, 1.0, 25.0),
+            "tube" => (80.0, 1.2, 1.1, 30.0),
+            "overground" => (100.0, 0.9, 0.9, 40.0),
+            "elizabeth-line" => (140.0, 1.0, 1.0, 45.0),
+            "national-rail" => (160.0, 0.8, 0.8, 60.0),
+            _ => (80.0, 1.0, 1.0, 30.0),
+        };
+        Self {
+            max_speed: km_per_hour / 3.6,
+            acceleration,
+            deceleration,
+            dwell_secs,
+        }
+    }
+}
+
+/// The [`ModeProfile`] for each mode, with any changes made to the typical
+/// figures
+#[derive(Clone, Debug, Default)]
+pub struct ModeProfiles {
+    overrides: HashMap<String, ModeProfile>,
+}
+
+impl ModeProfiles {
+    pub fn get(&self, mode: &str) -> ModeProfile {
+        self.overrides
+            .get(mode)
+            .copied()
+            .unwrap_or_else(|| ModeProfile::for_mode(mode))
+    }
+
+    /// Change how long a mode's vehicles wait at each stop
+    pub fn set_dwell_secs(&mut self, mode: &str, dwell_secs: f64) {
+        let mut profile = self.get(mode);
+        profile.dwell_secs = dwell_secs.max(0.0);
+        self.overrides.insert(mode.to_string(), profile);
+    }
+}
+
+/// A sample line's route each way, calling at every one of its points
+fn sample_routes(line_id: &str, mode: &str, stations: &[(f64, f64, &str)]) -> [Route; 2] {
+    let route = |direction: &str, stations: Vec<(f64, f64, &str)>| {
+        let mut route = Route::new(
+            0,
+            format!("{} {} (segment 0)", line_id, direction),
+            line_id.to_string(),
+            direction.to_string(),
+            get_line_color(line_id),
+            mode.to_string(),
+            stations.iter().map(|&(lng, lat, _)| (lng, lat)).collect(),
+        );
+        // The sample stations have no IDs, so go by their names
+        route.add_stops(
+            stations
+                .iter()
+                .map(|&(lng, lat, name)| (name, name, [lng, lat])),
+        );
+        route
+    };
+    let outbound = stations.to_vec();
+    let inbound = stations.iter().rev().copied().collect();
+    [route("outbound", outbound), route("inbound", inbound)]
+}
+
+/// Create sample routes based on TfL network
+pub fn build_sample_routes() -> Vec<Route> {
+    // Create a vector to hold the routes
+    let mut routes = Vec::new();
+
+    // Central Line (simplified)
+    routes.extend(sample_routes(
+        "central",
+        "tube",
+        &[
+            // West to East: Longitude, Latitude, Station
+            (-0.2810, 51.5170, "West Ruislip"),
+            (-0.2528, 51.5113, "Ruislip Gardens"),
+            (-0.2194, 51.5136, "South Ruislip"),
+            (-0.1987, 51.5202, "Northolt"),
+            (-0.1652, 51.5259, "Greenford"),
+            (-0.1350, 51.5210, "Perivale"),
+            (-0.0997, 51.5152, "Hanger Lane"),
+            (-0.0638, 51.5165, "North Acton"),
+            (-0.0362, 51.5111, "East Acton"),
+            (-0.0244, 51.5043, "White City"),
+            (-0.0048, 51.5035, "Shepherd's Bush"),
+            (-0.0125, 51.5009, "Holland Park"),
+            (-0.0199, 51.4996, "Notting Hill Gate"),
+            (-0.0457, 51.5068, "Queensway"),
+            (-0.0742, 51.5113, "Lancaster Gate"),
+            (-0.0983, 51.5142, "Marble Arch"),
+            (-0.1280, 51.5151, "Bond Street"),
+            (-0.1410, 51.5154, "Oxford Circus"),
+            (-0.1687, 51.5174, "Tottenham Court Road"),
+            (-0.1889, 51.5206, "Holborn"),
+            (-0.1205, 51.5152, "Chancery Lane"),
+            (-0.1025, 51.5168, "St. Paul's"),
+            (-0.0911, 51.5155, "Bank"),
+            (-0.0765, 51.5108, "Liverpool Street"),
+        ],
+    ));
+
+    // Northern Line (simplified)
+    routes.extend(sample_routes(
+        "northern",
+        "tube",
+        &[
+            // North to South
+            (-0.1938, 51.6503, "High Barnet"),
+            (-0.1932, 51.6302, "Totteridge & Whetstone"),
+            (-0.1858, 51.6179, "Woodside Park"),
+            (-0.1750, 51.6071, "West Finchley"),
+            (-0.1647, 51.5998, "Finchley Central"),
+            (-0.1534, 51.5874, "East Finchley"),
+            (-0.1419, 51.5775, "Highgate"),
+            (-0.1303, 51.5717, "Archway"),
+            (-0.1123, 51.5656, "Tufnell Park"),
+            (-0.1051, 51.5545, "Kentish Town"),
+            (-0.1426, 51.5302, "Camden Town"),
+            (-0.1385, 51.5248, "Mornington Crescent"),
+            (-0.1343, 51.5287, "Euston"),
+            (-0.1304, 51.5295, "King's Cross St. Pancras"),
+            (-0.1231, 51.5203, "Angel"),
+            (-0.1065, 51.5121, "Old Street"),
+            (-0.0882, 51.5176, "Moorgate"),
+            (-0.0911, 51.5155, "Bank"),
+            (-0.0924, 51.5113, "London Bridge"),
+            (-0.1002, 51.5044, "Borough"),
+            (-0.1052, 51.4944, "Elephant & Castle"),
+        ],
+    ));
+
+    // Bus route (sample)
+    routes.extend(sample_routes(
+        "88",
+        "bus",
+        &[
+            // West to East (Camden to Canning Town)
+            (-0.1465, 51.5365, "Camden Town"),
+            (-0.1325, 51.5300, "St Pancras"),
+            (-0.1155, 51.5235, "Farringdon"),
+            (-0.0958, 51.5181, "Barbican"),
+            (-0.0879, 51.5155, "Moorgate"),
+            (-0.0825, 51.5127, "Liverpool Street"),
+            (-0.0754, 51.5101, "Aldgate"),
+            (-0.0650, 51.5088, "Aldgate East"),
+            (-0.0550, 51.5070, "Whitechapel"),
+            (-0.0449, 51.5055, "Stepney Green"),
+            (-0.0349, 51.5040, "Mile End"),
+            (-0.0250, 51.5025, "Bow Road"),
+            (-0.0150, 51.5010, "Bow Church"),
+            (-0.0050, 51.4995, "Devons Road"),
+            (0.0050, 51.4980, "Langdon Park"),
+            (0.0150, 51.4965, "All Saints"),
+            (0.0250, 51.4950, "Poplar"),
+            (0.0350, 51.4935, "Blackwall"),
+            (0.0450, 51.4920, "East India"),
+            (0.0550, 51.4905, "Canning Town"),
+        ],
+    ));
+
+    for (id, route) in routes.iter_mut().enumerate() {
+        route.id = id;
+    }
+    routes
+}
+
+/// Every run through a direction's branches, as the branches in order: from
+/// each branch that nothing leads into, following each branch that leads on
+/// from it, to one that leads nowhere new
+///
+/// Branch IDs can repeat where a response covers several groups of
+/// services (as Thameslink's does), so a branch only leads on to one that
+/// starts where it ends. A branch leading back into itself or one already
+/// run through, as on the Circle line, ends the run there.
+fn branch_workings(branches: &[StopPointSequence]) -> Vec<Vec<&StopPointSequence>> {
+    let mut by_id: HashMap<i32, Vec<&StopPointSequence>> = HashMap::new();
+    for branch in branches {
+        by_id.entry(branch.branch_id).or_default().push(branch);
+    }
+    let stop_id = |stop: Option<&MatchedStop>| stop.and_then(|stop| stop.id.clone());
+    let leads_on = |from: &StopPointSequence, to: &StopPointSequence| match (
+        stop_id(from.stop_point.last()),
+        stop_id(to.stop_point.first()),
+    ) {
+        (Some(end), Some(start)) => end == start,
+        _ => true,
+    };
+
+    let mut unfinished: Vec<Vec<&StopPointSequence>> = branches
+        .iter()
+        .filter(|branch| {
+            branch
+                .prev_branch_ids
+                .iter()
+                .all(|id| *id == branch.branch_id || !by_id.contains_key(id))
+        })
+        .rev()
+        .map(|branch| vec![branch])
+        .collect();
+    let mut workings = Vec::new();
+    while let Some(working) = unfinished.pop() {
+        let last = *working.last().expect("every working has a branch");
+        let next: Vec<_> = last
+            .next_branch_ids
+            .iter()
+            .filter_map(|id| by_id.get(id))
+            .flatten()
+            .filter(|branch| leads_on(last, branch))
+            .filter(|branch| !working.iter().any(|run| std::ptr::eq(*run, **branch)))
+            .collect();
+        if next.is_empty() {
+            workings.push(working);
+            continue;
+        }
+        for branch in next.into_iter().rev() {
+            let mut longer = working.clone();
+            longer.push(*branch);
+            unfinished.push(longer);
+        }
+    }
+    workings
+}
+
+/// The `(station_id, name, [lng, lat])` of a stop on a branch: the station
+/// it belongs to where there is one, or the stop itself, placed where the
+/// stop is, as a hub's position is between all of its stations
+fn branch_station<'a>(
+    tfl_data: &'a TflDataRepository,
+    stop: &'a MatchedStop,
+) -> Option<(&'a str, &'a str, [f64; 2])> {
+    let ids = [&stop.top_most_parent_id, &stop.station_id, &stop.id];
+    let station = ids
+        .into_iter()
+        .flatten()
+        .find_map(|id| tfl_data.station_by_id.get(id));
+    let position = stop.lon.zip(stop.lat).map(|(lng, lat)| [lng, lat]);
+    match station {
+        Some(station) => Some((
+            station.station_unique_id.as_str(),
+            station.station_name.as_str(),
+            position.unwrap_or([station.lon, station.lat]),
+        )),
+        None => Some((stop.id.as_deref()?, stop.name.as_deref()?, position?)),
+    }
+}
+
+/// The stretch of a line from one point to another, beyond `after` metres
+/// along it, if the line passes both in that order (or either way if
+/// `either_way`), with its length and how far along the line it ends
+fn stretch_between(
+    line: &[[f64; 2]],
+    after: f64,
+    from: [f64; 2],
+    to: [f64; 2],
+    either_way: bool,
+) -> Option<(f64, f64, Vec<[f64; 2]>)> {
+    // A line that passes a station twice, round a loop, is searched from
+    // where the route has got to on it
+    let rest = slice_line(line, after, f64::INFINITY);
+    let start = project_onto_line(from, &rest)?;
+    let end = project_onto_line(to, &rest)?;
+    if start.offset > MAX_STOP_OFFSET || end.offset > MAX_STOP_OFFSET {
+        return None;
+    }
+    let (start, end) = (start.distance_along, end.distance_along);
+    if start < end {
+        Some((end - start, after + end, slice_line(&rest, start, end)))
+    } else if either_way && end < start {
+        let mut stretch = slice_line(&rest, end, start);
+        stretch.reverse();
+        Some((start - end, after + start, stretch))
+    } else {
+        None
+    }
+}
+
+/// The track from one station to the next: the shortest stretch of the
+/// line's own track that passes both in that order, beyond the `progress`
+/// made along each, or of any track of the same mode where it has none, or
+/// else a straight line
+fn leg_between(
+    lines: &[Vec<[f64; 2]>],
+    progress: &mut [f64],
+    shared: &[Vec<[f64; 2]>],
+    from: [f64; 2],
+    to: [f64; 2],
+) -> Vec<[f64; 2]> {
+    let own: Vec<_> = lines
+        .iter()
+        .zip(progress.iter_mut())
+        .filter_map(|(line, after)| {
+            let (length, reached, stretch) = stretch_between(line, *after, from, to, false)?;
+            *after = reached;
+            Some((length, stretch))
+        })
+        .collect();
+    let shortest = |stretches: Vec<(f64, Vec<[f64; 2]>)>| {
+        stretches
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, stretch)| stretch)
+    };
+    shortest(own)
+        .or_else(|| {
+            let any_way = shared
+                .iter()
+                .filter_map(|line| stretch_between(line, 0.0, from, to, true))
+                .map(|(length, _, stretch)| (length, stretch))
+                .collect();
+            shortest(any_way)
+        })
+        .unwrap_or_else(|| vec![from, to])
+}
+
+/// The points of a route calling at each station of a working in turn,
+/// along the track between them, with the stops it makes
+fn working_track(
+    stations: &[(&str, &str, [f64; 2])],
+    lines: &[Vec<[f64; 2]>],
+    shared: &[Vec<[f64; 2]>],
+) -> (Vec<(f64, f64)>, Vec<Stop>) {
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    let mut stops = Vec::with_capacity(stations.len());
+    let mut travelled = 0.0;
+    let mut progress = vec![0.0; lines.len()];
+    for (index, &(station_id, station_name, point)) in stations.iter().enumerate() {
+        let leg = match index {
+            0 => vec![point],
+            _ => leg_between(lines, &mut progress, shared, stations[index - 1].2, point),
+        };
+        for [lng, lat] in leg {
+            if let Some(&(last_lng, last_lat)) = points.last() {
+                travelled += haversine_distance([last_lng, last_lat], [lng, lat]);
+            }
+            points.push((lng, lat));
+        }
+        stops.push(Stop {
+            station_id: station_id.to_string(),
+            name: station_name.to_string(),
+            distance: travelled,
+        });
+    }
+    (points, stops)
+}
+
+/// Build actual routes from TfL data repository
+///
+/// Where a line's route sequences list its branches, each origin to
+/// destination working across them is a route; otherwise each of a
+/// direction's distinct line strings is.
+pub fn build_routes_from_tfl_data(tfl_data: &TflDataRepository) -> Vec<Route> {
+    let mut routes = Vec::new();
+
+    // The stations on each line, and the bus stops on each bus route
+    let mut stations_by_line: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for platforms in tfl_data.platforms_by_station.values() {
+        for platform in platforms {
+            stations_by_line
+                .entry(platform.line.as_str())
+                .or_default()
+                .insert(platform.station_unique_id.as_str());
+        }
+    }
+    let mut bus_stops_by_route: HashMap<&str, Vec<&crate::data::model::BusStop>> = HashMap::new();
+    for stop in tfl_data.bus_stops() {
+        for route in &stop.routes {
+            bus_stops_by_route
+                .entry(route.as_str())
+                .or_default()
+                .push(stop);
+        }
+    }
+
+    // The track of every line with branches, by mode, to lay the routes
+    // along where a line's own track is missing
+    let mut track_by_mode: HashMap<String, Vec<Vec<[f64; 2]>>> = HashMap::new();
+    let sequences = tfl_data
+        .routes
+        .values()
+        .flat_map(|directions| directions.values());
+    for sequence in sequences.flatten() {
+        if !sequence.stop_point_sequences.is_empty() {
+            track_by_mode
+                .entry(sequence.mode.to_lowercase())
+                .or_default()
+                .extend(
+                    sequence
+                        .line_strings
+                        .iter()
+                        .filter_map(|line_string| parse_line_string(line_string).ok()),
+                );
+        }
+    }
+
+    // Process each line's route sequences in a steady order, taking each
+    // direction's distinct line strings as routes
+    let mut line_ids: Vec<&String> = tfl_data.routes.keys().collect();
+    line_ids.sort();
+    for line_id in line_ids {
+        let line_stations: Vec<(&str, &str, [f64; 2])> = stations_by_line
+            .get(line_id.as_str())
+            .into_iter()
+            .flatten()
+            .filter_map(|id| tfl_data.station_by_id.get(*id))
+            .map(|station| {
+                (
+                    station.station_unique_id.as_str(),
+                    station.station_name.as_str(),
+                    [station.lon, station.lat],
+                )
+            })
+            .chain(
+                bus_stops_by_route
+                    .get(line_id.as_str())
+                    .into_iter()
+                    .flatten()
+                    .map(|stop| (stop.id.as_str(), stop.name.as_str(), [stop.lon, stop.lat])),
+            )
+            .collect();
+
+        let mut directions: Vec<_> = tfl_data.routes[line_id].iter().collect();
+        directions.sort_by_key(|(direction, _)| direction.as_str());
+        for (direction, sequences) in directions {
+            let mut seen = HashSet::new();
+            for sequence in sequences {
+                let route_mode = sequence.mode.to_lowercase();
+                if !sequence.stop_point_sequences.is_empty() {
+                    let lines: Vec<Vec<[f64; 2]>> = sequence
+                        .line_strings
+                        .iter()
+                        .filter_map(|line_string| parse_line_string(line_string).ok())
+                        .collect();
+                    let workings = branch_workings(&sequence.stop_point_sequences);
+                    let mut patterns = HashSet::new();
+                    for (index, working) in workings.into_iter().enumerate() {
+                        // Each branch starts where the one before it ends
+                        let mut stations: Vec<(&str, &str, [f64; 2])> = Vec::new();
+                        for stop in working.iter().flat_map(|branch| &branch.stop_point) {
+                            let Some(station) = branch_station(tfl_data, stop) else {
+                                continue;
+                            };
+                            if stations.last().is_none_or(|last| last.0 != station.0) {
+                                stations.push(station);
+                            }
+                        }
+                        let [(_, origin, _), .., (_, destination, _)] = stations.as_slice() else {
+                            continue;
+                        };
+                        // Branches repeated in the response give the same working twice
+                        let pattern: Vec<&str> = stations.iter().map(|station| station.0).collect();
+                        if !patterns.insert(pattern) {
+                            continue;
+                        }
+
+                        let name = format!(
+                            "{} {} {} → {} (working {})",
+                            line_id, direction, origin, destination, index
+                        );
+                        let shared = track_by_mode
+                            .get(&route_mode)
+                            .map_or(&[][..], Vec::as_slice);
+                        let (points, stops) = working_track(&stations, &lines, shared);
+                        let mut route = Route::new(
+                            routes.len(),
+                            name,
+                            line_id.clone(),
+                            direction.clone(),
+                            tfl_data.line_color(line_id),
+                            route_mode.clone(),
+                            points,
+                        );
+                        route.stops = stops;
+                        route.detect_loop();
+                        routes.push(route);
+                    }
+                    continue;
+                }
+
+                for line_string in &sequence.line_strings {
+                    if !seen.insert(line_string.as_str()) {
+                        continue;
+                    }
+                    let Ok(coordinates) = parse_line_string(line_string) else {
+                        continue;
+                    };
+
+                    // Create a route from this geometry
+                    let mut route = Route::new(
+                        routes.len(),
+                        format!("{} {} (segment {})", line_id, direction, seen.len() - 1),
+                        line_id.clone(),
+                        direction.clone(),
+                        tfl_data.line_color(line_id),
+                        route_mode.clone(),
+                        coordinates.iter().map(|&[lng, lat]| (lng, lat)).collect(),
+                    );
+
+                    // Skip segments with too few distinct coordinates
+                    if route.points.len() < 2 {
+                        continue;
+                    }
+                    route.add_stops(line_stations.iter().copied());
+                    route.detect_loop();
+                    routes.push(route);
+                }
+            }
+        }
+    }
+
+    for route in &mut routes {
+        if let Some(span) = route.loop_span {
+            route.name = format!("{} on the {} rail", route.name, span.rail.as_str());
+        }
+    }
+
+    debug_with_category(
+        LogCategory::Simulation,
+        &format!("Built {} routes from the TfL data", routes.len()),
+    );
+
+    // If no routes were created, fall back to sample routes
+    if routes.is_empty() {
+        warn_with_category(
+            LogCategory::Simulation,
+            "No valid routes found in TfL data, using sample routes",
+        );
+        return build_sample_routes();
+    }
+
+    routes
+}
+
+impl Vehicle {
+    /// A vehicle setting off from the start of a route's run
+    pub fn dispatch(id: usize, route: &Route, max_speed: f64) -> Self {
+        let distance = route.origin();
+        let (lng, lat) = route.point_at(distance);
+        Self {
+            id,
+            vehicle_type: route.vehicle_type.clone(),
+            route_index: route.id,
+            line_id: route.line_id.clone(),
+            distance,
+            speed: 0.0,
+            max_speed,
+            dwell_remaining: 0.0,
+            dwell_factor: 1.0,
+            departed_at: 0.0,
+            sections: 0..0,
+            held: false,
+            last_station: route
+                .stops
+                .iter()
+                .find(|stop| stop.distance == distance)
+                .map(|stop| stop.station_id.clone()),
+            next_station: route
+                .stop_ahead(distance)
+                .map(|stop| stop.station_id.clone()),
+            lng,
+            lat,
+        }
+    }
+
+    /// Set off round a closed loop again from its origin, where the vehicle
+    /// has just arrived, after calling there
+    pub fn start_lap(&mut self, route: &Route, profile: &ModeProfile, time: f64) {
+        let origin = route.stops.first();
+        self.distance = route.origin();
+        self.speed = 0.0;
+        self.dwell_remaining = profile.dwell_secs * self.dwell_factor;
+        self.departed_at = time;
+        self.last_station = origin.map(|stop| stop.station_id.clone());
+        self.next_station = route
+            .stop_ahead(self.distance)
+            .map(|stop| stop.station_id.clone());
+        (self.lng, self.lat) = route.point_at(self.distance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::error::parse_json;
+    use crate::data::model::{RouteSequence, StationsResponse};
+
+    #[test]
+    fn runs_each_working_across_the_branches() {
+        // A trunk from A to B, then a fork to C or D, with the track laid
+        // along the trunk and each fork
+        let stations: StationsResponse = parse_json(
+            "stations.json",
+            r#"{"success":true,"results":[
+                {"StationUniqueId":"A","StationName":"Alpha","FareZones":"1","OutsideStationUniqueId":"","Lat":51.50,"Lon":-0.10},
+                {"StationUniqueId":"B","StationName":"Bravo","FareZones":"1","OutsideStationUniqueId":"","Lat":51.51,"Lon":-0.10},
+                {"StationUniqueId":"C","StationName":"Charlie","FareZones":"1","OutsideStationUniqueId":"","Lat":51.52,"Lon":-0.11},
+                {"StationUniqueId":"D","StationName":"Delta","FareZones":"1","OutsideStationUniqueId":"","Lat":51.52,"Lon":-0.09}
+            ]}"#,
+        )
+        .unwrap();
+        let sequence: RouteSequence = parse_json(
+            "rail_routes.json",
+            r#"{"LineId":"test","Direction":"outbound","Mode":"tube","LineStrings":[
+                "[[[-0.10,51.50],[-0.10,51.51],[-0.11,51.52]]]",
+                "[[[-0.10,51.50],[-0.10,51.51],[-0.09,51.52]]]"],
+               "StopPointSequences":[
+                {"LineId":"test","LineName":"Test","Direction":"outbound","BranchId":0,
+                 "NextBranchIds":[1,2],"StopPoint":[{"Id":"A"},{"Id":"B"}]},
+                {"LineId":"test","LineName":"Test","Direction":"outbound","BranchId":1,
+                 "PrevBranchIds":[0],"StopPoint":[{"Id":"B"},{"Id":"C"}]},
+                {"LineId":"test","LineName":"Test","Direction":"outbound","BranchId":2,
+                 "PrevBranchIds":[0],"StopPoint":[{"Id":"B"},{"Id":"D"},
+                  {"Id":"E","Name":"Echo","Lat":51.53,"Lon":-0.09}]}]}"#,
+        )
+        .unwrap();
+        let routes = [(
+            "test".to_string(),
+            [("outbound".to_string(), vec![sequence])].into(),
+        )]
+        .into();
+        let tfl_data = TflDataRepository::from_datasets(stations.results, Vec::new(), routes);
+
+        let routes = build_routes_from_tfl_data(&tfl_data);
+        fn calls(route: &Route) -> Vec<&str> {
+            route.stops.iter().map(|stop| stop.name.as_str()).collect()
+        }
+        assert_eq!(routes.len(), 2);
+        assert_eq!(calls(&routes[0]), ["Alpha", "Bravo", "Charlie"]);
+        assert_eq!(calls(&routes[1]), ["Alpha", "Bravo", "Delta", "Echo"]);
+        assert_eq!(routes[1].name, "test outbound Alpha → Echo (working 1)");
+
+        // Each stop is where the route passes its station, along the track
+        for route in &routes {
+            for stop in &route.stops {
+                let station = tfl_data.station_by_id.get(&stop.station_id);
+                if let Some(station) = station {
+                    let (lng, lat) = route.point_at(stop.distance);
+                    assert!(haversine_distance([lng, lat], [station.lon, station.lat]) < 1.0);
+                }
+            }
+        }
+        // The last leg has no track, so runs straight
+        assert_eq!(routes[1].points.last(), Some(&(-0.09, 51.53)));
+    }
+
+    #[test]
+    fn finds_loops_and_which_rail_they_run_on() {
+        // A square drawn as a ring, clockwise from its south-west corner
+        let ring = vec![
+            (0.0, 51.5),
+            (0.0, 51.51),
+            (0.016, 51.51),
+            (0.016, 51.5),
+            (0.0, 51.5),
+        ];
+        let corners = [
+            ("A", "A", [0.0, 51.5]),
+            ("B", "B", [0.0, 51.51]),
+            ("C", "C", [0.016, 51.51]),
+            ("D", "D", [0.016, 51.5]),
+        ];
+        let route_round = |points: Vec<(f64, f64)>| {
+            let mut route = Route::new(
+                0,
+                "ring".to_string(),
+                "test".to_string(),
+                "outbound".to_string(),
+                "#000000".to_string(),
+                "bus".to_string(),
+                points,
+            );
+            route.add_stops(corners);
+            route.detect_loop();
+            route
+        };
+
+        // The run goes on round to call at its first stop again
+        let clockwise = route_round(ring.clone());
+        assert_eq!(clockwise.stops.len(), 5);
+        assert_eq!(clockwise.stops[4].station_id, "A");
+        assert!((clockwise.stops[4].distance - clockwise.length()).abs() < 1e-6);
+        assert!(clockwise.is_closed_loop());
+        assert_eq!(clockwise.loop_span.unwrap().rail, Rail::Outer);
+
+        let anticlockwise = route_round(ring.into_iter().rev().collect());
+        assert_eq!(anticlockwise.loop_span.unwrap().rail, Rail::Inner);
+
+        // A lasso: along a tail to B, round the loop and back to B
+        let mut lasso = route_round(vec![
+            (0.0, 51.49),
+            (0.0, 51.51),
+            (0.016, 51.51),
+            (0.016, 51.5),
+            (0.0, 51.5),
+        ]);
+        lasso.stops = ["T", "A", "B", "C", "D", "A"]
+            .iter()
+            .zip([0.0, 1_000.0, 2_000.0, 3_000.0, 4_000.0, 5_000.0])
+            .map(|(id, distance)| Stop {
+                station_id: id.to_string(),
+                name: id.to_string(),
+                distance,
+            })
+            .collect();
+        lasso.detect_loop();
+        let span = lasso.loop_span.unwrap();
+        assert_eq!((span.start, span.end), (1_000.0, 5_000.0));
+        assert!(!lasso.is_closed_loop());
+    }
+
+    #[test]
+    fn ends_a_working_where_it_loops_back() {
+        let branch = |id, prev: &str, next: &str| -> StopPointSequence {
+            parse_json(
+                "branch",
+                &format!(
+                    r#"{{"LineId":"circle","LineName":"Circle","Direction":"inbound",
+                        "BranchId":{},"PrevBranchIds":{},"NextBranchIds":{}}}"#,
+                    id, prev, next
+                ),
+            )
+            .unwrap()
+        };
+        // The Circle line: round the loop, then off along the tail
+        let branches = [branch(1, "[1]", "[1,0]"), branch(0, "[1]", "[]")];
+        let workings = branch_workings(&branches);
+        let ids: Vec<Vec<i32>> = workings
+            .iter()
+            .map(|working| working.iter().map(|branch| branch.branch_id).collect())
+            .collect();
+        assert_eq!(ids, [[1, 0]]);
+    }
+}
@@ -0,0 +1,342 @@
+//! Disruption scenarios, for rehearsing incidents.
+//!
+//! A scenario closes stations, suspends sections of line and runs lines on a
+//! reduced service. Trains run through a closed station without calling. A
+//! route through a suspended section is turned short either side of it, at
+//! the nearest station where its trains can turn back: a terminus of one of
+//! the line's workings, or a crossover the scenario names. Stations between
+//! there and the suspension go unserved. A line on a reduced service runs a
+//! fraction of its usual trains, at headways stretched to match. Scenarios
+//! are saved and loaded as JSON.
+
+use super::model::{Route, VehicleType};
+use crate::data::error::{DataError, parse_json};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+
+/// The disruptions a simulation runs with
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: String,
+    /// IDs of the stations closed
+    pub closed_stations: Vec<String>,
+    pub suspended_sections: Vec<Suspension>,
+    pub reduced_services: Vec<ReducedService>,
+    /// IDs of stations with a crossover trains can turn back at, besides
+    /// the termini of each line's workings
+    pub crossovers: Vec<String>,
+}
+
+/// A line suspended between two of its stations
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Suspension {
+    pub line_id: String,
+    /// IDs of the stations at either end, in either order
+    pub from: String,
+    pub to: String,
+}
+
+/// A line running fewer trains than usual
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReducedService {
+    pub line_id: String,
+    /// Fraction of the usual trains run, from 0 for none to 1 for all
+    pub service_level: f64,
+}
+
+/// A line's stations, for choosing what a scenario disrupts
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineStations {
+    pub line_id: String,
+    /// `(station_id, name)` for each station, by name
+    pub stations: Vec<(String, String)>,
+}
+
+impl Scenario {
+    /// Read a scenario saved as JSON
+    pub fn from_json(file: &str, text: &str) -> Result<Self, DataError> {
+        parse_json(file, text)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a scenario always serializes")
+    }
+
+    /// Whether the scenario disrupts nothing
+    pub fn is_empty(&self) -> bool {
+        self.closed_stations.is_empty()
+            && self.suspended_sections.is_empty()
+            && self.reduced_services.is_empty()
+    }
+
+    /// Fraction of a line's usual service run
+    pub fn service_level(&self, line_id: &str) -> f64 {
+        self.reduced_services
+            .iter()
+            .filter(|reduced| reduced.line_id == line_id)
+            .map(|reduced| reduced.service_level.clamp(0.0, 1.0))
+            .fold(1.0, f64::min)
+    }
+
+    /// The routes as the scenario leaves them, without the stops at closed
+    /// stations and turned short either side of the suspended sections,
+    /// numbered afresh
+    pub fn disrupt(&self, routes: Vec<Route>) -> Vec<Route> {
+        if self.closed_stations.is_empty() && self.suspended_sections.is_empty() {
+            return routes;
+        }
+        let closed: HashSet<&str> = self.closed_stations.iter().map(String::as_str).collect();
+
+        // Each line's trains can turn back at the ends of its workings
+        let mut turn_backs: HashMap<String, HashSet<String>> = HashMap::new();
+        for route in &routes {
+            let ends = [route.stops.first(), route.stops.last()];
+            turn_backs.entry(route.line_id.clone()).or_default().extend(
+                ends.into_iter()
+                    .flatten()
+                    .map(|stop| stop.station_id.clone()),
+            );
+        }
+
+        let mut disrupted = Vec::new();
+        for route in routes {
+            let can_turn_back = |index: usize| {
+                let station_id = route.stops[index].station_id.as_str();
+                !closed.contains(station_id)
+                    && (turn_backs[&route.line_id].contains(station_id)
+                        || self.crossovers.iter().any(|id| id == station_id))
+            };
+            let parts = self.turned_short(&route, can_turn_back);
+            let whole = parts.len() == 1 && parts[0] == (0..route.stops.len());
+            let (origin, destination) = (route.origin(), route.destination());
+
+            for part in parts {
+                let mut part_route = route.clone();
+                part_route.id = disrupted.len();
+                part_route.part_of = Some(route.id);
+                part_route.stops = route.stops[part]
+                    .iter()
+                    .filter(|stop| !closed.contains(stop.station_id.as_str()))
+                    .cloned()
+                    .collect();
+                let [first, .., last] = part_route.stops.as_slice() else {
+                    continue;
+                };
+                if !whole {
+                    part_route.name = format!(
+                        "{}, turned short {} → {}",
+                        route.name, first.name, last.name
+                    );
+                    part_route.timetable_share =
+                        (last.distance - first.distance) / (destination - origin);
+                }
+                let (first, last) = (first.distance, last.distance);
+                part_route.loop_span = route
+                    .loop_span
+                    .filter(|span| span.start >= first && span.end <= last);
+                disrupted.push(part_route);
+            }
+        }
+        disrupted
+    }
+
+    /// The ranges of a route's stops its trains still run, turned back
+    /// short of each suspended section it runs through
+    fn turned_short(
+        &self,
+        route: &Route,
+        can_turn_back: impl Fn(usize) -> bool,
+    ) -> Vec<Range<usize>> {
+        let mut parts = vec![Range {
+            start: 0,
+            end: route.stops.len(),
+        }];
+        let suspensions = self
+            .suspended_sections
+            .iter()
+            .filter(|suspension| suspension.line_id == route.line_id);
+        for suspension in suspensions {
+            let position = |part: &Range<usize>, station_id: &str| {
+                part.clone()
+                    .find(|&index| route.stops[index].station_id == station_id)
+            };
+            parts = parts
+                .into_iter()
+                .flat_map(|part| {
+                    let ends = (
+                        position(&part, &suspension.from),
+                        position(&part, &suspension.to),
+                    );
+                    let (Some(from), Some(to)) = ends else {
+                        return vec![part];
+                    };
+                    let (start, end) = (from.min(to), from.max(to));
+                    let before = (part.start..=start)
+                        .rev()
+                        .find(|&index| can_turn_back(index));
+                    let after = (end..part.end).find(|&index| can_turn_back(index));
+                    let mut split = Vec::new();
+                    if let Some(before) = before {
+                        split.push(part.start..before + 1);
+                    }
+                    if let Some(after) = after {
+                        split.push(after..part.end);
+                    }
+                    split
+                })
+                .collect();
+        }
+        parts
+    }
+}
+
+impl LineStations {
+    /// The stations each line's trains call at, by line ID, leaving out the
+    /// buses
+    pub fn from_routes(routes: &[Route]) -> Vec<Self> {
+        let mut lines: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+        for route in routes {
+            if matches!(route.vehicle_type, VehicleType::Bus) {
+                continue;
+            }
+            let stations = lines.entry(&route.line_id).or_default();
+            for stop in &route.stops {
+                stations.insert(&stop.station_id, &stop.name);
+            }
+        }
+        lines
+            .into_iter()
+            .map(|(line_id, stations)| {
+                let mut stations: Vec<(String, String)> = stations
+                    .into_iter()
+                    .map(|(id, name)| (id.to_string(), name.to_string()))
+                    .collect();
+                stations.sort_by(|a, b| a.1.cmp(&b.1));
+                Self {
+                    line_id: line_id.to_string(),
+                    stations,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::service::build_services;
+
+    /// A line from A to E, with a short working from A to C
+    fn line() -> Vec<Route> {
+        let stations = [
+            ("A", "Alpha", [0.0, 51.5]),
+            ("B", "Bravo", [0.01, 51.5]),
+            ("C", "Charlie", [0.02, 51.5]),
+            ("D", "Delta", [0.03, 51.5]),
+            ("E", "Echo", [0.04, 51.5]),
+        ];
+        [5, 3]
+            .into_iter()
+            .enumerate()
+            .map(|(id, count)| {
+                let mut route = Route::new(
+                    id,
+                    format!("test to {}", stations[count - 1].1),
+                    "test".to_string(),
+                    "outbound".to_string(),
+                    "#000000".to_string(),
+                    "tube".to_string(),
+                    stations[..count]
+                        .iter()
+                        .map(|(_, _, [lng, lat])| (*lng, *lat))
+                        .collect(),
+                );
+                route.add_stops(stations[..count].iter().copied());
+                route
+            })
+            .collect()
+    }
+
+    fn calls(route: &Route) -> Vec<&str> {
+        route
+            .stops
+            .iter()
+            .map(|stop| stop.station_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn skips_closed_stations() {
+        let scenario = Scenario {
+            closed_stations: vec!["B".to_string()],
+            ..Default::default()
+        };
+        let routes = scenario.disrupt(line());
+        assert_eq!(calls(&routes[0]), ["A", "C", "D", "E"]);
+        assert_eq!(calls(&routes[1]), ["A", "C"]);
+        assert_eq!(routes[0].timetable_share, 1.0);
+    }
+
+    #[test]
+    fn turns_trains_short_either_side_of_a_suspension() {
+        let suspend = |from: &str, to: &str| Scenario {
+            suspended_sections: vec![Suspension {
+                line_id: "test".to_string(),
+                from: from.to_string(),
+                to: to.to_string(),
+            }],
+            ..Default::default()
+        };
+
+        // Trains turn back at C, where the short working ends, and at the
+        // far terminus; D has no crossover, so goes unserved
+        let scenario = suspend("E", "D");
+        let routes = scenario.disrupt(line());
+        let parts: Vec<Vec<&str>> = routes.iter().map(calls).collect();
+        assert_eq!(parts, [vec!["A", "B", "C"], vec!["A", "B", "C"]]);
+        assert!((routes[0].timetable_share - 0.5).abs() < 0.01);
+        assert_eq!(routes[0].name, "test to Echo, turned short Alpha → Charlie");
+
+        // Without a crossover at B, nothing runs west of a suspension
+        // between B and C; with one, trains turn back there too, and both
+        // parts of a route split in the middle run on the same departures
+        let routes = suspend("B", "C").disrupt(line());
+        let parts: Vec<Vec<&str>> = routes.iter().map(calls).collect();
+        assert_eq!(parts, [vec!["C", "D", "E"]]);
+        let scenario = Scenario {
+            crossovers: vec!["B".to_string()],
+            ..suspend("B", "C")
+        };
+        let routes = scenario.disrupt(line());
+        let parts: Vec<Vec<&str>> = routes.iter().map(calls).collect();
+        assert_eq!(parts, [vec!["A", "B"], vec!["C", "D", "E"], vec!["A", "B"]]);
+        let services = build_services(&routes);
+        assert_eq!(services[0].routes, [vec![0, 1], vec![2]]);
+    }
+
+    #[test]
+    fn reads_back_what_it_saves() {
+        let scenario = Scenario {
+            name: "Charlie closed".to_string(),
+            closed_stations: vec!["C".to_string()],
+            reduced_services: vec![ReducedService {
+                line_id: "test".to_string(),
+                service_level: 0.5,
+            }],
+            ..Default::default()
+        };
+        let text = scenario.to_json();
+        assert_eq!(
+            Scenario::from_json("scenario.json", &text).unwrap(),
+            scenario
+        );
+        assert_eq!(scenario.service_level("test"), 0.5);
+        assert_eq!(scenario.service_level("other"), 1.0);
+
+        // Anything left out is undisrupted
+        let partial = Scenario::from_json("scenario.json", r#"{"name": "Quiet"}"#).unwrap();
+        assert!(partial.is_empty());
+    }
+}
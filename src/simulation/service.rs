@@ -0,0 +1,97 @@
+//! Dispatching vehicles by the lines' service patterns, see
+//! [`crate::data::services`].
+//!
+//! Each line runs a service in each direction, with a departure due every
+//! headway of the band it is in. Departures take the direction's routes in
+//! turn, so a line with branches sends its vehicles down each in rotation.
+//! A route a scenario turns short either side of a suspension runs as its
+//! parts, each departure sending a vehicle along each part, and a line on a
+//! reduced service runs at longer headways.
+
+use super::clock;
+use super::model::Route;
+use crate::data::services::ServicePatterns;
+use std::collections::BTreeMap;
+
+/// A line's service in one direction
+#[derive(Clone, Debug)]
+pub struct Service {
+    pub line_id: String,
+    pub mode: String,
+    /// Indices of the routes run, taken in turn, with the parts of a route
+    /// turned short run together
+    pub routes: Vec<Vec<usize>>,
+    next_route: usize,
+    /// Fraction of the usual service run, stretching the headways
+    pub service_level: f64,
+    /// Simulated time of the next departure, or `None` while the line isn't
+    /// running
+    next_departure: Option<f64>,
+}
+
+/// A departure from the start of a route
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Departure {
+    pub route_index: usize,
+    /// Simulated time it sets off
+    pub time: f64,
+    /// Seconds the run should take, if the service says
+    pub running_secs: Option<f64>,
+}
+
+impl Service {
+    /// The departures due up to `time`, in order
+    pub fn due(&mut self, patterns: &ServicePatterns, time: f64) -> Vec<Departure> {
+        let band = patterns
+            .band_at(&self.line_id, &self.mode, clock::time_of_day(time))
+            .filter(|band| band.headway_mins > 0.0 && self.service_level > 0.0);
+        let Some(band) = band else {
+            self.next_departure = None;
+            return Vec::new();
+        };
+
+        let next_departure = self.next_departure.get_or_insert(time);
+        let mut departures = Vec::new();
+        while *next_departure <= time {
+            let parts = &self.routes[self.next_route % self.routes.len()];
+            departures.extend(parts.iter().map(|&route_index| Departure {
+                route_index,
+                time: *next_departure,
+                running_secs: band.running_mins.map(|minutes| minutes * 60.0),
+            }));
+            *next_departure += band.headway_mins * 60.0 / self.service_level;
+            self.next_route += 1;
+        }
+        departures
+    }
+}
+
+/// The services run on the routes, one per line and direction
+pub fn build_services(routes: &[Route]) -> Vec<Service> {
+    let mut services: BTreeMap<(&str, &str), Service> = BTreeMap::new();
+    let mut parts_run: BTreeMap<(&str, &str, usize), usize> = BTreeMap::new();
+    for route in routes {
+        let service = services
+            .entry((&route.line_id, &route.direction))
+            .or_insert_with(|| Service {
+                line_id: route.line_id.clone(),
+                mode: route.mode.clone(),
+                routes: Vec::new(),
+                next_route: 0,
+                service_level: 1.0,
+                next_departure: None,
+            });
+        let Some(part_of) = route.part_of else {
+            service.routes.push(vec![route.id]);
+            continue;
+        };
+        let turn = *parts_run
+            .entry((&route.line_id, &route.direction, part_of))
+            .or_insert_with(|| {
+                service.routes.push(Vec::new());
+                service.routes.len() - 1
+            });
+        service.routes[turn].push(route.id);
+    }
+    services.into_values().collect()
+}
@@ -0,0 +1,247 @@
+//! Fixed block signalling, keeping trains apart.
+//!
+//! The track from each station to the next is divided into blocks of at most
+//! [`BLOCK_LENGTH`], one train at a time in each. A train reserves the
+//! blocks ahead of it as far as it could run and still stop, and keeps each
+//! until it has passed out of it, so a train standing at a platform keeps
+//! the block behind it too. With the block ahead taken, a train brakes for
+//! the signal at its start and is held there, and the trains behind queue
+//! up in turn. Lines running between the same stations along the same
+//! corridor share its blocks, so a train held on one line holds up the
+//! others behind it. Buses run on the road and cable cars on their own rope,
+//! and neither is signalled.
+
+use super::model::{Route, Vehicle, VehicleType};
+use crate::utils::geo::haversine_distance;
+use std::collections::{HashMap, HashSet};
+
+/// Longest a block runs for in metres, with longer stretches between
+/// stations divided into blocks of equal length
+pub const BLOCK_LENGTH: f64 = 500.0;
+
+/// How close in metres the middles of two routes' legs between the same
+/// stations have to be for them to run along the same corridor
+const CORRIDOR_TOLERANCE: f64 = 100.0;
+
+/// The blocks along each corridor between a pair of stations: the first
+/// block, how many there are, and where the corridor's middle is
+type Corridor = Vec<(usize, usize, [f64; 2])>;
+
+/// A stretch of a route inside one block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Section {
+    /// Metres along the route the stretch starts and ends
+    pub start: f64,
+    pub end: f64,
+    pub block: usize,
+}
+
+/// The blocks every route runs through, and the train in each
+#[derive(Clone, Debug, Default)]
+pub struct Signalling {
+    /// Each route's sections in order from its first stop to its last, by
+    /// route index, with none for routes that aren't signalled
+    sections: Vec<Vec<Section>>,
+    /// ID of the vehicle in each block
+    occupied: Vec<Option<usize>>,
+    /// How many blocks more than one line runs through
+    shared_blocks: usize,
+}
+
+impl Signalling {
+    /// Divide the routes' track into blocks, the same blocks for every route
+    /// along a corridor between a pair of stations
+    pub fn new(routes: &[Route]) -> Self {
+        let mut corridors: HashMap<(&str, &str), Corridor> = HashMap::new();
+        let mut block_lines: Vec<HashSet<&str>> = Vec::new();
+        let sections = routes
+            .iter()
+            .map(|route| {
+                if matches!(route.vehicle_type, VehicleType::Bus) || route.mode == "cable-car" {
+                    return Vec::new();
+                }
+                let mut sections = Vec::new();
+                for pair in route.stops.windows(2) {
+                    let (start, end) = (pair[0].distance, pair[1].distance);
+                    let (lng, lat) = route.point_at((start + end) / 2.0);
+                    let corridor = corridors
+                        .entry((&pair[0].station_id, &pair[1].station_id))
+                        .or_default();
+                    let shared = corridor.iter().find(|(_, _, middle)| {
+                        haversine_distance(*middle, [lng, lat]) <= CORRIDOR_TOLERANCE
+                    });
+                    let (first, count) = match shared {
+                        Some(&(first, count, _)) => (first, count),
+                        None => {
+                            let count = ((end - start) / BLOCK_LENGTH).ceil().max(1.0) as usize;
+                            corridor.push((block_lines.len(), count, [lng, lat]));
+                            let first = block_lines.len();
+                            block_lines.resize_with(first + count, HashSet::new);
+                            (first, count)
+                        }
+                    };
+                    let length = (end - start) / count as f64;
+                    for i in 0..count {
+                        block_lines[first + i].insert(&route.line_id);
+                        sections.push(Section {
+                            start: start + length * i as f64,
+                            end: if i + 1 == count {
+                                end
+                            } else {
+                                start + length * (i + 1) as f64
+                            },
+                            block: first + i,
+                        });
+                    }
+                }
+                sections
+            })
+            .collect();
+
+        Self {
+            sections,
+            occupied: vec![None; block_lines.len()],
+            shared_blocks: block_lines.iter().filter(|lines| lines.len() > 1).count(),
+        }
+    }
+
+    /// A route's sections from its first stop to its last, none if it
+    /// isn't signalled
+    pub fn sections(&self, route_index: usize) -> &[Section] {
+        self.sections.get(route_index).map_or(&[], Vec::as_slice)
+    }
+
+    /// Clear the blocks a vehicle has passed out of, and reserve those ahead
+    /// of it that are clear until they reach `reach` metres along its route,
+    /// returning how far along it the vehicle may go
+    pub fn authorise(&mut self, vehicle: &mut Vehicle, reach: f64) -> f64 {
+        let sections = match self.sections.get(vehicle.route_index) {
+            Some(sections) if !sections.is_empty() => sections,
+            _ => return f64::INFINITY,
+        };
+        let held = &mut vehicle.sections;
+        while held.start < held.end && sections[held.start].end < vehicle.distance {
+            let block = sections[held.start].block;
+            if self.occupied[block] == Some(vehicle.id) {
+                self.occupied[block] = None;
+            }
+            held.start += 1;
+        }
+
+        let authority = |end: usize| match end {
+            0 => vehicle.distance,
+            end => sections[end - 1].end.max(vehicle.distance),
+        };
+        while held.end < sections.len() && authority(held.end) < reach {
+            let block = sections[held.end].block;
+            if self.occupied[block].is_some_and(|id| id != vehicle.id) {
+                break;
+            }
+            self.occupied[block] = Some(vehicle.id);
+            held.end += 1;
+        }
+        match held.end == sections.len() {
+            true => f64::INFINITY,
+            false if held.start == held.end => vehicle.distance,
+            false => authority(held.end),
+        }
+    }
+
+    /// Clear every block a vehicle holds, as it leaves its route
+    pub fn release(&mut self, vehicle: &mut Vehicle) {
+        if let Some(sections) = self.sections.get(vehicle.route_index) {
+            for section in &sections[vehicle.sections.clone()] {
+                if self.occupied[section.block] == Some(vehicle.id) {
+                    self.occupied[section.block] = None;
+                }
+            }
+        }
+        vehicle.sections = 0..0;
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.occupied.len()
+    }
+
+    /// How many blocks are shared by more than one line
+    pub fn shared_block_count(&self) -> usize {
+        self.shared_blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(id: usize, line_id: &str) -> Route {
+        let mut route = Route::new(
+            id,
+            format!("{line_id} eastbound"),
+            line_id.to_string(),
+            "outbound".to_string(),
+            "#000000".to_string(),
+            "tube".to_string(),
+            vec![(0.0, 51.5), (0.0288, 51.5)],
+        );
+        route.add_stops([
+            ("A", "A", [0.0, 51.5]),
+            ("B", "B", [0.0144, 51.5]),
+            ("C", "C", [0.0288, 51.5]),
+        ]);
+        route
+    }
+
+    #[test]
+    fn shares_blocks_along_a_corridor() {
+        let signalling = Signalling::new(&[route(0, "one"), route(1, "two")]);
+
+        // About 1 km from each station to the next, in blocks of 500 m
+        let sections = signalling.sections(0);
+        assert_eq!(sections.len(), 4);
+        assert!(
+            sections
+                .iter()
+                .all(|section| section.end - section.start <= BLOCK_LENGTH)
+        );
+        assert_eq!(sections, signalling.sections(1));
+        assert_eq!(signalling.block_count(), 4);
+        assert_eq!(signalling.shared_block_count(), 4);
+    }
+
+    #[test]
+    fn reserves_only_the_clear_blocks_ahead() {
+        let routes = [route(0, "one")];
+        let mut signalling = Signalling::new(&routes);
+        let mut ahead = Vehicle::dispatch(0, &routes[0], 20.0);
+        let mut behind = Vehicle::dispatch(1, &routes[0], 20.0);
+        let sections = signalling.sections(0).to_vec();
+
+        // The train ahead takes the first two blocks, so the one behind
+        // can't leave the platform
+        ahead.distance = sections[0].end;
+        assert_eq!(
+            signalling.authorise(&mut ahead, sections[1].end),
+            sections[1].end
+        );
+        assert_eq!(
+            signalling.authorise(&mut behind, sections[2].end),
+            behind.distance
+        );
+
+        // Once it's out of the first block, the one behind may follow to
+        // the signal at the start of the next
+        ahead.distance = sections[1].start + 1.0;
+        signalling.authorise(&mut ahead, sections[1].end);
+        assert_eq!(
+            signalling.authorise(&mut behind, sections[2].end),
+            sections[0].end
+        );
+
+        // And as it leaves the route, the rest clear
+        signalling.release(&mut ahead);
+        assert_eq!(
+            signalling.authorise(&mut behind, f64::INFINITY),
+            f64::INFINITY
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! What happened over a run of the simulation, for the headless runner's
+//! report.
+
+use super::Movement;
+use super::model::Vehicle;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Totals over the network since the start time
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Statistics {
+    /// Simulated seconds run
+    pub simulated_secs: f64,
+    /// Vehicles sent off on a run
+    pub departures: usize,
+    /// Runs finished at the end of their route
+    pub completed_runs: usize,
+    /// Calls made at stations along the way
+    pub stop_calls: usize,
+    /// Kilometres run by all the vehicles
+    pub distance_km: f64,
+    /// Most vehicles running at once
+    pub peak_vehicles: usize,
+    /// Minutes trains spent held at signals for the block ahead to clear
+    pub held_mins: f64,
+    /// The same by line ID
+    pub lines: BTreeMap<String, LineStatistics>,
+}
+
+/// Totals for one line
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LineStatistics {
+    pub departures: usize,
+    pub completed_runs: usize,
+    pub stop_calls: usize,
+    pub distance_km: f64,
+    pub held_mins: f64,
+    /// Mean minutes taken by a completed run, or 0 with none completed
+    pub mean_run_mins: f64,
+    /// Seconds taken by all the completed runs, for their mean
+    #[serde(skip)]
+    run_secs: f64,
+}
+
+impl Statistics {
+    pub(super) fn record_departure(&mut self, vehicle: &Vehicle) {
+        self.departures += 1;
+        self.line(vehicle).departures += 1;
+    }
+
+    /// Count a vehicle's step of `metres`, made by `time`
+    pub(super) fn record(&mut self, vehicle: &Vehicle, movement: Movement, metres: f64, time: f64) {
+        self.distance_km += metres / 1000.0;
+        let line = self.line(vehicle);
+        line.distance_km += metres / 1000.0;
+        match movement {
+            Movement::Running | Movement::Held => {}
+            Movement::CalledAt => {
+                line.stop_calls += 1;
+                self.stop_calls += 1;
+            }
+            Movement::Finished => {
+                line.completed_runs += 1;
+                line.run_secs += time - vehicle.departed_at;
+                line.mean_run_mins = line.run_secs / line.completed_runs as f64 / 60.0;
+                self.completed_runs += 1;
+            }
+        }
+    }
+
+    /// Count `seconds` a vehicle spent held at a signal
+    pub(super) fn record_hold(&mut self, vehicle: &Vehicle, seconds: f64) {
+        self.held_mins += seconds / 60.0;
+        self.line(vehicle).held_mins += seconds / 60.0;
+    }
+
+    fn line(&mut self, vehicle: &Vehicle) -> &mut LineStatistics {
+        self.lines.entry(vehicle.line_id.clone()).or_default()
+    }
+}
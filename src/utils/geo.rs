@@ -0,0 +1,147 @@
+//! Distances between `[lon, lat]` points, and along lines made of them.
+
+/// Mean radius of the Earth in metres
+pub const EARTH_RADIUS_METRES: f64 = 6_371_000.0;
+
+/// Great-circle distance in metres between two `[lon, lat]` points
+pub fn haversine_distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    let [lon1, lat1] = a.map(f64::to_radians);
+    let [lon2, lat2] = b.map(f64::to_radians);
+
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METRES * h.sqrt().asin()
+}
+
+/// Length in metres of a line through `[lon, lat]` points
+pub fn line_length(line: &[[f64; 2]]) -> f64 {
+    line.windows(2)
+        .map(|pair| haversine_distance(pair[0], pair[1]))
+        .sum()
+}
+
+/// Where a point is closest to a line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Metres along the line from its start
+    pub distance_along: f64,
+    /// Metres from the point to the line
+    pub offset: f64,
+    /// The closest point on the line
+    pub point: [f64; 2],
+}
+
+/// Find where a point is closest to a line through `[lon, lat]` points
+///
+/// Each segment is treated as straight on a flat projection centred on the
+/// point, which is accurate to well under a metre over city distances.
+/// Returns `None` for a line with fewer than two points.
+pub fn project_onto_line(point: [f64; 2], line: &[[f64; 2]]) -> Option<Projection> {
+    let metres_per_degree = EARTH_RADIUS_METRES.to_radians();
+    let lon_scale = point[1].to_radians().cos() * metres_per_degree;
+    let to_local = |[lon, lat]: [f64; 2]| {
+        (
+            (lon - point[0]) * lon_scale,
+            (lat - point[1]) * metres_per_degree,
+        )
+    };
+
+    let mut best: Option<Projection> = None;
+    let mut distance_before = 0.0;
+    for pair in line.windows(2) {
+        let (ax, ay) = to_local(pair[0]);
+        let (bx, by) = to_local(pair[1]);
+        let (dx, dy) = (bx - ax, by - ay);
+        let length_squared = dx * dx + dy * dy;
+        let t = if length_squared > 0.0 {
+            (-(ax * dx + ay * dy) / length_squared).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let offset = (ax + t * dx).hypot(ay + t * dy);
+        let segment_length = haversine_distance(pair[0], pair[1]);
+
+        if best.is_none_or(|best| offset < best.offset) {
+            best = Some(Projection {
+                distance_along: distance_before + t * segment_length,
+                offset,
+                point: [
+                    pair[0][0] + t * (pair[1][0] - pair[0][0]),
+                    pair[0][1] + t * (pair[1][1] - pair[0][1]),
+                ],
+            });
+        }
+        distance_before += segment_length;
+    }
+    best
+}
+
+/// The part of a line between two distances along it, in metres from its
+/// start, with the ends placed between its points where they fall
+pub fn slice_line(line: &[[f64; 2]], from: f64, to: f64) -> Vec<[f64; 2]> {
+    let mut slice = Vec::new();
+    let mut distance_before = 0.0;
+    for pair in line.windows(2) {
+        let segment_length = haversine_distance(pair[0], pair[1]);
+        let distance_after = distance_before + segment_length;
+        let point_at = |distance: f64| {
+            let t = if segment_length > 0.0 {
+                ((distance - distance_before) / segment_length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            [
+                pair[0][0] + t * (pair[1][0] - pair[0][0]),
+                pair[0][1] + t * (pair[1][1] - pair[0][1]),
+            ]
+        };
+
+        if slice.is_empty() && from <= distance_after {
+            slice.push(point_at(from));
+        }
+        if !slice.is_empty() {
+            if to <= distance_after {
+                slice.push(point_at(to));
+                return slice;
+            }
+            slice.push(pair[1]);
+        }
+        distance_before = distance_after;
+    }
+    slice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projects_onto_the_nearest_segment() {
+        // East along 51.5°N, then north
+        let line = [[-0.1, 51.5], [-0.09, 51.5], [-0.09, 51.51]];
+        let first_leg = haversine_distance(line[0], line[1]);
+
+        let projection = project_onto_line([-0.095, 51.5001], &line).unwrap();
+        assert!((projection.distance_along - first_leg / 2.0).abs() < 1.0);
+        assert!((projection.offset - 11.1).abs() < 0.1);
+
+        // Past the end, the end is closest
+        let projection = project_onto_line([-0.09, 51.52], &line).unwrap();
+        assert!((projection.distance_along - line_length(&line)).abs() < 1e-6);
+        assert_eq!(projection.point, [-0.09, 51.51]);
+
+        assert_eq!(project_onto_line([0.0, 0.0], &line[..1]), None);
+    }
+
+    #[test]
+    fn slices_between_distances_along() {
+        let line = [[-0.1, 51.5], [-0.09, 51.5], [-0.09, 51.51]];
+        let first_leg = haversine_distance(line[0], line[1]);
+
+        let slice = slice_line(&line, first_leg / 2.0, first_leg + 1.0);
+        assert_eq!(slice.len(), 3);
+        assert!((slice[0][0] + 0.095).abs() < 1e-9);
+        assert_eq!(slice[1], line[1]);
+        assert!((line_length(&slice) - (first_leg / 2.0 + 1.0)).abs() < 1e-6);
+    }
+}
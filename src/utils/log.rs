@@ -1,5 +1,7 @@
 use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::JsValue;
+#[cfg(target_arch = "wasm32")]
 use web_sys::console;
 
 // Log levels
@@ -91,6 +93,7 @@ fn log_base(level: LogLevel, category: LogCategory, message: &str, source_info:
     };
 
     // Use the appropriate console method based on log level
+    #[cfg(target_arch = "wasm32")]
     match level {
         LogLevel::Debug => console::debug_1(&JsValue::from_str(&log_message)),
         LogLevel::Info => console::log_1(&JsValue::from_str(&log_message)),
@@ -98,6 +101,14 @@ fn log_base(level: LogLevel, category: LogCategory, message: &str, source_info:
         LogLevel::Error => console::error_1(&JsValue::from_str(&log_message)),
         LogLevel::None => unreachable!(),
     }
+
+    // Natively there is no console, so warnings and errors go to stderr
+    #[cfg(not(target_arch = "wasm32"))]
+    match level {
+        LogLevel::Debug | LogLevel::Info => println!("{}", log_message),
+        LogLevel::Warn | LogLevel::Error => eprintln!("{}", log_message),
+        LogLevel::None => unreachable!(),
+    }
 }
 
 // Debug log
@@ -0,0 +1,86 @@
+//! A small least-recently-used set, for caches that only need to know which
+//! keys to evict.
+
+use std::collections::VecDeque;
+
+/// A set of at most `capacity` keys that forgets the least recently used
+///
+/// Lookups are linear, which is fine for the few hundred keys it is used for.
+#[derive(Debug, Clone)]
+pub struct LruSet<T> {
+    capacity: usize,
+    /// Least recently used at the front
+    keys: VecDeque<T>,
+}
+
+impl<T: PartialEq> LruSet<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            keys: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn contains(&self, key: &T) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Mark a key as just used, returning whether it was in the set
+    pub fn touch(&mut self, key: &T) -> bool {
+        match self.keys.iter().position(|k| k == key) {
+            Some(index) => {
+                if let Some(key) = self.keys.remove(index) {
+                    self.keys.push_back(key);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add a key as the most recently used, returning the key evicted to make
+    /// room for it, if any
+    pub fn insert(&mut self, key: T) -> Option<T> {
+        if self.touch(&key) {
+            return None;
+        }
+
+        let evicted = if self.keys.len() >= self.capacity {
+            self.keys.pop_front()
+        } else {
+            None
+        };
+        self.keys.push_back(key);
+        evicted
+    }
+
+    /// Remove every key, least recently used first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.keys.drain(..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let mut lru = LruSet::new(2);
+        assert_eq!(lru.insert("a"), None);
+        assert_eq!(lru.insert("b"), None);
+        assert!(lru.touch(&"a"));
+        assert_eq!(lru.insert("c"), Some("b"));
+        assert!(lru.contains(&"a") && lru.contains(&"c"));
+        assert_eq!(lru.insert("c"), None);
+        assert_eq!(lru.len(), 2);
+    }
+}
@@ -1,5 +1,11 @@
+pub mod geo;
+#[cfg(target_arch = "wasm32")]
 pub mod geojson;
 pub mod log;
+pub mod lru;
+pub mod platform;
+pub mod rng;
+pub mod time;
 
 // Re-export commonly used logging functions to make them easier to import
 pub use log::{LogLevel, set_log_level};
@@ -0,0 +1,8 @@
+//! Small shims over the few things the core modules need from the platform,
+//! so they run both in the browser and natively (e.g. the `desktop` feature).
+
+/// A random number in `[0, 1)`
+#[cfg(target_arch = "wasm32")]
+pub fn random() -> f64 {
+    js_sys::Math::random()
+}
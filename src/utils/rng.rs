@@ -0,0 +1,76 @@
+//! A small seedable random number generator, so that anything driven by it
+//! can be replayed from its seed.
+
+/// SplitMix64: fast, any seed (including zero) gives a good sequence, and
+/// the same seed always gives the same one on every platform
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        // Use the top 53 bits for the mantissa
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number in `[low, high)`
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// A fresh seed, for when none is given, small enough to read out and type
+/// back in
+#[cfg(target_arch = "wasm32")]
+pub fn random_seed() -> u64 {
+    (super::platform::random() * f64::from(u32::MAX)) as u64
+}
+
+/// A fresh seed, for when none is given, small enough to read out and type
+/// back in: taken from the clock, as nothing else natively needs randomness
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64 % u64::from(u32::MAX))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_its_sequence_from_a_seed() {
+        let draw = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..100).map(|_| rng.next_f64()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(draw(42), draw(42));
+        assert_ne!(draw(42), draw(43));
+        assert!(draw(0).iter().all(|x| (0.0..1.0).contains(x)));
+    }
+}
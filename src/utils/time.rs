@@ -0,0 +1,63 @@
+//! Timestamps as seconds since the Unix epoch.
+
+/// The current time, in seconds since the Unix epoch
+#[cfg(target_arch = "wasm32")]
+pub fn now_seconds() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+/// The current time, in seconds since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_seconds() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+/// Days since the epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // From Howard Hinnant's `days_from_civil`
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parse a UTC timestamp such as `2025-04-04T10:58:23.565156Z`
+///
+/// Only the form the API uses is accepted: a date, `T`, a time with optional
+/// fractional seconds, then `Z`.
+pub fn parse_utc_timestamp(timestamp: &str) -> Option<f64> {
+    let (date, time) = timestamp.trim().strip_suffix('Z')?.split_once('T')?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut time = time.splitn(3, ':');
+    let hours: i64 = time.next()?.parse().ok()?;
+    let minutes: i64 = time.next()?.parse().ok()?;
+    let seconds: f64 = time.next()?.parse().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    Some((days * 86_400 + hours * 3_600 + minutes * 60) as f64 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_api_timestamps() {
+        assert_eq!(parse_utc_timestamp("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(
+            parse_utc_timestamp("2025-04-04T10:58:23.5Z"),
+            Some(1_743_764_303.5)
+        );
+        assert_eq!(parse_utc_timestamp("2025-04-04 10:58:23"), None);
+    }
+}